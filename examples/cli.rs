@@ -77,7 +77,7 @@ fn authorize<'a>() -> Client<'a> {
     let req_token = oauth::request_token(CONSUMER_KEY, CONSUMER_SECRET, "oob")
         .execute(&handler).unwrap().object;
 
-    write_and_flush(format_args!("Go to https://api.twitter.com/oauth/authorize?oauth_token={}\nPut PIN: ", req_token.oauth_token));
+    write_and_flush(format_args!("Go to {}\nPut PIN: ", req_token.authorize_url()));
 
     let mut pin = String::with_capacity(7);
     io::stdin().read_line(&mut pin).unwrap();
@@ -288,41 +288,11 @@ impl Command for CmdUploadVideo {
 
             let file_len = video_file.metadata()?.len();
 
-            let init_res = client.media().upload_init_command(file_len, "video/mp4")
+            let upload_res = client.media().upload_chunked(&mut video_file, file_len, "video/mp4")
                 .media_category("tweet_video")
                 .execute()?;
 
-            print!("\n{:?}\n\n", init_res);
-
-            {
-                const BUF_SIZE: usize = 5 * 1000 * 1000; // 5MB
-                let mut buf = Vec::with_capacity(BUF_SIZE);
-                unsafe { buf.set_len(BUF_SIZE); }
-
-                for segment_index in 0.. {
-                    let read_bytes = video_file.read(&mut buf)?;
-                    if read_bytes == 0 { break; }
-                    let mut buf_reader = io::Cursor::new(&buf[..read_bytes]);
-                    println!("Uploading {} bytes", read_bytes);
-                    client.media().upload_append_command(init_res.object.media_id, segment_index)
-                        .media(&mut buf_reader)
-                        .execute()?;
-                }
-            }
-
-            let finalize_res = client.media().upload_finalize_command(init_res.object.media_id).execute()?;
-            println!("\n{:?}", finalize_res);
-
-            if let Some(models::ProcessingInfo { mut check_after_secs, .. }) = finalize_res.object.processing_info {
-                while let Some(x) = check_after_secs {
-                    std::thread::sleep(std::time::Duration::from_secs(x as u64));
-
-                    let status_res = client.media().upload_status_command(init_res.object.media_id).execute()?;
-                    println!("\n{:?}", status_res);
-
-                    check_after_secs = status_res.object.processing_info.check_after_secs;
-                }
-            }
+            print!("\n{:?}\n\n", upload_res);
 
             write_and_flush(format_args!("\nTweet: "));
             let mut status = String::new();
@@ -332,7 +302,7 @@ impl Command for CmdUploadVideo {
                 "\n{:?}",
                 client.statuses()
                     .update(status)
-                    .media_ids(Some(init_res.object.media_id))
+                    .media_ids(Some(upload_res.object.media_id))
                     .execute()?
             );
 
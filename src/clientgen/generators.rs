@@ -9,12 +9,12 @@ use inflector::Inflector;
 pub fn twitter_client<W: Write>(writer: &mut W, input: &[parser::ApiTemplate]) -> io::Result<()> {
     try!(writer.write_all(b"\
 #[derive(Clone, Debug)]
-pub struct TwitterClient<A: Authenticator, H: HttpHandler> {
+pub struct TwitterClient<A: Authenticator, H> {
     auth: A,
     handler: H,
 }
 
-impl<A: Authenticator, H: HttpHandler> TwitterClient<A, H> {
+impl<A: Authenticator, H> TwitterClient<A, H> {
     pub fn new(authenticator: A, http_handler: H) -> TwitterClient<A, H> {
         TwitterClient {
             auth: authenticator,
@@ -327,7 +327,7 @@ fn client_struct<W: Write>(writer: &mut W, api_template: &parser::ApiTemplate) -
     write!(
         writer,
         "#[derive(Clone, Debug)]
-pub struct {}Client<'a, A: 'a + Authenticator, H: 'a + HttpHandler> {{
+pub struct {}Client<'a, A: 'a + Authenticator, H: 'a> {{
     client: &'a TwitterClient<A, H>
 }}
 ",
@@ -338,7 +338,7 @@ pub struct {}Client<'a, A: 'a + Authenticator, H: 'a + HttpHandler> {{
 fn client_impl<W: Write>(writer: &mut W, api_template: &parser::ApiTemplate, endpoints: &[Endpoint]) -> io::Result<()> {
     try!(write!(
         writer,
-        "\nimpl<'a, A: Authenticator, H: HttpHandler> {0}Client<'a, A, H> {{",
+        "\nimpl<'a, A: Authenticator, H> {0}Client<'a, A, H> {{",
         api_template.namespace
     ));
 
@@ -407,7 +407,7 @@ fn request_builder_struct<W: Write>(writer: &mut W, endpoint: &Endpoint, api_tem
     try!(write!(
         writer,
         "
-pub struct {}{}RequestBuilder<'a, A: 'a + Authenticator, H: 'a + HttpHandler> {{
+pub struct {}{}RequestBuilder<'a, A: 'a + Authenticator, H: 'a> {{
     _client: &'a TwitterClient<A, H>,
 ",
         api_template.namespace,
@@ -428,7 +428,7 @@ pub struct {}{}RequestBuilder<'a, A: 'a + Authenticator, H: 'a + HttpHandler> {{
 fn request_builder_impl<W: Write>(writer: &mut W, endpoint: &Endpoint, api_template: &parser::ApiTemplate) -> io::Result<()> {
     try!(write!(
         writer,
-        "\nimpl<'a, A: Authenticator, H: HttpHandler> {}{}RequestBuilder<'a, A, H> {{",
+        "\nimpl<'a, A: Authenticator, H> {}{}RequestBuilder<'a, A, H> {{",
         api_template.namespace,
         endpoint.name
     ));
@@ -437,8 +437,49 @@ fn request_builder_impl<W: Write>(writer: &mut W, endpoint: &Endpoint, api_templ
         try!(request_builder_setter(writer, n, t));
     }
 
+    try!(writer.write_all(b"}\n"));
+
+    try!(write!(
+        writer,
+        "\nimpl<'a, A: Authenticator, H: HttpHandler> {}{}RequestBuilder<'a, A, H> {{",
+        api_template.namespace,
+        endpoint.name
+    ));
     try!(request_builder_execute(writer, endpoint));
+    if is_cursored(endpoint) {
+        try!(request_builder_iter_pages(writer, endpoint));
+    }
+    if is_timeline(endpoint) {
+        try!(request_builder_timeline(writer, endpoint));
+    }
+    try!(writer.write_all(b"}\n"));
+
+    if is_cursored(endpoint) {
+        try!(write!(
+            writer,
+            "
+impl<'a, A: Authenticator, H: HttpHandler> IntoIterator for &'a mut {0}{1}RequestBuilder<'a, A, H> {{
+    type Item = Result<<{2} as CursorPage>::Item, TwitterError>;
+    type IntoIter = CursorItems<'a, A, H, {2}>;
 
+    fn into_iter(self) -> Self::IntoIter {{
+        self.iter_pages().items()
+    }}
+}}
+",
+            api_template.namespace,
+            endpoint.name,
+            endpoint.return_type
+        ));
+    }
+
+    try!(write!(
+        writer,
+        "\n#[cfg(feature = \"futures\")]\nimpl<'a, A: Authenticator, H: AsyncHttpHandler> {}{}RequestBuilder<'a, A, H> {{",
+        api_template.namespace,
+        endpoint.name
+    ));
+    try!(request_builder_execute_async(writer, endpoint));
     writer.write_all(b"}\n")
 }
 
@@ -476,6 +517,146 @@ fn request_builder_execute<W: Write>(writer: &mut W, endpoint: &Endpoint) -> io:
         endpoint.return_type
     ));
 
+    try!(request_builder_params(writer, endpoint));
+
+    if let &parser::EndpointType::Impl = endpoint.method {
+        try!(writeln!(
+            writer,
+            "        impls::{}_{}(self._client, params)",
+            endpoint.namespace.to_snake_case(),
+            endpoint.name.to_snake_case()
+        ));
+    } else {
+        let method = try!(request_builder_url(writer, endpoint));
+
+        if &endpoint.return_type == "()" {
+            writeln!(
+                writer,
+                "        execute_core_unit(self._client, {}, url, params)",
+                method
+            )?;
+        } else {
+            writeln!(
+                writer,
+                "        execute_core(self._client, {}, url, params)",
+                method
+            )?;
+        }
+    }
+
+    writer.write_all(b"    }\n")
+}
+
+fn request_builder_execute_async<W: Write>(writer: &mut W, endpoint: &Endpoint) -> io::Result<()> {
+    try!(write!(
+        writer,
+         "
+    /// The parameters are moved into the future, so it does not borrow the builder.
+    /// A file parameter is taken from the builder.
+    pub fn execute_async(&mut self) -> FutureTwitterResult<'a, {}> {{
+        ",
+        endpoint.return_type
+    ));
+
+    try!(request_builder_take_params(writer, endpoint));
+
+    if let &parser::EndpointType::Impl = endpoint.method {
+        try!(writeln!(
+            writer,
+            "        impls::{}_{}_async(self._client, params)",
+            endpoint.namespace.to_snake_case(),
+            endpoint.name.to_snake_case()
+        ));
+    } else {
+        let method = try!(request_builder_url(writer, endpoint));
+
+        if &endpoint.return_type == "()" {
+            writeln!(
+                writer,
+                "        execute_core_unit_async(self._client, {}, url, params)",
+                method
+            )?;
+        } else {
+            writeln!(
+                writer,
+                "        execute_core_async(self._client, {}, url, params)",
+                method
+            )?;
+        }
+    }
+
+    writer.write_all(b"    }\n")
+}
+
+/// Returns true if the endpoint returns `Cursor*` and has `cursor` parameter.
+fn is_cursored(endpoint: &Endpoint) -> bool {
+    endpoint.return_type.starts_with("Cursor")
+        && endpoint.method != &parser::EndpointType::Impl
+        && endpoint.optional_parameters.iter().any(|&(n, _)| n == "cursor")
+}
+
+fn request_builder_iter_pages<W: Write>(writer: &mut W, endpoint: &Endpoint) -> io::Result<()> {
+    try!(write!(
+        writer,
+         "
+    /// Returns the iterator which sends the request for each page until `next_cursor` becomes 0.
+    pub fn iter_pages(&'a mut self) -> CursorPages<'a, A, H, {}> {{
+        ",
+        endpoint.return_type
+    ));
+
+    try!(request_builder_params(writer, endpoint));
+    let method = try!(request_builder_url(writer, endpoint));
+    try!(writeln!(writer, "        CursorPages::new(self._client, {}, url, params)", method));
+
+    writer.write_all(b"    }\n")
+}
+
+/// Returns true if the endpoint returns tweets or direct messages and has `max_id` and `since_id` parameters.
+fn is_timeline(endpoint: &Endpoint) -> bool {
+    match endpoint.return_type.as_ref() {
+        "Vec<Tweet>" | "Vec<DirectMessage>" | "SearchResponse" => (),
+        _ => return false,
+    }
+
+    endpoint.method != &parser::EndpointType::Impl
+        && endpoint.optional_parameters.iter().any(|&(n, _)| n == "max_id")
+        && endpoint.optional_parameters.iter().any(|&(n, _)| n == "since_id")
+}
+
+fn request_builder_timeline<W: Write>(writer: &mut W, endpoint: &Endpoint) -> io::Result<()> {
+    try!(write!(
+        writer,
+         "
+    /// Returns the iterator which goes back the timeline by setting `max_id` to the oldest ID of the last page.
+    pub fn iter_timeline(&'a mut self) -> TimelinePages<'a, A, H, {}> {{
+        ",
+        endpoint.return_type
+    ));
+
+    try!(request_builder_params(writer, endpoint));
+    let method = try!(request_builder_url(writer, endpoint));
+    try!(writeln!(writer, "        TimelinePages::new(self._client, {}, url, params)", method));
+    try!(writer.write_all(b"    }\n"));
+
+    try!(write!(
+        writer,
+         "
+    /// Returns the poller which fetches the items newer than the last poll by `since_id`.
+    pub fn poll_timeline(&'a mut self) -> TimelinePoller<'a, A, H, {}> {{
+        ",
+        endpoint.return_type
+    ));
+
+    try!(request_builder_params(writer, endpoint));
+    let method = try!(request_builder_url(writer, endpoint));
+    try!(writeln!(writer, "        TimelinePoller::new(self._client, {}, url, params)", method));
+
+    writer.write_all(b"    }\n")
+}
+
+/// Writes the statements to make `params` from the fields of the builder.
+fn request_builder_params<W: Write>(writer: &mut W, endpoint: &Endpoint) -> io::Result<()> {
     let capacity = endpoint.required_parameters.len() + endpoint.optional_parameters.len()
         - if endpoint.reserved_parameter.is_some() { 1 } else { 0 };
 
@@ -499,50 +680,60 @@ fn request_builder_execute<W: Write>(writer: &mut W, endpoint: &Endpoint) -> io:
         ));
     }
 
-    if let &parser::EndpointType::Impl = endpoint.method {
+    Ok(())
+}
+
+/// Writes the statements to make `params` which do not borrow the builder.
+fn request_builder_take_params<W: Write>(writer: &mut W, endpoint: &Endpoint) -> io::Result<()> {
+    let capacity = endpoint.required_parameters.len() + endpoint.optional_parameters.len()
+        - if endpoint.reserved_parameter.is_some() { 1 } else { 0 };
+
+    if capacity > 0 { try!(writeln!(writer, "let mut params = Vec::with_capacity({});", capacity)) }
+    else { try!(writer.write_all(b"let params = Vec::<(Cow<str>, ParameterValue)>::new();\n")) }
+
+    for &(p, _) in endpoint.required_parameters.iter() {
+        if endpoint.reserved_parameter == Some(p) { continue; }
         try!(writeln!(
             writer,
-            "        impls::{}_{}(self._client, params)",
-            endpoint.namespace.to_snake_case(),
-            endpoint.name.to_snake_case()
+            "        params.push((Cow::Borrowed(\"{0}\"), self.{0}.take_parameter_value()));",
+            p
         ));
-    } else {
-        let (method, url) = match *endpoint.method {
-            parser::EndpointType::Get(ref x) => ("Get", x),
-            parser::EndpointType::Post(ref x) => ("Post", x),
-            _ => unreachable!(),
-        };
+    }
 
-        try!(writer.write_all(b"        let url = "));
+    for &(p, _) in endpoint.optional_parameters.iter() {
+        try!(writeln!(
+            writer,
+            "        if let Some(ref mut x) = self.{0} {{ params.push((Cow::Borrowed(\"{0}\"), x.take_parameter_value())) }}",
+            p
+        ));
+    }
 
-        if let Some(reserved) = endpoint.reserved_parameter {
-            try!(writeln!(
-                writer,
-                "format!(\"https://api.twitter.com/1.1/{}.json\", {1} = self.{1});",
-                url, reserved
-            ));
-        } else {
-            try!(writeln!(
-                writer,
-                "\"https://api.twitter.com/1.1/{}.json\";",
-                url
-            ));
-        }
+    Ok(())
+}
 
-        if &endpoint.return_type == "()" {
-            writeln!(
-                writer,
-                "        execute_core_unit(self._client, {}, url, params)",
-                method
-            )?;
-        } else {
-            writeln!(
-                writer,
-                "       execute_core(self._client, {}, url, params)",
-                method
-            )?;
-        }
+/// Writes the statement to make `url` and returns the name of the HTTP method.
+fn request_builder_url<W: Write>(writer: &mut W, endpoint: &Endpoint) -> io::Result<&'static str> {
+    let (method, url) = match *endpoint.method {
+        parser::EndpointType::Get(ref x) => ("Get", x),
+        parser::EndpointType::Post(ref x) => ("Post", x),
+        _ => unreachable!(),
+    };
+
+    try!(writer.write_all(b"        let url = "));
+
+    if let Some(reserved) = endpoint.reserved_parameter {
+        try!(writeln!(
+            writer,
+            "format!(\"https://api.twitter.com/1.1/{}.json\", {1} = self.{1});",
+            url, reserved
+        ));
+    } else {
+        try!(writeln!(
+            writer,
+            "\"https://api.twitter.com/1.1/{}.json\";",
+            url
+        ));
     }
 
-    writer.write_all(b"    }\n")
+    Ok(method)
 }
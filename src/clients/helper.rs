@@ -3,9 +3,17 @@ use std::fmt;
 use std::fmt::Write;
 use std::io;
 use hyper::method::Method;
-use ::TwitterResult;
+use hyper::status::StatusCode;
+use ::{TwitterError, TwitterResult};
 use conn::*;
-use models::TweetMode;
+use models::{RawResponse, TweetMode};
+
+#[cfg(feature = "futures")]
+use std::mem;
+#[cfg(feature = "futures")]
+use futures::{future, Future};
+#[cfg(feature = "futures")]
+use ::FutureTwitterResult;
 
 pub fn collection_paramter<I, D>(values: I) -> String
     where I: IntoIterator<Item=D>, D: fmt::Display
@@ -42,12 +50,73 @@ pub fn str_collection_parameter<I, S>(values: I) -> String
     dest
 }
 
+/// Sends the request, and resends it once if the authenticator could refresh the credentials after 401.
+/// The credentials are refreshed before sending if the authenticator says they have expired.
+fn send_request<A, H>(client: &super::TwitterClient<A, H>, req: Request) -> Result<RawResponse, TwitterError>
+    where A: Authenticator, H: HttpHandler
+{
+    let mut scheme = client.auth.create_authorization_header(&req);
+    if client.auth.needs_refresh() && try!(client.auth.refresh_credentials(scheme.as_ref(), &client.handler)) {
+        scheme = client.auth.create_authorization_header(&req);
+    }
+
+    let retry = req.try_clone();
+    match client.handler.send_request(req, &client.auth) {
+        Err(TwitterError::ErrorResponse(e)) => {
+            if e.status == StatusCode::Unauthorized {
+                if let Some(retry) = retry {
+                    if try!(client.auth.refresh_credentials(scheme.as_ref(), &client.handler)) {
+                        return client.handler.send_request(retry, &client.auth);
+                    }
+                }
+            }
+            Err(TwitterError::ErrorResponse(e))
+        }
+        x => x,
+    }
+}
+
+/// The non-blocking version of `send_request`.
+#[cfg(feature = "futures")]
+fn send_request_async<'a, A, H>(client: &'a super::TwitterClient<A, H>, req: Request<'a>) -> FutureRawResponse<'a>
+    where A: Authenticator, H: AsyncHttpHandler
+{
+    let auth = &client.auth;
+    let handler = &client.handler;
+    let scheme = auth.create_authorization_header(&req);
+    let refreshed = if auth.needs_refresh() {
+        auth.refresh_credentials_async(scheme.as_ref(), handler)
+    } else {
+        Box::new(future::ok(false))
+    };
+
+    Box::new(refreshed.and_then(move |refreshed| {
+        let scheme = if refreshed { auth.create_authorization_header(&req) } else { scheme };
+        let retry = req.try_clone();
+
+        handler.send_request_async(req, auth).or_else(move |e| -> FutureRawResponse<'a> {
+            let unauthorized = match e {
+                TwitterError::ErrorResponse(ref x) => x.status == StatusCode::Unauthorized,
+                _ => false,
+            };
+            match retry {
+                Some(retry) if unauthorized => Box::new(
+                    auth.refresh_credentials_async(scheme.as_ref(), handler).and_then(move |refreshed| -> FutureRawResponse<'a> {
+                        if refreshed { handler.send_request_async(retry, auth) } else { Box::new(future::err(e)) }
+                    })
+                ),
+                _ => Box::new(future::err(e)),
+            }
+        })
+    }))
+}
+
 pub fn execute_core<'a, A, H, U, R>(client: &super::TwitterClient<A, H>, method: Method,
     url: U, params: Vec<(Cow<'a, str>, ParameterValue<'a>)>) -> TwitterResult<R>
     where A: Authenticator, H: HttpHandler, U: AsRef<str>, R: ::serde::de::DeserializeOwned
 {
     let req = Request::new(method, url.as_ref(), RequestContent::from_name_value_pairs(params))?;
-    client.handler.send_request(req, &client.auth)?.parse_to_object()
+    send_request(client, req)?.parse_to_object()
 }
 
 pub fn execute_core_unit<'a, A, H, U>(client: &super::TwitterClient<A, H>, method: Method,
@@ -55,7 +124,48 @@ pub fn execute_core_unit<'a, A, H, U>(client: &super::TwitterClient<A, H>, metho
     where A: Authenticator, H: HttpHandler, U: AsRef<str>
 {
     let req = Request::new(method, url.as_ref(), RequestContent::from_name_value_pairs(params))?;
-    Ok(client.handler.send_request(req, &client.auth)?.into_twitter_response())
+    Ok(send_request(client, req)?.into_twitter_response())
+}
+
+#[cfg(feature = "futures")]
+pub fn execute_core_async<'a, A, H, U, R>(client: &'a super::TwitterClient<A, H>, method: Method,
+    url: U, params: Vec<(Cow<'a, str>, ParameterValue<'a>)>) -> FutureTwitterResult<'a, R>
+    where A: Authenticator, H: AsyncHttpHandler, U: AsRef<str>, R: ::serde::de::DeserializeOwned + 'a
+{
+    match Request::new(method, url.as_ref(), RequestContent::from_name_value_pairs(params)) {
+        Ok(req) => Box::new(send_request_async(client, req).and_then(|x| x.parse_to_object())),
+        Err(e) => Box::new(future::err(e)),
+    }
+}
+
+#[cfg(feature = "futures")]
+pub fn execute_core_unit_async<'a, A, H, U>(client: &'a super::TwitterClient<A, H>, method: Method,
+    url: U, params: Vec<(Cow<'a, str>, ParameterValue<'a>)>) -> FutureTwitterResult<'a, ()>
+    where A: Authenticator, H: AsyncHttpHandler, U: AsRef<str>
+{
+    match Request::new(method, url.as_ref(), RequestContent::from_name_value_pairs(params)) {
+        Ok(req) => Box::new(send_request_async(client, req).map(|x| x.into_twitter_response())),
+        Err(e) => Box::new(future::err(e)),
+    }
+}
+
+pub fn execute_json_unit<A, H, U>(client: &super::TwitterClient<A, H>, method: Method,
+    url: U, body: String) -> TwitterResult<()>
+    where A: Authenticator, H: HttpHandler, U: AsRef<str>
+{
+    let req = Request::new(method, url.as_ref(), RequestContent::Json(body))?;
+    Ok(send_request(client, req)?.into_twitter_response())
+}
+
+#[cfg(feature = "futures")]
+pub fn execute_json_unit_async<'a, A, H, U>(client: &'a super::TwitterClient<A, H>, method: Method,
+    url: U, body: String) -> FutureTwitterResult<'a, ()>
+    where A: Authenticator, H: AsyncHttpHandler, U: AsRef<str>
+{
+    match Request::new(method, url.as_ref(), RequestContent::Json(body)) {
+        Ok(req) => Box::new(send_request_async(client, req).map(|x| x.into_twitter_response())),
+        Err(e) => Box::new(future::err(e)),
+    }
 }
 
 pub trait ToParameterValue<'a> {
@@ -111,3 +221,69 @@ impl<'a> ToParameterValue<'a> for &'a mut io::Read {
         ParameterValue::File(self)
     }
 }
+
+/// Converts a field of a request builder to `ParameterValue` which does not borrow the builder,
+/// so that the future returned by `execute_async` can outlive it.
+#[cfg(feature = "futures")]
+pub trait TakeParameterValue<'a> {
+    fn take_parameter_value(&mut self) -> ParameterValue<'a>;
+}
+
+#[cfg(feature = "futures")]
+impl<'a> TakeParameterValue<'a> for bool {
+    fn take_parameter_value(&mut self) -> ParameterValue<'a> {
+        ParameterValue::Text(Cow::Borrowed(if *self { "true" } else { "false" }))
+    }
+}
+
+#[cfg(feature = "futures")]
+impl<'a> TakeParameterValue<'a> for TweetMode {
+    fn take_parameter_value(&mut self) -> ParameterValue<'a> {
+        ParameterValue::Text(Cow::Borrowed(match *self {
+            TweetMode::Compat => "compat",
+            TweetMode::Extended => "extended"
+        }))
+    }
+}
+
+#[cfg(feature = "futures")]
+impl<'a> TakeParameterValue<'a> for String {
+    fn take_parameter_value(&mut self) -> ParameterValue<'a> {
+        ParameterValue::Text(Cow::Owned(self.clone()))
+    }
+}
+
+#[cfg(feature = "futures")]
+impl<'a> TakeParameterValue<'a> for Cow<'a, str> {
+    fn take_parameter_value(&mut self) -> ParameterValue<'a> {
+        ParameterValue::Text(self.clone())
+    }
+}
+
+macro_rules! take_string_parameter {
+    ($t:ty) => (
+        #[cfg(feature = "futures")]
+        impl<'a> TakeParameterValue<'a> for $t {
+            fn take_parameter_value(&mut self) -> ParameterValue<'a> {
+                ParameterValue::Text(Cow::Owned(self.to_string()))
+            }
+        }
+    )
+}
+
+take_string_parameter!(i32);
+take_string_parameter!(u32);
+take_string_parameter!(i64);
+take_string_parameter!(u64);
+take_string_parameter!(f32);
+take_string_parameter!(f64);
+
+/// The reader is moved out and replaced with an empty one, since it cannot be read twice.
+#[cfg(feature = "futures")]
+impl<'a> TakeParameterValue<'a> for &'a mut io::Read {
+    fn take_parameter_value(&mut self) -> ParameterValue<'a> {
+        // io::Empty has no size, so leaking it allocates nothing
+        let empty: &'a mut io::Read = Box::leak(Box::new(io::empty()));
+        ParameterValue::File(mem::replace(self, empty))
+    }
+}
@@ -6,6 +6,9 @@ use ::TwitterResult;
 use conn::*;
 use models::*;
 
+#[cfg(feature = "futures")]
+use ::FutureTwitterResult;
+
 type Params<'a> = Vec<(Cow<'a, str>, ParameterValue<'a>)>;
 
 pub fn account_settings<'a, A, H>(client: &TwitterClient<A, H>, params: Params<'a>) -> TwitterResult<AccountSettings>
@@ -15,6 +18,14 @@ pub fn account_settings<'a, A, H>(client: &TwitterClient<A, H>, params: Params<'
     execute_core(client, method, "https://api.twitter.com/1.1/account/settings.json", params)
 }
 
+#[cfg(feature = "futures")]
+pub fn account_settings_async<'a, A, H>(client: &'a TwitterClient<A, H>, params: Params<'a>) -> FutureTwitterResult<'a, AccountSettings>
+    where A: Authenticator, H: AsyncHttpHandler
+{
+    let method = if params.is_empty() { Get } else { Post };
+    execute_core_async(client, method, "https://api.twitter.com/1.1/account/settings.json", params)
+}
+
 const MEDIA_UPLOAD_URL: &'static str = "https://upload.twitter.com/1.1/media/upload.json";
 
 pub fn media_upload<'a, A, H>(client: &TwitterClient<A, H>, params: Params<'a>) -> TwitterResult<MediaUploadResponse>
@@ -23,6 +34,13 @@ pub fn media_upload<'a, A, H>(client: &TwitterClient<A, H>, params: Params<'a>)
     execute_core(client, Post, MEDIA_UPLOAD_URL, params)
 }
 
+#[cfg(feature = "futures")]
+pub fn media_upload_async<'a, A, H>(client: &'a TwitterClient<A, H>, params: Params<'a>) -> FutureTwitterResult<'a, MediaUploadResponse>
+    where A: Authenticator, H: AsyncHttpHandler
+{
+    execute_core_async(client, Post, MEDIA_UPLOAD_URL, params)
+}
+
 pub fn media_upload_init_command<'a, A, H>(client: &TwitterClient<A, H>, mut params: Params<'a>) -> TwitterResult<UploadInitCommandResponse>
     where A: Authenticator, H: HttpHandler
 {
@@ -30,6 +48,14 @@ pub fn media_upload_init_command<'a, A, H>(client: &TwitterClient<A, H>, mut par
     execute_core(client, Post, MEDIA_UPLOAD_URL, params)
 }
 
+#[cfg(feature = "futures")]
+pub fn media_upload_init_command_async<'a, A, H>(client: &'a TwitterClient<A, H>, mut params: Params<'a>) -> FutureTwitterResult<'a, UploadInitCommandResponse>
+    where A: Authenticator, H: AsyncHttpHandler
+{
+    params.push((Cow::Borrowed("command"), ParameterValue::Text(Cow::Borrowed("INIT"))));
+    execute_core_async(client, Post, MEDIA_UPLOAD_URL, params)
+}
+
 pub fn media_upload_append_command<'a, A, H>(client: &TwitterClient<A, H>, mut params: Params<'a>) -> TwitterResult<()>
     where A: Authenticator, H: HttpHandler
 {
@@ -37,6 +63,14 @@ pub fn media_upload_append_command<'a, A, H>(client: &TwitterClient<A, H>, mut p
     execute_core_unit(client, Post, MEDIA_UPLOAD_URL, params)
 }
 
+#[cfg(feature = "futures")]
+pub fn media_upload_append_command_async<'a, A, H>(client: &'a TwitterClient<A, H>, mut params: Params<'a>) -> FutureTwitterResult<'a, ()>
+    where A: Authenticator, H: AsyncHttpHandler
+{
+    params.push((Cow::Borrowed("command"), ParameterValue::Text(Cow::Borrowed("APPEND"))));
+    execute_core_unit_async(client, Post, MEDIA_UPLOAD_URL, params)
+}
+
 pub fn media_upload_finalize_command<'a, A, H>(client: &TwitterClient<A, H>, mut params: Params<'a>) -> TwitterResult<UploadFinalizeCommandResponse>
     where A: Authenticator, H: HttpHandler
 {
@@ -44,9 +78,164 @@ pub fn media_upload_finalize_command<'a, A, H>(client: &TwitterClient<A, H>, mut
     execute_core(client, Post, MEDIA_UPLOAD_URL, params)
 }
 
+#[cfg(feature = "futures")]
+pub fn media_upload_finalize_command_async<'a, A, H>(client: &'a TwitterClient<A, H>, mut params: Params<'a>) -> FutureTwitterResult<'a, UploadFinalizeCommandResponse>
+    where A: Authenticator, H: AsyncHttpHandler
+{
+    params.push((Cow::Borrowed("command"), ParameterValue::Text(Cow::Borrowed("FINALIZE"))));
+    execute_core_async(client, Post, MEDIA_UPLOAD_URL, params)
+}
+
 pub fn media_upload_status_command<'a, A, H>(client: &TwitterClient<A, H>, mut params: Params<'a>) -> TwitterResult<UploadStatusCommandResponse>
     where A: Authenticator, H: HttpHandler
 {
     params.push((Cow::Borrowed("command"), ParameterValue::Text(Cow::Borrowed("STATUS"))));
     execute_core(client, Get, MEDIA_UPLOAD_URL, params)
 }
+
+#[cfg(feature = "futures")]
+pub fn media_upload_status_command_async<'a, A, H>(client: &'a TwitterClient<A, H>, mut params: Params<'a>) -> FutureTwitterResult<'a, UploadStatusCommandResponse>
+    where A: Authenticator, H: AsyncHttpHandler
+{
+    params.push((Cow::Borrowed("command"), ParameterValue::Text(Cow::Borrowed("STATUS"))));
+    execute_core_async(client, Get, MEDIA_UPLOAD_URL, params)
+}
+
+/// Returns the value of the text parameter, or an empty string if it is not set.
+fn text_param<'b>(params: &'b Params, key: &str) -> &'b str {
+    params.iter()
+        .find(|x| x.0 == key)
+        .and_then(|x| match x.1 {
+            ParameterValue::Text(ref x) => Some(x.as_ref()),
+            ParameterValue::File(_) => None,
+        })
+        .unwrap_or("")
+}
+
+fn optional_text_param<'b>(params: &'b Params, key: &str) -> Option<&'b str> {
+    match text_param(params, key) {
+        "" => None,
+        x => Some(x),
+    }
+}
+
+#[derive(Serialize)]
+struct MetadataCreateBody<'a> {
+    media_id: &'a str,
+    alt_text: AltText<'a>,
+}
+
+#[derive(Serialize)]
+struct AltText<'a> {
+    text: &'a str,
+}
+
+#[derive(Serialize)]
+struct SubtitlesBody<'a> {
+    media_id: &'a str,
+    media_category: &'a str,
+    subtitle_info: SubtitleInfo<'a>,
+}
+
+#[derive(Serialize)]
+struct SubtitleInfo<'a> {
+    subtitles: Vec<Subtitle<'a>>,
+}
+
+#[derive(Serialize)]
+struct Subtitle<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    media_id: Option<&'a str>,
+    language_code: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    display_name: Option<&'a str>,
+}
+
+const MEDIA_METADATA_CREATE_URL: &'static str = "https://upload.twitter.com/1.1/media/metadata/create.json";
+const MEDIA_SUBTITLES_CREATE_URL: &'static str = "https://upload.twitter.com/1.1/media/subtitles/create.json";
+const MEDIA_SUBTITLES_DELETE_URL: &'static str = "https://upload.twitter.com/1.1/media/subtitles/delete.json";
+const DEFAULT_SUBTITLES_MEDIA_CATEGORY: &'static str = "TweetVideo";
+
+fn media_metadata_create_body(params: &Params) -> String {
+    ::serde_json::to_string(&MetadataCreateBody {
+        media_id: text_param(params, "media_id"),
+        alt_text: AltText { text: text_param(params, "alt_text") },
+    }).unwrap()
+}
+
+fn media_subtitles_body(params: &Params, delete: bool) -> String {
+    ::serde_json::to_string(&SubtitlesBody {
+        media_id: text_param(params, "media_id"),
+        media_category: optional_text_param(params, "media_category").unwrap_or(DEFAULT_SUBTITLES_MEDIA_CATEGORY),
+        subtitle_info: SubtitleInfo {
+            subtitles: vec![Subtitle {
+                media_id: if delete { None } else { Some(text_param(params, "subtitle_media_id")) },
+                language_code: text_param(params, "language_code"),
+                display_name: if delete { None } else { Some(text_param(params, "display_name")) },
+            }],
+        },
+    }).unwrap()
+}
+
+pub fn media_metadata_create<'a, A, H>(client: &TwitterClient<A, H>, params: Params<'a>) -> TwitterResult<()>
+    where A: Authenticator, H: HttpHandler
+{
+    execute_json_unit(client, Post, MEDIA_METADATA_CREATE_URL, media_metadata_create_body(&params))
+}
+
+#[cfg(feature = "futures")]
+pub fn media_metadata_create_async<'a, A, H>(client: &'a TwitterClient<A, H>, params: Params<'a>) -> FutureTwitterResult<'a, ()>
+    where A: Authenticator, H: AsyncHttpHandler
+{
+    execute_json_unit_async(client, Post, MEDIA_METADATA_CREATE_URL, media_metadata_create_body(&params))
+}
+
+pub fn media_subtitles_create<'a, A, H>(client: &TwitterClient<A, H>, params: Params<'a>) -> TwitterResult<()>
+    where A: Authenticator, H: HttpHandler
+{
+    execute_json_unit(client, Post, MEDIA_SUBTITLES_CREATE_URL, media_subtitles_body(&params, false))
+}
+
+#[cfg(feature = "futures")]
+pub fn media_subtitles_create_async<'a, A, H>(client: &'a TwitterClient<A, H>, params: Params<'a>) -> FutureTwitterResult<'a, ()>
+    where A: Authenticator, H: AsyncHttpHandler
+{
+    execute_json_unit_async(client, Post, MEDIA_SUBTITLES_CREATE_URL, media_subtitles_body(&params, false))
+}
+
+pub fn media_subtitles_delete<'a, A, H>(client: &TwitterClient<A, H>, params: Params<'a>) -> TwitterResult<()>
+    where A: Authenticator, H: HttpHandler
+{
+    execute_json_unit(client, Post, MEDIA_SUBTITLES_DELETE_URL, media_subtitles_body(&params, true))
+}
+
+#[cfg(feature = "futures")]
+pub fn media_subtitles_delete_async<'a, A, H>(client: &'a TwitterClient<A, H>, params: Params<'a>) -> FutureTwitterResult<'a, ()>
+    where A: Authenticator, H: AsyncHttpHandler
+{
+    execute_json_unit_async(client, Post, MEDIA_SUBTITLES_DELETE_URL, media_subtitles_body(&params, true))
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::Post;
+    use conn::mock_http_handler::*;
+    use ::{ApplicationOnlyAuthenticator, TwitterClient};
+
+    #[test]
+    fn json_body_test() {
+        let mock = MockHttpHandler::new();
+        mock.add(MockRule::new(Post, "https://upload.twitter.com/1.1/media/metadata/create.json", MockResponse::ok("")));
+        mock.add(MockRule::new(Post, "https://upload.twitter.com/1.1/media/subtitles/create.json", MockResponse::ok("")));
+        let c = TwitterClient::new(ApplicationOnlyAuthenticator::new("token"), &mock);
+
+        c.media().metadata_create(1, "a \"cat\"").execute().unwrap();
+        assert_eq!(mock.last_request().unwrap().body.unwrap(), br#"{"media_id":"1","alt_text":{"text":"a \"cat\""}}"#.to_vec());
+
+        c.media().subtitles_create(1, 2, "EN", "English").execute().unwrap();
+        assert_eq!(
+            mock.last_request().unwrap().body.unwrap(),
+            br#"{"media_id":"1","media_category":"TweetVideo","subtitle_info":{"subtitles":[{"media_id":"2","language_code":"EN","display_name":"English"}]}}"#.to_vec()
+        );
+    }
+}
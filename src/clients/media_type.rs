@@ -0,0 +1,332 @@
+use std::borrow::Cow;
+use std::io::{self, Read};
+use std::sync::Arc;
+use super::{CancellationToken, MediaClient, TwitterClient, UploadProgress, UploadSession};
+use ::{TwitterError, TwitterResult};
+use conn::*;
+use models::*;
+
+/// The size limit of images used when `Configuration` is not given.
+pub const DEFAULT_PHOTO_SIZE_LIMIT: u64 = 5 * 1024 * 1024;
+/// The size limit of animated GIFs.
+pub const GIF_SIZE_LIMIT: u64 = 15 * 1024 * 1024;
+/// The size limit of videos.
+pub const VIDEO_SIZE_LIMIT: u64 = 512 * 1024 * 1024;
+
+/// The number of bytes `MediaType::sniff` needs.
+pub const SNIFF_LENGTH: usize = 12;
+
+/// The media formats which Twitter accepts.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum MediaType {
+    Jpeg,
+    Png,
+    Gif,
+    Webp,
+    Mp4,
+    Mov,
+}
+
+impl MediaType {
+    /// Detects the format from the magic bytes at the beginning of the file.
+    pub fn sniff(header: &[u8]) -> Option<MediaType> {
+        if header.starts_with(b"\xFF\xD8\xFF") {
+            Some(MediaType::Jpeg)
+        } else if header.starts_with(b"\x89PNG\r\n\x1A\n") {
+            Some(MediaType::Png)
+        } else if header.starts_with(b"GIF87a") || header.starts_with(b"GIF89a") {
+            Some(MediaType::Gif)
+        } else if header.len() >= 12 && &header[..4] == b"RIFF" && &header[8..12] == b"WEBP" {
+            Some(MediaType::Webp)
+        } else if header.len() >= 12 && &header[4..8] == b"ftyp" {
+            Some(if &header[8..12] == b"qt  " { MediaType::Mov } else { MediaType::Mp4 })
+        } else if header.len() >= 8 {
+            // QuickTime files without ftyp box. `free`, `skip` and `wide` are not enough
+            // because they can start any file based on ISO BMFF
+            match &header[4..8] {
+                b"moov" | b"mdat" => Some(MediaType::Mov),
+                _ => None,
+            }
+        } else {
+            None
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match *self {
+            MediaType::Jpeg => "image/jpeg",
+            MediaType::Png => "image/png",
+            MediaType::Gif => "image/gif",
+            MediaType::Webp => "image/webp",
+            MediaType::Mp4 => "video/mp4",
+            MediaType::Mov => "video/quicktime",
+        }
+    }
+
+    /// Returns `media_category` for the media attached to tweets.
+    pub fn media_category(&self) -> &'static str {
+        match *self {
+            MediaType::Jpeg | MediaType::Png | MediaType::Webp => "tweet_image",
+            MediaType::Gif => "tweet_gif",
+            MediaType::Mp4 | MediaType::Mov => "tweet_video",
+        }
+    }
+
+    /// Returns true if the media should be uploaded with the chunked upload.
+    pub fn requires_chunked_upload(&self) -> bool {
+        match *self {
+            MediaType::Jpeg | MediaType::Png | MediaType::Webp => false,
+            MediaType::Gif | MediaType::Mp4 | MediaType::Mov => true,
+        }
+    }
+
+    /// Returns the maximum size in bytes. The limit of images is taken from `config` if given.
+    pub fn size_limit(&self, config: Option<&Configuration>) -> u64 {
+        match *self {
+            MediaType::Jpeg | MediaType::Png | MediaType::Webp =>
+                config.map_or(DEFAULT_PHOTO_SIZE_LIMIT, |x| x.photo_size_limit as u64),
+            MediaType::Gif => GIF_SIZE_LIMIT,
+            MediaType::Mp4 | MediaType::Mov => VIDEO_SIZE_LIMIT,
+        }
+    }
+}
+
+impl<'a, A: Authenticator, H> MediaClient<'a, A, H> {
+    /// Uploads the media detecting `media_type` and `media_category` from the content.
+    ///
+    /// Images are sent by the simple upload, and GIFs and videos are sent by the chunked upload.
+    pub fn upload_auto(&self, media: &'a mut Read, total_bytes: u64) -> UploadAutoRequestBuilder<'a, A, H> {
+        UploadAutoRequestBuilder {
+            _client: self.client,
+            media: media,
+            total_bytes: total_bytes,
+            additional_owners: Vec::new(),
+            media_category: None,
+            configuration: None,
+            progress_handler: None,
+            cancellation_token: None,
+        }
+    }
+}
+
+pub struct UploadAutoRequestBuilder<'a, A: 'a + Authenticator, H: 'a> {
+    _client: &'a TwitterClient<A, H>,
+    media: &'a mut Read,
+    total_bytes: u64,
+    additional_owners: Vec<i64>,
+    media_category: Option<Cow<'a, str>>,
+    configuration: Option<&'a Configuration>,
+    progress_handler: Option<Arc<Fn(&UploadProgress) + Send + Sync>>,
+    cancellation_token: Option<CancellationToken>,
+}
+
+impl<'a, A: Authenticator, H> UploadAutoRequestBuilder<'a, A, H> {
+    pub fn additional_owners<T1: IntoIterator<Item = i64>>(&'a mut self, val: T1) -> &'a mut Self {
+        self.additional_owners = val.into_iter().collect();
+        self
+    }
+
+    /// Overrides the detected `media_category`, e.g. `dm_video`.
+    pub fn media_category<T1: Into<Cow<'a, str>>>(&'a mut self, val: T1) -> &'a mut Self {
+        self.media_category = Some(val.into());
+        self
+    }
+
+    /// Sets the response of `help().configuration()` to check the size.
+    pub fn configuration(&'a mut self, val: &'a Configuration) -> &'a mut Self {
+        self.configuration = Some(val);
+        self
+    }
+
+    /// Sets the function which is called with the progress of the upload and the processing.
+    pub fn on_progress<F: Fn(&UploadProgress) + Send + Sync + 'static>(&'a mut self, f: F) -> &'a mut Self {
+        self.progress_handler = Some(Arc::new(f));
+        self
+    }
+
+    pub fn cancellation_token(&'a mut self, val: CancellationToken) -> &'a mut Self {
+        self.cancellation_token = Some(val);
+        self
+    }
+}
+
+impl<'a, A: Authenticator, H: HttpHandler> UploadAutoRequestBuilder<'a, A, H> {
+    /// Returns `TwitterError::UnsupportedMedia` if the format is unknown,
+    /// or `TwitterError::MediaTooLarge` if `total_bytes` exceeds the limit.
+    pub fn execute(&'a mut self) -> TwitterResult<UploadFinalizeCommandResponse> {
+        let client = self._client;
+
+        let mut header = [0; SNIFF_LENGTH];
+        let mut header_len = 0;
+        while header_len < header.len() {
+            match try!(self.media.read(&mut header[header_len..])) {
+                0 => break,
+                n => header_len += n,
+            }
+        }
+
+        let media_type = match MediaType::sniff(&header[..header_len]) {
+            Some(x) => x,
+            None => return Err(TwitterError::UnsupportedMedia),
+        };
+
+        let limit = media_type.size_limit(self.configuration);
+        if self.total_bytes > limit {
+            return Err(TwitterError::MediaTooLarge { size: self.total_bytes, limit: limit });
+        }
+
+        let mut reader = (&header[..header_len]).chain(&mut *self.media);
+
+        if !media_type.requires_chunked_upload() && self.media_category.is_none() {
+            if self.cancellation_token.as_ref().map_or(false, |x| x.is_cancelled()) {
+                return Err(TwitterError::Cancelled);
+            }
+
+            let total_bytes = self.total_bytes;
+            let handler = self.progress_handler.clone();
+            let token = self.cancellation_token.clone();
+            let mut on_read = |n| {
+                if let Some(ref f) = handler {
+                    f(&UploadProgress::Uploading { segment_index: 0, bytes_sent: n, total_bytes: total_bytes });
+                }
+                match token {
+                    Some(ref x) if x.is_cancelled() => Err(io::Error::new(io::ErrorKind::Other, "the upload has been cancelled")),
+                    _ => Ok(()),
+                }
+            };
+            let mut reader = ProgressReader::new(&mut reader, &mut on_read);
+
+            let mut req = client.media().upload();
+            let res = req.media(&mut reader).additional_owners(self.additional_owners.iter().cloned()).execute();
+            if self.cancellation_token.as_ref().map_or(false, |x| x.is_cancelled()) {
+                return Err(TwitterError::Cancelled);
+            }
+            let res = try!(res);
+            let x = res.object;
+            return Ok(TwitterResponse {
+                object: UploadFinalizeCommandResponse {
+                    expires_after_secs: x.expires_after_secs,
+                    image: x.image,
+                    media_id: x.media_id,
+                    processing_info: None,
+                    size: x.size,
+                    video: x.video,
+                    #[cfg(feature = "extra-fields")]
+                    extra: x.extra,
+                },
+                raw_response: res.raw_response,
+                rate_limit: res.rate_limit,
+            });
+        }
+
+        let mut session = UploadSession::new(self.total_bytes, media_type.mime_type());
+        session.media_category = Some(match self.media_category {
+            Some(ref x) => x.as_ref().to_owned(),
+            None => media_type.media_category().to_owned(),
+        });
+        session.additional_owners = self.additional_owners.clone();
+        if let Some(ref f) = self.progress_handler {
+            let f = f.clone();
+            session.on_progress(move |x| f(x));
+        }
+        if let Some(ref x) = self.cancellation_token {
+            session.set_cancellation_token(x.clone());
+        }
+
+        try!(session.init(client));
+        while try!(session.append_next(client, &mut reader)) { }
+        session.finalize(client)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::sync::{Arc, Mutex};
+    use hyper::Post;
+    use conn::mock_http_handler::*;
+    use ::{ApplicationOnlyAuthenticator, TwitterClient, TwitterError};
+    use clients::{CancellationToken, UploadProgress};
+    use super::*;
+
+    const URL: &'static str = "https://upload.twitter.com/1.1/media/upload.json";
+
+    #[test]
+    fn sniff_test() {
+        assert_eq!(MediaType::sniff(b"\xFF\xD8\xFF\xE0\x00\x10JFIF\x00"), Some(MediaType::Jpeg));
+        assert_eq!(MediaType::sniff(b"\x89PNG\r\n\x1A\n\x00\x00\x00\x0D"), Some(MediaType::Png));
+        assert_eq!(MediaType::sniff(b"GIF89a\x01\x00\x01\x00"), Some(MediaType::Gif));
+        assert_eq!(MediaType::sniff(b"RIFF\x24\x00\x00\x00WEBPVP8 "), Some(MediaType::Webp));
+        assert_eq!(MediaType::sniff(b"\x00\x00\x00\x18ftypmp42"), Some(MediaType::Mp4));
+        assert_eq!(MediaType::sniff(b"\x00\x00\x00\x14ftypqt  "), Some(MediaType::Mov));
+        assert_eq!(MediaType::sniff(b"\x00\x00\x00\x08moov"), Some(MediaType::Mov));
+        assert_eq!(MediaType::sniff(b"\x00\x00\x00\x08wide"), None);
+        assert_eq!(MediaType::sniff(b"\x00\x00\x00\x08free"), None);
+        assert_eq!(MediaType::sniff(b"RIFF\x24\x00\x00\x00WAVE"), None);
+        assert_eq!(MediaType::sniff(b"\xFF\xD8"), None);
+        assert_eq!(MediaType::sniff(b""), None);
+    }
+
+    #[test]
+    fn upload_auto_test() {
+        let mock = MockHttpHandler::new();
+        mock.add(MockRule::new(Post, URL, MockResponse::ok(r#"{"media_id":2,"expires_after_secs":86400}"#)).param("command", "INIT"));
+        mock.add(MockRule::new(Post, URL, MockResponse::ok("")).param("command", "APPEND"));
+        mock.add(MockRule::new(Post, URL, MockResponse::ok(r#"{"media_id":2,"size":10,"expires_after_secs":86400}"#)).param("command", "FINALIZE"));
+        mock.add(MockRule::new(Post, URL, MockResponse::ok(r#"{"media_id":1,"size":12,"expires_after_secs":86400}"#)));
+        let c = TwitterClient::new(ApplicationOnlyAuthenticator::new("token"), &mock);
+
+        let png = b"\x89PNG\r\n\x1A\n\x00\x00\x00\x0D";
+        let res = c.media().upload_auto(&mut io::Cursor::new(&png[..]), 12).execute().unwrap();
+        assert_eq!(res.object.media_id, 1);
+        let req = mock.last_request().unwrap();
+        assert_eq!(req.param("command"), None);
+        assert_eq!(req.files[0].1, png.to_vec());
+
+        let res = c.media().upload_auto(&mut io::Cursor::new(&b"GIF89a\x01\x00\x01\x00"[..]), 10).execute().unwrap();
+        assert_eq!(res.object.media_id, 2);
+        let init = &mock.requests()[1];
+        assert_eq!(init.param("media_type"), Some("image/gif"));
+        assert_eq!(init.param("media_category"), Some("tweet_gif"));
+
+        let progress = Arc::new(Mutex::new(Vec::new()));
+        let p = progress.clone();
+        mock.clear();
+        mock.add(MockRule::new(Post, URL, MockResponse::ok(r#"{"media_id":3,"expires_after_secs":86400}"#)).param("command", "INIT"));
+        mock.add(MockRule::new(Post, URL, MockResponse::ok("")).param("command", "APPEND"));
+        mock.add(MockRule::new(Post, URL, MockResponse::ok(r#"{"media_id":3,"size":10,"expires_after_secs":86400}"#)).param("command", "FINALIZE"));
+        c.media().upload_auto(&mut io::Cursor::new(&b"GIF89a\x01\x00\x01\x00"[..]), 10)
+            .on_progress(move |x| if let UploadProgress::Uploading { bytes_sent, .. } = *x { p.lock().unwrap().push(bytes_sent) })
+            .execute().unwrap();
+        assert_eq!(*progress.lock().unwrap(), vec![10]);
+
+        let token = CancellationToken::new();
+        token.cancel();
+        let err = c.media().upload_auto(&mut io::Cursor::new(&png[..]), 12).cancellation_token(token.clone()).execute().unwrap_err();
+        assert!(matches!(err, TwitterError::Cancelled));
+        let err = c.media().upload_auto(&mut io::Cursor::new(&b"GIF89a\x01\x00\x01\x00"[..]), 10).cancellation_token(token).execute().unwrap_err();
+        assert!(matches!(err, TwitterError::Cancelled));
+
+        // The simple upload is also reported and can be cancelled while sending the body
+        mock.respond(Post, URL, MockResponse::ok(r#"{"media_id":4,"size":12,"expires_after_secs":86400}"#));
+        let progress = Arc::new(Mutex::new(Vec::new()));
+        let p = progress.clone();
+        c.media().upload_auto(&mut io::Cursor::new(&png[..]), 12)
+            .on_progress(move |x| if let UploadProgress::Uploading { bytes_sent, total_bytes, .. } = *x { p.lock().unwrap().push((bytes_sent, total_bytes)) })
+            .execute().unwrap();
+        assert_eq!(*progress.lock().unwrap(), vec![(12, 12)]);
+
+        let token = CancellationToken::new();
+        let t = token.clone();
+        let err = c.media().upload_auto(&mut io::Cursor::new(&png[..]), 12)
+            .on_progress(move |_| t.cancel())
+            .cancellation_token(token)
+            .execute().unwrap_err();
+        assert!(matches!(err, TwitterError::Cancelled));
+
+        let err = c.media().upload_auto(&mut io::Cursor::new(&png[..]), DEFAULT_PHOTO_SIZE_LIMIT + 1).execute().unwrap_err();
+        assert!(matches!(err, TwitterError::MediaTooLarge { .. }));
+        let err = c.media().upload_auto(&mut io::Cursor::new(&b"hello, world"[..]), 12).execute().unwrap_err();
+        assert!(matches!(err, TwitterError::UnsupportedMedia));
+    }
+}
@@ -0,0 +1,724 @@
+use std::borrow::Cow;
+use std::cmp;
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+use std::time::Duration;
+use chrono;
+use crossbeam_utils;
+use super::{impls, MediaClient, TwitterClient};
+use super::helper::*;
+use ::{TwitterError, TwitterResult};
+use conn::*;
+use conn::retrying_http_handler::RetryPolicy;
+use models::*;
+
+/// The default size of each APPEND segment. Twitter accepts segments up to 5MB.
+pub const DEFAULT_SEGMENT_SIZE: usize = 5 * 1024 * 1024;
+
+/// The interval of STATUS commands used when the server does not tell `check_after_secs`.
+const DEFAULT_CHECK_AFTER_SECS: u32 = 5;
+
+/// The progress reported to the handler set by `on_progress`.
+#[derive(Clone, Debug)]
+pub enum UploadProgress {
+    /// The body of APPEND command or the simple upload is being sent. `segment_index` is 0 for the simple upload.
+    /// `bytes_sent` includes the segments which have been appended,
+    /// and never decreases during an upload even if a segment is retried.
+    Uploading { segment_index: i32, bytes_sent: u64, total_bytes: u64 },
+    /// FINALIZE or STATUS command returned `processing_info`.
+    Processing(ProcessingInfo),
+}
+
+#[derive(Clone)]
+struct ProgressHandler(Arc<Fn(&UploadProgress) + Send + Sync>);
+
+impl ProgressHandler {
+    fn report(&self, progress: UploadProgress) {
+        (self.0)(&progress)
+    }
+}
+
+impl fmt::Debug for ProgressHandler {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("ProgressHandler")
+    }
+}
+
+/// Aborts an upload from another thread. The clones share the same state.
+///
+/// The upload stops with `TwitterError::Cancelled` before the next segment or the next STATUS command.
+/// The simple upload by `upload_auto` stops while sending the body.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> CancellationToken {
+        Default::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst)
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// The error returned when an upload worker has panicked.
+fn worker_panicked<T>(_: T) -> TwitterError {
+    TwitterError::Io(io::Error::new(io::ErrorKind::Other, "an upload worker panicked"))
+}
+
+fn check_cancelled(token: &Option<CancellationToken>) -> Result<(), TwitterError> {
+    match *token {
+        Some(ref x) if x.is_cancelled() => Err(TwitterError::Cancelled),
+        _ => Ok(()),
+    }
+}
+
+impl<'a, A: Authenticator, H> MediaClient<'a, A, H> {
+    /// Uploads the media with INIT, APPEND and FINALIZE commands, and waits until the processing completes.
+    pub fn upload_chunked<T: Into<Cow<'a, str>>>(&self, media: &'a mut Read, total_bytes: u64, media_type: T)
+        -> UploadChunkedRequestBuilder<'a, A, H>
+    {
+        UploadChunkedRequestBuilder {
+            _client: self.client,
+            media: media,
+            total_bytes: total_bytes,
+            media_type: media_type.into(),
+            additional_owners: Vec::new(),
+            media_category: None,
+            segment_size: DEFAULT_SEGMENT_SIZE,
+            progress_handler: None,
+            cancellation_token: None,
+        }
+    }
+}
+
+pub struct UploadChunkedRequestBuilder<'a, A: 'a + Authenticator, H: 'a> {
+    _client: &'a TwitterClient<A, H>,
+    media: &'a mut Read,
+    total_bytes: u64,
+    media_type: Cow<'a, str>,
+    additional_owners: Vec<i64>,
+    media_category: Option<Cow<'a, str>>,
+    segment_size: usize,
+    progress_handler: Option<ProgressHandler>,
+    cancellation_token: Option<CancellationToken>,
+}
+
+impl<'a, A: Authenticator, H> UploadChunkedRequestBuilder<'a, A, H> {
+    pub fn additional_owners<T1: IntoIterator<Item = i64>>(&'a mut self, val: T1) -> &'a mut Self {
+        self.additional_owners = val.into_iter().collect();
+        self
+    }
+
+    pub fn media_category<T1: Into<Cow<'a, str>>>(&'a mut self, val: T1) -> &'a mut Self {
+        self.media_category = Some(val.into());
+        self
+    }
+
+    /// Sets the size of each APPEND segment in bytes. The default is `DEFAULT_SEGMENT_SIZE`.
+    /// `execute` fails with `TwitterError::InvalidRequest` if the size is 0.
+    pub fn segment_size(&'a mut self, val: usize) -> &'a mut Self {
+        self.segment_size = val;
+        self
+    }
+
+    /// Sets the function which is called with the progress of the upload and the processing.
+    pub fn on_progress<F: Fn(&UploadProgress) + Send + Sync + 'static>(&'a mut self, f: F) -> &'a mut Self {
+        self.progress_handler = Some(ProgressHandler(Arc::new(f)));
+        self
+    }
+
+    pub fn cancellation_token(&'a mut self, val: CancellationToken) -> &'a mut Self {
+        self.cancellation_token = Some(val);
+        self
+    }
+}
+
+impl<'a, A: Authenticator, H: HttpHandler> UploadChunkedRequestBuilder<'a, A, H> {
+    /// Returns the response of FINALIZE command whose `processing_info` is replaced with the last one.
+    ///
+    /// If the processing fails, `TwitterError::MediaProcessing` is returned.
+    pub fn execute(&'a mut self) -> TwitterResult<UploadFinalizeCommandResponse> {
+        let client = self._client;
+
+        let mut session = UploadSession::new(self.total_bytes, self.media_type.as_ref());
+        session.media_category = self.media_category.as_ref().map(|x| x.as_ref().to_owned());
+        session.additional_owners = self.additional_owners.clone();
+        session.segment_size = self.segment_size;
+        session.progress_handler = self.progress_handler.clone();
+        session.cancellation_token = self.cancellation_token.clone();
+
+        try!(session.check_segment_size());
+        try!(session.init(client));
+        while try!(session.append_next(client, self.media)) { }
+        session.finalize(client)
+    }
+}
+
+/// The state of a chunked upload, which can be saved and resumed by another process.
+///
+/// ```no_run
+/// # use std::fs::File;
+/// # use tweetust::*;
+/// # use tweetust::clients::UploadSession;
+/// # let client = TwitterClient::new(ApplicationOnlyAuthenticator::new(""), DefaultHttpHandler::with_https_connector().unwrap());
+/// let mut file = File::open("video.mp4").unwrap();
+/// let mut session = UploadSession::new(file.metadata().unwrap().len(), "video/mp4");
+/// // INIT again if the session has expired, and seek to the first byte not appended yet
+/// session.prepare(&client, &mut file).unwrap();
+/// while session.append_next(&client, &mut file).unwrap() {
+///     // Save the session here to resume later
+/// }
+/// let media_id = session.finalize(&client).unwrap().object.media_id;
+/// ```
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UploadSession {
+    /// 0 if INIT command has not been sent.
+    pub media_id: i64,
+    pub total_bytes: u64,
+    pub media_type: String,
+    pub media_category: Option<String>,
+    pub additional_owners: Vec<i64>,
+    pub segment_size: usize,
+    /// The number of the segments which have been appended, that is, the next `segment_index`.
+    pub segment_index: i32,
+    /// The number of the bytes which have been appended.
+    pub offset: u64,
+    /// The Unix time when `media_id` expires.
+    pub expires_at: i64,
+    #[serde(skip)]
+    progress_handler: Option<ProgressHandler>,
+    #[serde(skip)]
+    cancellation_token: Option<CancellationToken>,
+}
+
+impl UploadSession {
+    /// Creates a session which has not been initialized.
+    pub fn new<T: Into<String>>(total_bytes: u64, media_type: T) -> UploadSession {
+        UploadSession {
+            media_id: 0,
+            total_bytes: total_bytes,
+            media_type: media_type.into(),
+            media_category: None,
+            additional_owners: Vec::new(),
+            segment_size: DEFAULT_SEGMENT_SIZE,
+            segment_index: 0,
+            offset: 0,
+            expires_at: 0,
+            progress_handler: None,
+            cancellation_token: None,
+        }
+    }
+
+    /// Sets the function which is called with the progress of the upload and the processing.
+    pub fn on_progress<F: Fn(&UploadProgress) + Send + Sync + 'static>(&mut self, f: F) -> &mut Self {
+        self.progress_handler = Some(ProgressHandler(Arc::new(f)));
+        self
+    }
+
+    pub fn set_cancellation_token(&mut self, val: CancellationToken) -> &mut Self {
+        self.cancellation_token = Some(val);
+        self
+    }
+
+    /// Returns true if `media_id` is no longer valid or INIT command has not been sent.
+    pub fn is_expired(&self) -> bool {
+        self.media_id == 0 || self.expires_at <= chrono::Utc::now().timestamp()
+    }
+
+    /// Returns true if all bytes have been appended.
+    pub fn is_complete(&self) -> bool {
+        self.offset >= self.total_bytes
+    }
+
+    fn check_segment_size(&self) -> Result<(), TwitterError> {
+        if self.segment_size == 0 { Err(TwitterError::InvalidRequest) } else { Ok(()) }
+    }
+
+    /// Sends INIT command and resets the progress.
+    pub fn init<A: Authenticator, H: HttpHandler>(&mut self, client: &TwitterClient<A, H>) -> Result<(), TwitterError> {
+        let additional_owners = if self.additional_owners.is_empty() { None }
+            else { Some(collection_paramter(self.additional_owners.iter())) };
+
+        let mut params = Vec::with_capacity(4);
+        params.push((Cow::Borrowed("total_bytes"), self.total_bytes.to_parameter_value()));
+        params.push((Cow::Borrowed("media_type"), self.media_type.as_str().to_parameter_value()));
+        if let Some(ref x) = additional_owners { params.push((Cow::Borrowed("additional_owners"), x.as_str().to_parameter_value())) }
+        if let Some(ref x) = self.media_category { params.push((Cow::Borrowed("media_category"), x.as_str().to_parameter_value())) }
+
+        let res = try!(impls::media_upload_init_command(client, params)).object;
+        self.media_id = res.media_id;
+        self.expires_at = chrono::Utc::now().timestamp() + res.expires_after_secs as i64;
+        self.segment_index = 0;
+        self.offset = 0;
+        Ok(())
+    }
+
+    /// Sends INIT command if the session has expired, and seeks `media` to `offset`.
+    ///
+    /// `TwitterError::InvalidRequest` is returned if `segment_size` is 0.
+    pub fn prepare<A, H, R>(&mut self, client: &TwitterClient<A, H>, media: &mut R) -> Result<(), TwitterError>
+        where A: Authenticator, H: HttpHandler, R: Seek + ?Sized
+    {
+        try!(self.check_segment_size());
+        if self.is_expired() {
+            try!(self.init(client));
+        }
+        try!(media.seek(SeekFrom::Start(self.offset)));
+        Ok(())
+    }
+
+    /// Reads a segment from the current position of `media` and sends APPEND command.
+    /// Returns false if there is nothing to append.
+    ///
+    /// `TwitterError::InvalidRequest` is returned if `segment_size` is 0,
+    /// and `TwitterError::Io` with `UnexpectedEof` if `media` ends before `total_bytes`.
+    pub fn append_next<A, H, R>(&mut self, client: &TwitterClient<A, H>, media: &mut R) -> Result<bool, TwitterError>
+        where A: Authenticator, H: HttpHandler, R: Read + ?Sized
+    {
+        try!(self.check_segment_size());
+        if self.is_complete() { return Ok(false); }
+        try!(check_cancelled(&self.cancellation_token));
+
+        let len = cmp::min(self.segment_size as u64, self.total_bytes - self.offset);
+        let mut buf = Vec::with_capacity(len as usize);
+        try!(media.take(len).read_to_end(&mut buf));
+        if (buf.len() as u64) < len {
+            return Err(TwitterError::Io(io::Error::new(io::ErrorKind::UnexpectedEof, "the media is shorter than total_bytes")));
+        }
+
+        {
+            let (segment_index, total_bytes) = (self.segment_index, self.total_bytes);
+            let offset = self.offset;
+            let handler = &self.progress_handler;
+            try!(append_segment(client, self.media_id, segment_index, &buf, &mut |n| {
+                if let Some(ref h) = *handler {
+                    h.report(UploadProgress::Uploading { segment_index: segment_index, bytes_sent: offset + n, total_bytes: total_bytes });
+                }
+            }));
+        }
+        self.segment_index += 1;
+        self.offset += buf.len() as u64;
+        Ok(true)
+    }
+
+    /// Sends FINALIZE command and waits until the processing completes.
+    pub fn finalize<A: Authenticator, H: HttpHandler>(&self, client: &TwitterClient<A, H>) -> TwitterResult<UploadFinalizeCommandResponse> {
+        let params = vec![(Cow::Borrowed("media_id"), self.media_id.to_parameter_value())];
+        let mut res = try!(impls::media_upload_finalize_command(client, params));
+
+        while let Some(info) = res.object.processing_info.take() {
+            if let Some(ref h) = self.progress_handler {
+                h.report(UploadProgress::Processing(info.clone()));
+            }
+
+            let check_after_secs = match info.state.as_ref() {
+                "succeeded" => {
+                    res.object.processing_info = Some(info);
+                    break;
+                }
+                "failed" => return Err(TwitterError::MediaProcessing(info.error.unwrap_or_else(|| MediaProcessingError {
+                    message: "the processing failed".to_owned(),
+                    .. Default::default()
+                }))),
+                _ => info.check_after_secs.unwrap_or(DEFAULT_CHECK_AFTER_SECS),
+            };
+
+            try!(check_cancelled(&self.cancellation_token));
+            thread::sleep(Duration::from_secs(check_after_secs as u64));
+            try!(check_cancelled(&self.cancellation_token));
+
+            let params = vec![(Cow::Borrowed("media_id"), self.media_id.to_parameter_value())];
+            let status = try!(impls::media_upload_status_command(client, params));
+            res.object.processing_info = Some(status.object.processing_info);
+            res.raw_response = status.raw_response;
+            res.rate_limit = status.rate_limit;
+        }
+
+        Ok(res)
+    }
+
+    /// Appends the rest of `media` with `workers` threads, sending the segments concurrently.
+    ///
+    /// Each failed segment is retried according to `retry_policy`. When a segment fails finally,
+    /// the other workers stop and `segment_index` and `offset` are set to the segments which have been appended in a row,
+    /// so the upload can be resumed.
+    ///
+    /// If the session has expired, the upload starts over from INIT.
+    pub fn append_parallel<A, H, R>(&mut self, client: &TwitterClient<A, H>, media: &mut R, workers: usize, retry_policy: &RetryPolicy)
+        -> Result<(), TwitterError>
+        where A: Authenticator + Sync, H: HttpHandler + Sync, R: Read + Seek + Send + ?Sized
+    {
+        try!(self.check_segment_size());
+        if self.is_expired() {
+            try!(self.init(client));
+        }
+        let segment_size = self.segment_size as u64;
+        let total_bytes = self.total_bytes;
+        let media_id = self.media_id;
+        let first = self.segment_index;
+        let count = ((total_bytes + segment_size - 1) / segment_size) as i32;
+        if first >= count { return Ok(()); }
+
+        let next_index = Mutex::new(first);
+        let media = Mutex::new(media);
+        let done = Mutex::new(vec![false; (count - first) as usize]);
+        let error = Mutex::new(None);
+        let total_sent = AtomicU64::new(first as u64 * segment_size);
+        let reported = Mutex::new(first as u64 * segment_size);
+        let handler = &self.progress_handler;
+        let token = &self.cancellation_token;
+
+        let work = || -> Result<(), TwitterError> {
+            loop {
+                if try!(error.lock().map_err(worker_panicked)).is_some() { return Ok(()); }
+                try!(check_cancelled(token));
+
+                let index = {
+                    let mut x = try!(next_index.lock().map_err(worker_panicked));
+                    if *x >= count { return Ok(()); }
+                    *x += 1;
+                    *x - 1
+                };
+
+                let start = index as u64 * segment_size;
+                let mut buf = vec![0; cmp::min(segment_size, total_bytes - start) as usize];
+                {
+                    let mut media = try!(media.lock().map_err(worker_panicked));
+                    try!(media.seek(SeekFrom::Start(start)).and_then(|_| media.read_exact(&mut buf)));
+                }
+
+                let mut attempt = 1;
+                loop {
+                    let mut attempt_sent = 0;
+                    let res = append_segment(client, media_id, index, &buf, &mut |n| {
+                        let delta = n - attempt_sent;
+                        attempt_sent = n;
+                        let bytes_sent = total_sent.fetch_add(delta, Ordering::SeqCst) + delta;
+                        if let Some(ref h) = *handler {
+                            // Reports only the largest value so far, since the retried bytes are subtracted
+                            // and the workers may reach here out of order
+                            if let Ok(mut reported) = reported.lock() {
+                                if bytes_sent > *reported {
+                                    *reported = bytes_sent;
+                                    h.report(UploadProgress::Uploading { segment_index: index, bytes_sent: bytes_sent, total_bytes: total_bytes });
+                                }
+                            }
+                        }
+                    });
+                    if res.is_err() {
+                        // The bytes will be sent again
+                        total_sent.fetch_sub(attempt_sent, Ordering::SeqCst);
+                    }
+
+                    match res {
+                        Ok(_) => break,
+                        Err(e) => match retry_policy.retry_delay(&e, attempt) {
+                            Some(delay) => {
+                                thread::sleep(delay);
+                                attempt += 1;
+                            }
+                            None => return Err(e),
+                        },
+                    }
+                }
+
+                try!(done.lock().map_err(worker_panicked))[(index - first) as usize] = true;
+            }
+        };
+
+        let worker = || {
+            if let Err(e) = work() {
+                if let Ok(mut x) = error.lock() {
+                    if x.is_none() { *x = Some(e); }
+                }
+            }
+        };
+
+        // A panic in a worker is reported after all workers have stopped
+        let joined = crossbeam_utils::thread::scope(|s| {
+            for _ in 0..cmp::max(cmp::min(workers, (count - first) as usize), 1) {
+                s.spawn(|_| worker());
+            }
+        });
+
+        let appended = try!(done.into_inner().map_err(worker_panicked)).iter().take_while(|&&x| x).count() as i32;
+        self.segment_index = first + appended;
+        self.offset = cmp::min(self.segment_index as u64 * segment_size, total_bytes);
+
+        try!(joined.map_err(worker_panicked));
+        match try!(error.into_inner().map_err(worker_panicked)) {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// Appends the rest of `media` concurrently by `append_parallel` and finalizes after all segments have been appended.
+    /// If the session has expired, the upload starts over from INIT.
+    pub fn upload_parallel<A, H, R>(&mut self, client: &TwitterClient<A, H>, media: &mut R, workers: usize, retry_policy: &RetryPolicy)
+        -> TwitterResult<UploadFinalizeCommandResponse>
+        where A: Authenticator + Sync, H: HttpHandler + Sync, R: Read + Seek + Send + ?Sized
+    {
+        try!(self.append_parallel(client, media, workers, retry_policy));
+        self.finalize(client)
+    }
+
+    /// Appends the rest of `media` from `offset` and finalizes.
+    /// If the session has expired, the upload starts over from INIT.
+    pub fn resume<A, H, R>(&mut self, client: &TwitterClient<A, H>, media: &mut R) -> TwitterResult<UploadFinalizeCommandResponse>
+        where A: Authenticator, H: HttpHandler, R: Read + Seek + ?Sized
+    {
+        try!(self.prepare(client, media));
+        while try!(self.append_next(client, media)) { }
+        self.finalize(client)
+    }
+}
+
+/// Sends APPEND command with `data`. `on_read` is called with the number of the bytes sent so far.
+fn append_segment<A, H>(client: &TwitterClient<A, H>, media_id: i64, segment_index: i32, data: &[u8], on_read: &mut FnMut(u64))
+    -> TwitterResult<()>
+    where A: Authenticator, H: HttpHandler
+{
+    let mut data = data;
+    let mut on_read = |n| { on_read(n); Ok(()) };
+    let mut reader = ProgressReader::new(&mut data, &mut on_read);
+    client.media().upload_append_command(media_id, segment_index)
+        .media(&mut reader)
+        .execute()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use hyper::{Get, Post};
+    use hyper::status::StatusCode;
+    use conn::mock_http_handler::*;
+    use conn::retrying_http_handler::RetryPolicy;
+    use ::{ApplicationOnlyAuthenticator, TwitterClient, TwitterError};
+    use super::{CancellationToken, UploadProgress, UploadSession};
+
+    const URL: &'static str = "https://upload.twitter.com/1.1/media/upload.json";
+
+    fn client(mock: &MockHttpHandler) -> TwitterClient<ApplicationOnlyAuthenticator, &MockHttpHandler> {
+        TwitterClient::new(ApplicationOnlyAuthenticator::new("token"), mock)
+    }
+
+    #[test]
+    fn upload_chunked_test() {
+        let mock = MockHttpHandler::new();
+        mock.add(MockRule::new(Post, URL, MockResponse::ok(r#"{"media_id":1,"expires_after_secs":86400}"#)).param("command", "INIT"));
+        mock.add(MockRule::new(Post, URL, MockResponse::ok("")).param("command", "APPEND"));
+        mock.add(MockRule::new(Post, URL, MockResponse::ok(
+            r#"{"media_id":1,"expires_after_secs":86400,"size":10,"processing_info":{"state":"pending","check_after_secs":0}}"#
+        )).param("command", "FINALIZE"));
+        mock.add(MockRule::new(Get, URL, MockResponse::ok(
+            r#"{"media_id":1,"processing_info":{"state":"in_progress","check_after_secs":0,"progress_percent":50}}"#
+        )).param("command", "STATUS").times(1));
+        mock.add(MockRule::new(Get, URL, MockResponse::ok(
+            r#"{"media_id":1,"processing_info":{"state":"succeeded","progress_percent":100}}"#
+        )).param("command", "STATUS"));
+
+        let progress = Arc::new(Mutex::new(Vec::new()));
+        let p = progress.clone();
+
+        let mut data = io::Cursor::new(b"0123456789".to_vec());
+        let c = client(&mock);
+        let res = c.media().upload_chunked(&mut data, 10, "video/mp4")
+            .segment_size(4)
+            .on_progress(move |x| p.lock().unwrap().push(match *x {
+                UploadProgress::Uploading { bytes_sent, .. } => bytes_sent as i64,
+                UploadProgress::Processing(ref x) => -(x.progress_percent.unwrap_or(0) as i64),
+            }))
+            .execute().unwrap();
+        assert_eq!(res.object.processing_info.unwrap().state, "succeeded");
+        assert_eq!(*progress.lock().unwrap(), vec![4, 8, 10, 0, -50, -100]);
+
+        let requests = mock.requests();
+        let appends: Vec<_> = requests.iter().filter(|x| x.param("command") == Some("APPEND")).collect();
+        assert_eq!(appends.len(), 3);
+        assert_eq!(appends[2].param("segment_index"), Some("2"));
+        assert_eq!(appends[2].files[0].1, b"89");
+        assert_eq!(requests.iter().filter(|x| x.param("command") == Some("STATUS")).count(), 2);
+    }
+
+    #[test]
+    fn resume_test() {
+        let mock = MockHttpHandler::new();
+        mock.add(MockRule::new(Post, URL, MockResponse::ok(r#"{"media_id":2,"expires_after_secs":86400}"#)).param("command", "INIT"));
+        mock.add(MockRule::new(Post, URL, MockResponse::ok("")).param("command", "APPEND"));
+        mock.add(MockRule::new(Post, URL, MockResponse::ok(r#"{"media_id":2,"expires_after_secs":86400,"size":10}"#)).param("command", "FINALIZE"));
+        let c = client(&mock);
+
+        let mut session = UploadSession::new(10, "video/mp4");
+        session.media_id = 1;
+        session.segment_size = 4;
+        session.segment_index = 2;
+        session.offset = 8;
+        session.expires_at = ::chrono::Utc::now().timestamp() + 60;
+
+        let mut data = io::Cursor::new(b"0123456789".to_vec());
+        session.resume(&c, &mut data).unwrap();
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].param("media_id"), Some("1"));
+        assert_eq!(requests[0].param("segment_index"), Some("2"));
+        assert_eq!(requests[0].files[0].1, b"89");
+
+        // Starts over if expired
+        mock.clear();
+        mock.add(MockRule::new(Post, URL, MockResponse::ok(r#"{"media_id":2,"expires_after_secs":86400}"#)).param("command", "INIT"));
+        mock.add(MockRule::new(Post, URL, MockResponse::ok("")).param("command", "APPEND"));
+        mock.add(MockRule::new(Post, URL, MockResponse::ok(r#"{"media_id":2,"expires_after_secs":86400,"size":10}"#)).param("command", "FINALIZE"));
+        session.expires_at = 0;
+        session.offset = 8;
+        session.resume(&c, &mut data).unwrap();
+        assert_eq!(session.media_id, 2);
+        assert_eq!(session.segment_index, 3);
+        assert_eq!(mock.requests()[0].param("command"), Some("INIT"));
+    }
+
+    #[test]
+    fn truncated_media_test() {
+        let mock = MockHttpHandler::new();
+        mock.add(MockRule::new(Post, URL, MockResponse::ok(r#"{"media_id":1,"expires_after_secs":86400}"#)).param("command", "INIT"));
+        mock.add(MockRule::new(Post, URL, MockResponse::ok("")).param("command", "APPEND"));
+        let c = client(&mock);
+
+        let mut data = io::Cursor::new(b"012345".to_vec());
+        let res = c.media().upload_chunked(&mut data, 10, "video/mp4").segment_size(4).execute();
+        match res {
+            Err(TwitterError::Io(ref e)) => assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof),
+            _ => panic!("expected UnexpectedEof"),
+        }
+
+        // The short segment and FINALIZE are not sent
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1].files[0].1, b"0123");
+    }
+
+    #[test]
+    fn zero_segment_size_test() {
+        let mock = MockHttpHandler::new();
+        let c = client(&mock);
+
+        let mut session = UploadSession::new(10, "video/mp4");
+        session.segment_size = 0;
+        let mut data = io::Cursor::new(b"0123456789".to_vec());
+        assert!(matches!(session.resume(&c, &mut data), Err(TwitterError::InvalidRequest)));
+        assert!(matches!(session.append_next(&c, &mut data), Err(TwitterError::InvalidRequest)));
+        assert!(matches!(session.upload_parallel(&c, &mut data, 2, &RetryPolicy::default()), Err(TwitterError::InvalidRequest)));
+        let res = c.media().upload_chunked(&mut data, 10, "video/mp4").segment_size(0).execute();
+        assert!(matches!(res, Err(TwitterError::InvalidRequest)));
+        assert!(mock.requests().is_empty());
+    }
+
+    #[test]
+    fn parallel_test() {
+        let mock = MockHttpHandler::new();
+        mock.add(MockRule::new(Post, URL, MockResponse::ok(r#"{"media_id":1,"expires_after_secs":86400}"#)).param("command", "INIT"));
+        mock.add(MockRule::new(Post, URL, MockResponse::with_status(StatusCode::ServiceUnavailable, ""))
+            .param("command", "APPEND").param("segment_index", "1").times(1));
+        mock.add(MockRule::new(Post, URL, MockResponse::ok("")).param("command", "APPEND"));
+        mock.add(MockRule::new(Post, URL, MockResponse::ok(r#"{"media_id":1,"expires_after_secs":86400,"size":10}"#)).param("command", "FINALIZE"));
+        let c = client(&mock);
+
+        let mut policy = RetryPolicy::default();
+        policy.initial_backoff = Duration::from_millis(0);
+
+        let progress = Arc::new(Mutex::new(Vec::new()));
+        let p = progress.clone();
+
+        let mut session = UploadSession::new(10, "video/mp4");
+        session.segment_size = 4;
+        session.on_progress(move |x| if let UploadProgress::Uploading { bytes_sent, .. } = *x {
+            p.lock().unwrap().push(bytes_sent);
+        });
+        let mut data = io::Cursor::new(b"0123456789".to_vec());
+        session.upload_parallel(&c, &mut data, 2, &policy).unwrap();
+        assert_eq!(session.segment_index, 3);
+        assert_eq!(session.offset, 10);
+
+        // The progress never goes back even though segment 1 is sent twice
+        let progress = progress.lock().unwrap();
+        assert!(progress.windows(2).all(|x| x[0] < x[1]));
+        assert_eq!(progress.last(), Some(&10));
+
+        let requests = mock.requests();
+        let mut appends: Vec<_> = requests.iter()
+            .filter(|x| x.param("command") == Some("APPEND"))
+            .map(|x| (x.param("segment_index").unwrap().to_owned(), x.files[0].1.clone()))
+            .collect();
+        appends.sort();
+        assert_eq!(appends, vec![
+            ("0".to_owned(), b"0123".to_vec()),
+            ("1".to_owned(), b"4567".to_vec()),
+            ("1".to_owned(), b"4567".to_vec()),
+            ("2".to_owned(), b"89".to_vec()),
+        ]);
+        assert_eq!(requests.last().unwrap().param("command"), Some("FINALIZE"));
+    }
+
+    #[test]
+    fn append_parallel_init_test() {
+        let mock = MockHttpHandler::new();
+        mock.add(MockRule::new(Post, URL, MockResponse::ok(r#"{"media_id":1,"expires_after_secs":86400}"#)).param("command", "INIT"));
+        mock.add(MockRule::new(Post, URL, MockResponse::ok("")).param("command", "APPEND"));
+        let c = client(&mock);
+
+        // An expired session starts over from INIT
+        let mut session = UploadSession::new(10, "video/mp4");
+        session.segment_size = 4;
+        session.segment_index = 1;
+        session.offset = 4;
+        let mut data = io::Cursor::new(b"0123456789".to_vec());
+        session.append_parallel(&c, &mut data, 2, &RetryPolicy::default()).unwrap();
+        assert_eq!(session.media_id, 1);
+        assert_eq!(session.segment_index, 3);
+
+        let requests = mock.requests();
+        assert_eq!(requests[0].param("command"), Some("INIT"));
+        assert_eq!(requests.iter().filter(|x| x.param("command") == Some("APPEND")).count(), 3);
+    }
+
+    #[test]
+    fn cancel_test() {
+        let mock = MockHttpHandler::new();
+        mock.add(MockRule::new(Post, URL, MockResponse::ok(r#"{"media_id":1,"expires_after_secs":86400}"#)).param("command", "INIT"));
+
+        let token = CancellationToken::new();
+        token.cancel();
+
+        let mut data = io::Cursor::new(b"0123456789".to_vec());
+        let c = client(&mock);
+        let res = c.media().upload_chunked(&mut data, 10, "video/mp4").cancellation_token(token).execute();
+        assert!(matches!(res, Err(TwitterError::Cancelled)));
+        assert_eq!(mock.requests().len(), 1);
+    }
+
+    #[test]
+    fn processing_error_test() {
+        let mock = MockHttpHandler::new();
+        mock.add(MockRule::new(Post, URL, MockResponse::ok(r#"{"media_id":1,"expires_after_secs":86400}"#)).param("command", "INIT"));
+        mock.add(MockRule::new(Post, URL, MockResponse::ok("")).param("command", "APPEND"));
+        mock.add(MockRule::new(Post, URL, MockResponse::ok(
+            r#"{"media_id":1,"expires_after_secs":86400,"size":1,"processing_info":{"state":"failed","error":{"code":1,"name":"InvalidMedia","message":"Unsupported video format"}}}"#
+        )).param("command", "FINALIZE"));
+
+        let mut data = io::Cursor::new(b"0".to_vec());
+        let c = client(&mock);
+        match c.media().upload_chunked(&mut data, 1, "video/mp4").execute() {
+            Err(TwitterError::MediaProcessing(e)) => assert_eq!(e.name, "InvalidMedia"),
+            x => panic!("{:?}", x),
+        }
+    }
+}
@@ -1,12 +1,24 @@
 use std::borrow::Cow;
 use std::io::Read;
 use hyper::{Get, Post};
-use ::TwitterResult;
+use ::{TwitterError, TwitterResult};
 use conn::*;
 use models::*;
 use self::helper::*;
 
+pub use self::media_type::{DEFAULT_PHOTO_SIZE_LIMIT, GIF_SIZE_LIMIT, MediaType, SNIFF_LENGTH, UploadAutoRequestBuilder, VIDEO_SIZE_LIMIT};
+pub use self::media_upload::{CancellationToken, DEFAULT_SEGMENT_SIZE, UploadChunkedRequestBuilder, UploadProgress, UploadSession};
+pub use self::pagination::{CursorItems, CursorPages, TimelinePages, TimelinePoller};
+pub use self::pool::{PooledAuthenticator, PooledHttpHandler, TwitterClientPool};
+
+#[cfg(feature = "futures")]
+use ::FutureTwitterResult;
+
 mod helper;
 mod impls;
+mod media_type;
+mod media_upload;
+mod pagination;
+mod pool;
 
 include!(concat!(env!("OUT_DIR"), "/clients.rs"));
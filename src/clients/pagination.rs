@@ -0,0 +1,583 @@
+use std::borrow::Cow;
+use std::marker::PhantomData;
+use std::thread;
+use std::time::Duration;
+use std::vec;
+use chrono;
+use hyper::method::Method;
+use serde::de::DeserializeOwned;
+use super::TwitterClient;
+use super::helper::*;
+use ::{TwitterError, TwitterResult};
+use conn::*;
+use models::*;
+
+type TextParams<'a> = Vec<(Cow<'a, str>, Cow<'a, str>)>;
+
+/// Takes the text parameters except `exclude`. The values of the excluded parameters are returned as the second item.
+///
+/// Returns `TwitterError::InvalidRequest` if there is a file, which cannot be sent repeatedly.
+fn split_params<'a>(params: Vec<(Cow<'a, str>, ParameterValue<'a>)>, exclude: &[&str])
+    -> Result<(TextParams<'a>, Vec<(Cow<'a, str>, Cow<'a, str>)>), TwitterError>
+{
+    let mut text_params = Vec::with_capacity(params.len());
+    let mut excluded = Vec::new();
+
+    for (key, val) in params {
+        let val = match val {
+            ParameterValue::Text(x) => x,
+            ParameterValue::File(_) => return Err(TwitterError::InvalidRequest),
+        };
+
+        if exclude.iter().any(|&x| x == key) {
+            excluded.push((key, val));
+        } else {
+            text_params.push((key, val));
+        }
+    }
+
+    Ok((text_params, excluded))
+}
+
+fn to_parameter_values<'a, 'b>(params: &'b TextParams<'a>) -> Vec<(Cow<'b, str>, ParameterValue<'b>)> {
+    params.iter()
+        .map(|&(ref k, ref v)| (Cow::Borrowed(k.as_ref()), ParameterValue::Text(Cow::Borrowed(v.as_ref()))))
+        .collect()
+}
+
+/// Sleeps until the window resets if the last response says no request remains.
+fn wait_for_rate_limit(rate_limit: Option<RateLimitStatus>) {
+    if let Some(x) = rate_limit {
+        if x.remaining <= 0 {
+            let secs = x.reset - chrono::Utc::now().timestamp();
+            if secs > 0 {
+                // Add a second to make sure the window has been reset
+                thread::sleep(Duration::from_secs(secs as u64 + 1));
+            }
+        }
+    }
+}
+
+/// The iterator which sends the request repeatedly with `cursor` parameter until `next_cursor` becomes 0.
+///
+/// This is returned by `iter_pages` methods of the request builders for the cursored endpoints.
+pub struct CursorPages<'a, A: 'a + Authenticator, H: 'a, R> {
+    client: &'a TwitterClient<A, H>,
+    method: Method,
+    url: String,
+    params: TextParams<'a>,
+    next_cursor: i64,
+    last_rate_limit: Option<RateLimitStatus>,
+    wait_for_rate_limit: bool,
+    invalid_request: bool,
+    _response: PhantomData<R>,
+}
+
+impl<'a, A: Authenticator, H, R> CursorPages<'a, A, H, R> {
+    #[doc(hidden)]
+    pub fn new<U: AsRef<str>>(client: &'a TwitterClient<A, H>, method: Method, url: U,
+        params: Vec<(Cow<'a, str>, ParameterValue<'a>)>) -> CursorPages<'a, A, H, R>
+    {
+        let (params, cursor, invalid_request) = match split_params(params, &["cursor"]) {
+            Ok((params, cursor)) => (params, cursor, false),
+            Err(_) => (Vec::new(), Vec::new(), true),
+        };
+
+        CursorPages {
+            client: client,
+            method: method,
+            url: url.as_ref().to_owned(),
+            params: params,
+            next_cursor: cursor.first().and_then(|x| x.1.parse().ok()).unwrap_or(-1),
+            last_rate_limit: None,
+            wait_for_rate_limit: true,
+            invalid_request: invalid_request,
+            _response: PhantomData,
+        }
+    }
+
+    /// If `true` (default), sleeps until the rate limit window resets when no request remains.
+    /// Otherwise the next request fails with 429.
+    pub fn wait_for_rate_limit(mut self, val: bool) -> Self {
+        self.wait_for_rate_limit = val;
+        self
+    }
+
+    /// Returns the cursor which will be used for the next request. 0 means no more page.
+    pub fn next_cursor(&self) -> i64 {
+        self.next_cursor
+    }
+
+    /// Returns the iterator which flattens the pages.
+    pub fn items(self) -> CursorItems<'a, A, H, R> where R: CursorPage {
+        CursorItems {
+            pages: self,
+            buffer: Vec::new().into_iter(),
+        }
+    }
+}
+
+impl<'a, A, H, R> Iterator for CursorPages<'a, A, H, R>
+    where A: Authenticator, H: HttpHandler, R: CursorPage + DeserializeOwned
+{
+    type Item = TwitterResult<R>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next_cursor == 0 { return None; }
+
+        if self.invalid_request {
+            self.next_cursor = 0;
+            return Some(Err(TwitterError::InvalidRequest));
+        }
+
+        if self.wait_for_rate_limit {
+            wait_for_rate_limit(self.last_rate_limit);
+        }
+
+        let cursor = self.next_cursor.to_string();
+        let mut params = to_parameter_values(&self.params);
+        params.push((Cow::Borrowed("cursor"), ParameterValue::Text(Cow::Borrowed(&cursor))));
+
+        let res: TwitterResult<R> = execute_core(self.client, self.method.clone(), &self.url, params);
+
+        match res {
+            Ok(ref x) => {
+                self.next_cursor = x.object.next_cursor();
+                self.last_rate_limit = x.rate_limit;
+            }
+            // Stop paging
+            Err(_) => self.next_cursor = 0,
+        }
+
+        Some(res)
+    }
+}
+
+/// The iterator which returns the items of `CursorPages` one by one.
+pub struct CursorItems<'a, A: 'a + Authenticator, H: 'a, R: CursorPage> {
+    pages: CursorPages<'a, A, H, R>,
+    buffer: vec::IntoIter<R::Item>,
+}
+
+impl<'a, A, H, R> Iterator for CursorItems<'a, A, H, R>
+    where A: Authenticator, H: HttpHandler, R: CursorPage + DeserializeOwned
+{
+    type Item = Result<R::Item, TwitterError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(x) = self.buffer.next() {
+                return Some(Ok(x));
+            }
+
+            match self.pages.next() {
+                Some(Ok(x)) => self.buffer = x.object.into_items().into_iter(),
+                Some(Err(e)) => return Some(Err(e)),
+                None => return None,
+            }
+        }
+    }
+}
+
+/// The iterator which goes back the timeline by setting `max_id` to the oldest ID of the last page minus 1.
+///
+/// This is returned by `iter_timeline` methods of the request builders which have `max_id` and `since_id`.
+/// It stops when the page is empty, `limit` items have been returned or an item older than `until` is found.
+pub struct TimelinePages<'a, A: 'a + Authenticator, H: 'a, R: TimelinePage> {
+    client: &'a TwitterClient<A, H>,
+    method: Method,
+    url: String,
+    params: TextParams<'a>,
+    max_id: Option<i64>,
+    since_id: Option<i64>,
+    limit: Option<usize>,
+    until: Option<chrono::DateTime<chrono::FixedOffset>>,
+    count: usize,
+    buffer: vec::IntoIter<R::Item>,
+    finished: bool,
+    last_rate_limit: Option<RateLimitStatus>,
+    wait_for_rate_limit: bool,
+    invalid_request: bool,
+}
+
+impl<'a, A: Authenticator, H, R: TimelinePage> TimelinePages<'a, A, H, R> {
+    #[doc(hidden)]
+    pub fn new<U: AsRef<str>>(client: &'a TwitterClient<A, H>, method: Method, url: U,
+        params: Vec<(Cow<'a, str>, ParameterValue<'a>)>) -> TimelinePages<'a, A, H, R>
+    {
+        let (params, ids, invalid_request) = match split_params(params, &["max_id", "since_id"]) {
+            Ok((params, ids)) => (params, ids, false),
+            Err(_) => (Vec::new(), Vec::new(), true),
+        };
+        let get_id = |name| ids.iter().find(|x| x.0 == name).and_then(|x| x.1.parse().ok());
+
+        TimelinePages {
+            client: client,
+            method: method,
+            url: url.as_ref().to_owned(),
+            params: params,
+            max_id: get_id("max_id"),
+            since_id: get_id("since_id"),
+            limit: None,
+            until: None,
+            count: 0,
+            buffer: Vec::new().into_iter(),
+            finished: false,
+            last_rate_limit: None,
+            wait_for_rate_limit: true,
+            invalid_request: invalid_request,
+        }
+    }
+
+    /// Stops after returning `val` items.
+    pub fn limit(mut self, val: usize) -> Self {
+        self.limit = Some(val);
+        self
+    }
+
+    /// Stops when an item created before `val` is found.
+    pub fn until<Tz: chrono::TimeZone>(mut self, val: chrono::DateTime<Tz>) -> Self {
+        self.until = Some(val.with_timezone(&chrono::FixedOffset::east(0)));
+        self
+    }
+
+    /// If `true` (default), sleeps until the rate limit window resets when no request remains.
+    /// Otherwise the next request fails with 429.
+    pub fn wait_for_rate_limit(mut self, val: bool) -> Self {
+        self.wait_for_rate_limit = val;
+        self
+    }
+
+    /// Returns `max_id` which will be used for the next request.
+    pub fn max_id(&self) -> Option<i64> {
+        self.max_id
+    }
+
+    fn fetch(&mut self) -> TwitterResult<R> where H: HttpHandler, R: DeserializeOwned {
+        if self.invalid_request {
+            return Err(TwitterError::InvalidRequest);
+        }
+
+        if self.wait_for_rate_limit {
+            wait_for_rate_limit(self.last_rate_limit);
+        }
+
+        let max_id = self.max_id.map(|x| x.to_string());
+        let since_id = self.since_id.map(|x| x.to_string());
+        let mut params = to_parameter_values(&self.params);
+        if let Some(ref x) = max_id {
+            params.push((Cow::Borrowed("max_id"), ParameterValue::Text(Cow::Borrowed(x))));
+        }
+        if let Some(ref x) = since_id {
+            params.push((Cow::Borrowed("since_id"), ParameterValue::Text(Cow::Borrowed(x))));
+        }
+
+        execute_core(self.client, self.method.clone(), &self.url, params)
+    }
+}
+
+impl<'a, A, H, R> Iterator for TimelinePages<'a, A, H, R>
+    where A: Authenticator, H: HttpHandler, R: TimelinePage + DeserializeOwned
+{
+    type Item = Result<R::Item, TwitterError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.limit.map_or(false, |x| self.count >= x) {
+                return None;
+            }
+
+            if let Some(x) = self.buffer.next() {
+                if self.until.map_or(false, |u| x.created_at().0 < u) {
+                    self.finished = true;
+                    self.buffer = Vec::new().into_iter();
+                    return None;
+                }
+
+                self.count += 1;
+                return Some(Ok(x));
+            }
+
+            if self.finished { return None; }
+
+            let res = match self.fetch() {
+                Ok(x) => x,
+                Err(e) => {
+                    // Stop paging
+                    self.finished = true;
+                    return Some(Err(e));
+                }
+            };
+            self.last_rate_limit = res.rate_limit;
+
+            let items = res.object.into_items();
+            match items.iter().map(|x| x.id()).min() {
+                Some(min_id) if self.max_id.map_or(true, |x| min_id <= x) => self.max_id = Some(min_id - 1),
+                _ => self.finished = true,
+            }
+            self.buffer = items.into_iter();
+        }
+    }
+}
+
+/// The poller which sends the request with `since_id` set to the newest ID it has ever seen.
+///
+/// This is returned by `poll_timeline` methods of the request builders which have `max_id` and `since_id`.
+/// If more items than `count` are posted between the polls, the poller goes back with `max_id` until it reaches `since_id`.
+/// The first poll without `since_id` fetches only one page.
+pub struct TimelinePoller<'a, A: 'a + Authenticator, H: 'a, R> {
+    client: &'a TwitterClient<A, H>,
+    method: Method,
+    url: String,
+    params: TextParams<'a>,
+    since_id: Option<i64>,
+    last_rate_limit: Option<RateLimitStatus>,
+    wait_for_rate_limit: bool,
+    invalid_request: bool,
+    _response: PhantomData<R>,
+}
+
+impl<'a, A: Authenticator, H, R> TimelinePoller<'a, A, H, R> {
+    #[doc(hidden)]
+    pub fn new<U: AsRef<str>>(client: &'a TwitterClient<A, H>, method: Method, url: U,
+        params: Vec<(Cow<'a, str>, ParameterValue<'a>)>) -> TimelinePoller<'a, A, H, R>
+    {
+        // max_id makes no sense for polling
+        let (params, ids, invalid_request) = match split_params(params, &["max_id", "since_id"]) {
+            Ok((params, ids)) => (params, ids, false),
+            Err(_) => (Vec::new(), Vec::new(), true),
+        };
+
+        TimelinePoller {
+            client: client,
+            method: method,
+            url: url.as_ref().to_owned(),
+            params: params,
+            since_id: ids.iter().find(|x| x.0 == "since_id").and_then(|x| x.1.parse().ok()),
+            last_rate_limit: None,
+            wait_for_rate_limit: true,
+            invalid_request: invalid_request,
+            _response: PhantomData,
+        }
+    }
+
+    /// If `true` (default), sleeps until the rate limit window resets when no request remains.
+    /// Otherwise the next request fails with 429.
+    pub fn wait_for_rate_limit(mut self, val: bool) -> Self {
+        self.wait_for_rate_limit = val;
+        self
+    }
+
+    /// Returns `since_id` which will be used for the next poll.
+    pub fn since_id(&self) -> Option<i64> {
+        self.since_id
+    }
+
+    pub fn set_since_id(&mut self, val: Option<i64>) {
+        self.since_id = val;
+    }
+
+    /// Returns the rate limit status of the last response.
+    pub fn rate_limit(&self) -> Option<RateLimitStatus> {
+        self.last_rate_limit
+    }
+}
+
+impl<'a, A, H, R> TimelinePoller<'a, A, H, R>
+    where A: Authenticator, H: HttpHandler, R: TimelinePage + DeserializeOwned
+{
+    /// Fetches the items newer than the last poll, newest first.
+    ///
+    /// If a request fails, `since_id` is not updated so that the next poll fetches the same items again.
+    pub fn poll(&mut self) -> Result<Vec<R::Item>, TwitterError> {
+        if self.invalid_request {
+            return Err(TwitterError::InvalidRequest);
+        }
+
+        let since_id = self.since_id.map(|x| x.to_string());
+        let mut max_id: Option<i64> = None;
+        let mut items = Vec::new();
+
+        loop {
+            if self.wait_for_rate_limit {
+                wait_for_rate_limit(self.last_rate_limit);
+            }
+
+            let max_id_str = max_id.map(|x| x.to_string());
+            let mut params = to_parameter_values(&self.params);
+            if let Some(ref x) = since_id {
+                params.push((Cow::Borrowed("since_id"), ParameterValue::Text(Cow::Borrowed(x))));
+            }
+            if let Some(ref x) = max_id_str {
+                params.push((Cow::Borrowed("max_id"), ParameterValue::Text(Cow::Borrowed(x))));
+            }
+
+            let res: TwitterResponse<R> = try!(execute_core(self.client, self.method.clone(), &self.url, params));
+            self.last_rate_limit = res.rate_limit;
+
+            let page = res.object.into_items();
+            let min_id = page.iter().map(|x| x.id()).min();
+            items.extend(page);
+
+            match min_id {
+                // Go back until the page reaches since_id
+                Some(min_id) if self.since_id.map_or(false, |x| min_id > x + 1) && max_id.map_or(true, |x| min_id <= x) => {
+                    max_id = Some(min_id - 1)
+                }
+                _ => break,
+            }
+        }
+
+        if let Some(max_id) = items.iter().map(|x| x.id()).max() {
+            if self.since_id.map_or(true, |x| max_id > x) {
+                self.since_id = Some(max_id);
+            }
+        }
+
+        Ok(items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use std::io;
+    use hyper::Get;
+    use conn::mock_http_handler::*;
+    use conn::ParameterValue;
+    use models::CursorIds;
+    use ::{ApplicationOnlyAuthenticator, TwitterClient, TwitterError};
+    use super::*;
+
+    const IDS_URL: &'static str = "https://api.twitter.com/1.1/friends/ids.json";
+    const TIMELINE_URL: &'static str = "https://api.twitter.com/1.1/statuses/home_timeline.json";
+
+    fn client(mock: &MockHttpHandler) -> TwitterClient<ApplicationOnlyAuthenticator, &MockHttpHandler> {
+        TwitterClient::new(ApplicationOnlyAuthenticator::new("token"), mock)
+    }
+
+    /// A page of tweets created `id` minutes after 2018-01-01T00:00:00Z.
+    fn tweets(ids: &[i64]) -> String {
+        let tweets: Vec<_> = ids.iter()
+            .map(|&id| format!(
+                r#"{{"created_at":"Mon Jan 01 00:{:02}:00 +0000 2018","id":{},"retweet_count":0,"source":"web"}}"#,
+                id, id
+            ))
+            .collect();
+        format!("[{}]", tweets.join(","))
+    }
+
+    fn ids_mock() -> MockHttpHandler {
+        let mock = MockHttpHandler::new();
+        mock.add(MockRule::new(Get, IDS_URL, MockResponse::ok(r#"{"previous_cursor":0,"next_cursor":5,"ids":[1,2]}"#)).param("cursor", "-1"));
+        mock.add(MockRule::new(Get, IDS_URL, MockResponse::ok(r#"{"previous_cursor":-1,"next_cursor":7,"ids":[]}"#)).param("cursor", "5"));
+        mock.add(MockRule::new(Get, IDS_URL, MockResponse::ok(r#"{"previous_cursor":5,"next_cursor":0,"ids":[3]}"#)).param("cursor", "7"));
+        mock
+    }
+
+    #[test]
+    fn cursor_pages_test() {
+        let mock = ids_mock();
+        let c = client(&mock);
+
+        let mut req = c.friends().ids();
+        let mut pages = req.count(2).iter_pages();
+        assert_eq!(pages.next_cursor(), -1);
+        assert_eq!(pages.next().unwrap().unwrap().object.ids, vec![1, 2]);
+        assert_eq!(pages.next_cursor(), 5);
+        assert_eq!(pages.next().unwrap().unwrap().object.ids, Vec::<i64>::new());
+        assert_eq!(pages.next().unwrap().unwrap().object.ids, vec![3]);
+        // Stops at next_cursor == 0 without another request
+        assert!(pages.next().is_none());
+        assert_eq!(mock.requests().len(), 3);
+        assert!(mock.requests().iter().all(|x| x.param("count") == Some("2")));
+    }
+
+    #[test]
+    fn cursor_items_test() {
+        let mock = ids_mock();
+        let c = client(&mock);
+
+        // Starts from the given cursor
+        let ids: Vec<i64> = c.friends().ids().cursor(5).iter_pages().items().map(|x| x.unwrap()).collect();
+        assert_eq!(ids, vec![3]);
+        let ids: Vec<i64> = c.friends().ids().iter_pages().items().map(|x| x.unwrap()).collect();
+        assert_eq!(ids, vec![1, 2, 3]);
+
+        // An error stops the iteration
+        mock.clear();
+        let mut req = c.friends().ids();
+        let mut items = req.iter_pages().items();
+        assert!(matches!(items.next(), Some(Err(TwitterError::Io(_)))));
+        assert!(items.next().is_none());
+    }
+
+    #[test]
+    fn file_param_test() {
+        let mock = MockHttpHandler::new();
+        let c = client(&mock);
+        let mut file = io::Cursor::new(Vec::new());
+        let params = vec![(Cow::Borrowed("media"), ParameterValue::File(&mut file))];
+
+        let mut pages: CursorPages<_, _, CursorIds> = CursorPages::new(&c, Get, IDS_URL, params);
+        assert!(matches!(pages.next(), Some(Err(TwitterError::InvalidRequest))));
+        assert!(pages.next().is_none());
+        assert!(mock.requests().is_empty());
+    }
+
+    #[test]
+    fn timeline_pages_test() {
+        let mock = MockHttpHandler::new();
+        mock.add(MockRule::new(Get, TIMELINE_URL, MockResponse::ok(tweets(&[4]))).param("max_id", "4"));
+        mock.add(MockRule::new(Get, TIMELINE_URL, MockResponse::ok(tweets(&[]))).param("max_id", "3"));
+        mock.respond(Get, TIMELINE_URL, MockResponse::ok(tweets(&[9, 7, 5])));
+        let c = client(&mock);
+
+        let mut req = c.statuses().home_timeline();
+        let ids: Vec<i64> = req.count(3).iter_timeline().map(|x| x.unwrap().id).collect();
+        assert_eq!(ids, vec![9, 7, 5, 4]);
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[0].param("max_id"), None);
+        assert!(requests.iter().all(|x| x.param("count") == Some("3")));
+
+        // limit stops without fetching the next page
+        let mut req = c.statuses().home_timeline();
+        let ids: Vec<i64> = req.iter_timeline().limit(2).map(|x| x.unwrap().id).collect();
+        assert_eq!(ids, vec![9, 7]);
+        assert_eq!(mock.requests().len(), 4);
+
+        // until stops at the first tweet older than the time
+        let mut req = c.statuses().home_timeline();
+        let until = "2018-01-01T00:06:00Z".parse::<::chrono::DateTime<::chrono::Utc>>().unwrap();
+        let ids: Vec<i64> = req.iter_timeline().until(until).map(|x| x.unwrap().id).collect();
+        assert_eq!(ids, vec![9, 7]);
+    }
+
+    #[test]
+    fn timeline_poller_test() {
+        let mock = MockHttpHandler::new();
+        mock.add(MockRule::new(Get, TIMELINE_URL, MockResponse::ok(tweets(&[3, 2]))).param("since_id", "1").param("max_id", "4"));
+        mock.add(MockRule::new(Get, TIMELINE_URL, MockResponse::ok(tweets(&[6, 5]))).param("since_id", "1"));
+        mock.add(MockRule::new(Get, TIMELINE_URL, MockResponse::ok(tweets(&[])).rate_limit(15, 14, 0)).param("since_id", "6"));
+        mock.respond(Get, TIMELINE_URL, MockResponse::ok(tweets(&[1])));
+        let c = client(&mock);
+
+        let mut req = c.statuses().home_timeline();
+        let mut poller = req.count(2).poll_timeline();
+        // The first poll fetches only one page
+        assert_eq!(poller.poll().unwrap().iter().map(|x| x.id).collect::<Vec<_>>(), vec![1]);
+        assert_eq!(poller.since_id(), Some(1));
+
+        // Goes back until since_id
+        assert_eq!(poller.poll().unwrap().iter().map(|x| x.id).collect::<Vec<_>>(), vec![6, 5, 3, 2]);
+        assert_eq!(poller.since_id(), Some(6));
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[2].param("max_id"), Some("4"));
+
+        assert!(poller.poll().unwrap().is_empty());
+        assert_eq!(poller.since_id(), Some(6));
+        assert_eq!(poller.rate_limit().map(|x| x.remaining), Some(14));
+    }
+}
@@ -0,0 +1,255 @@
+use std::i32;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use chrono;
+use hyper::header::Bearer;
+use hyper::status::StatusCode;
+use super::TwitterClient;
+use ::TwitterError;
+use conn::*;
+use conn::rate_limit_tracker::{endpoint_path, RateLimitTracker};
+use models::*;
+
+/// The seconds to hold back a token which got 429 without `X-Rate-Limit-Reset`.
+const DEFAULT_RATE_LIMIT_WINDOW: i64 = 15 * 60;
+
+/// `TwitterClient` which spreads the requests across several tokens.
+///
+/// For each request, the token with the most remaining requests for the endpoint is used.
+/// When the server responds 429 Too Many Requests, the request is sent again with another token.
+/// Use `credentials::Credential` as `A` to mix user tokens and application-only tokens.
+///
+/// ```no_run
+/// use tweetust::*;
+/// use tweetust::clients::TwitterClientPool;
+///
+/// let handler = DefaultHttpHandler::with_https_connector().unwrap();
+/// let pool = TwitterClientPool::new(handler)
+///     .add(OAuthAuthenticator::new("API Key", "API Secret", "Access Token 1", "Access Token Secret 1"))
+///     .add(OAuthAuthenticator::new("API Key", "API Secret", "Access Token 2", "Access Token Secret 2"));
+///
+/// let ids = pool.friends().ids().screen_name("twitter").execute();
+/// ```
+pub struct TwitterClientPool<A: Authenticator, H> {
+    client: TwitterClient<PooledAuthenticator, PooledHttpHandler<A, H>>,
+}
+
+impl<A: Authenticator, H> TwitterClientPool<A, H> {
+    pub fn new(handler: H) -> TwitterClientPool<A, H> {
+        TwitterClientPool {
+            client: TwitterClient::new(PooledAuthenticator, PooledHttpHandler {
+                handler: handler,
+                entries: Vec::new(),
+                next: AtomicUsize::new(0),
+            }),
+        }
+    }
+
+    /// Adds a token to the pool.
+    pub fn add(mut self, auth: A) -> Self {
+        self.client.handler.entries.push(PoolEntry { auth: auth, tracker: RateLimitTracker::new() });
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.client.handler.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.client.handler.entries.is_empty()
+    }
+
+    pub fn authenticators(&self) -> Vec<&A> {
+        self.client.handler.entries.iter().map(|x| &x.auth).collect()
+    }
+
+    /// Returns the rate limit tracker of the `index`-th token.
+    pub fn tracker(&self, index: usize) -> Option<&RateLimitTracker> {
+        self.client.handler.entries.get(index).map(|x| &x.tracker)
+    }
+}
+
+impl<A: Authenticator, H> Deref for TwitterClientPool<A, H> {
+    type Target = TwitterClient<PooledAuthenticator, PooledHttpHandler<A, H>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.client
+    }
+}
+
+/// The placeholder authenticator of `TwitterClientPool`.
+/// The actual credentials are chosen by `PooledHttpHandler`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PooledAuthenticator;
+
+impl Authenticator for PooledAuthenticator {
+    type Scheme = Bearer;
+
+    fn create_authorization_header(&self, _: &Request) -> Option<Self::Scheme> {
+        None
+    }
+}
+
+struct PoolEntry<A> {
+    auth: A,
+    tracker: RateLimitTracker,
+}
+
+/// `HttpHandler` of `TwitterClientPool`. The authenticator given by the client is ignored.
+pub struct PooledHttpHandler<A, H> {
+    handler: H,
+    entries: Vec<PoolEntry<A>>,
+    next: AtomicUsize,
+}
+
+impl<A: Authenticator, H: HttpHandler> PooledHttpHandler<A, H> {
+    /// Returns the index of the token with the most remaining requests, skipping `tried`.
+    /// The tokens whose status is unknown are preferred so that their status is learned.
+    fn choose(&self, endpoint: &str, tried: &[usize]) -> Option<usize> {
+        let len = self.entries.len();
+        // Start from a different token each time to spread the requests among the ties
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        let mut best: Option<(usize, i32)> = None;
+
+        for i in (0..len).map(|x| (start + x) % len) {
+            if tried.contains(&i) { continue; }
+            let tracker = &self.entries[i].tracker;
+            if tracker.check(endpoint).is_err() { continue; }
+
+            let remaining = tracker.remaining(endpoint).unwrap_or(i32::MAX);
+            if best.map_or(true, |(_, x)| remaining > x) {
+                best = Some((i, remaining));
+            }
+        }
+
+        best.map(|x| x.0)
+    }
+
+    /// Returns the status of the token which will be available first.
+    fn earliest_reset(&self, endpoint: &str) -> Option<RateLimitStatus> {
+        self.entries.iter()
+            .filter_map(|x| x.tracker.check(endpoint).err())
+            .min_by_key(|x| x.reset)
+    }
+
+    fn send_with(&self, entry: &PoolEntry<A>, endpoint: &str, request: Request) -> Result<RawResponse, TwitterError> {
+        let mut scheme = entry.auth.create_authorization_header(&request);
+        if entry.auth.needs_refresh() && try!(entry.auth.refresh_credentials(scheme.as_ref(), &self.handler)) {
+            scheme = entry.auth.create_authorization_header(&request);
+        }
+
+        let retry = request.try_clone();
+        let mut res = self.handler.send_request(request, &entry.auth);
+
+        if let Err(TwitterError::ErrorResponse(ref e)) = res {
+            if e.status == StatusCode::TooManyRequests {
+                let status = e.rate_limit.unwrap_or_else(|| RateLimitStatus {
+                    limit: 0,
+                    remaining: 0,
+                    reset: chrono::Utc::now().timestamp() + DEFAULT_RATE_LIMIT_WINDOW,
+                });
+                entry.tracker.update(endpoint, RateLimitStatus { remaining: 0, ..status });
+                return res;
+            }
+        }
+
+        let rate_limit = match res {
+            Ok(ref x) => x.rate_limit,
+            Err(TwitterError::ErrorResponse(ref x)) => x.rate_limit,
+            Err(_) => None,
+        };
+        if let Some(x) = rate_limit {
+            entry.tracker.update(endpoint, x);
+        }
+
+        let unauthorized = match res {
+            Err(TwitterError::ErrorResponse(ref e)) => e.status == StatusCode::Unauthorized,
+            _ => false,
+        };
+        if unauthorized {
+            if let Some(retry) = retry {
+                if try!(entry.auth.refresh_credentials(scheme.as_ref(), &self.handler)) {
+                    res = self.handler.send_request(retry, &entry.auth);
+                }
+            }
+        }
+
+        res
+    }
+}
+
+impl<A: Authenticator, H: HttpHandler> HttpHandler for PooledHttpHandler<A, H> {
+    fn send_request<A2: Authenticator>(&self, request: Request, _: &A2) -> Result<RawResponse, TwitterError> {
+        let endpoint = endpoint_path(&request.url);
+        let mut tried = Vec::new();
+        let mut request = Some(request);
+
+        loop {
+            let index = match self.choose(&endpoint, &tried) {
+                Some(x) => x,
+                None => return Err(match self.earliest_reset(&endpoint) {
+                    Some(x) => TwitterError::RateLimitExceeded(x),
+                    None => TwitterError::InvalidRequest,
+                }),
+            };
+            tried.push(index);
+
+            let req = request.take().unwrap();
+            let next = req.try_clone();
+
+            let res = self.send_with(&self.entries[index], &endpoint, req);
+            let rate_limited = match res {
+                Err(TwitterError::ErrorResponse(ref e)) => e.status == StatusCode::TooManyRequests,
+                _ => false,
+            };
+
+            // Fail over to another token if the request can be sent again
+            if rate_limited && next.is_some() && self.choose(&endpoint, &tried).is_some() {
+                request = next;
+                continue;
+            }
+
+            return res;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::Get;
+    use hyper::status::StatusCode;
+    use conn::mock_http_handler::*;
+    use ::{ApplicationOnlyAuthenticator, TwitterError};
+    use super::*;
+
+    const URL: &'static str = "https://api.twitter.com/1.1/friends/ids.json";
+    const BODY: &'static str = r#"{"previous_cursor":0,"next_cursor":0,"ids":[1]}"#;
+
+    #[test]
+    fn failover_test() {
+        let reset = chrono::Utc::now().timestamp() + 60;
+        let mock = MockHttpHandler::new();
+        mock.add(MockRule::new(Get, URL, MockResponse::ok(BODY).rate_limit(15, 3, reset)).times(1));
+        mock.add(MockRule::new(Get, URL, MockResponse::ok(BODY).rate_limit(15, 10, reset)).times(1));
+        mock.add(MockRule::new(Get, URL, MockResponse::with_status(StatusCode::TooManyRequests, "").rate_limit(15, 0, reset)).times(1));
+        mock.respond(Get, URL, MockResponse::ok(BODY).rate_limit(15, 2, reset));
+
+        let pool = TwitterClientPool::new(&mock)
+            .add(ApplicationOnlyAuthenticator::new("a"))
+            .add(ApplicationOnlyAuthenticator::new("b"));
+
+        // Each token is tried once because their status is unknown
+        pool.friends().ids().execute().unwrap();
+        pool.friends().ids().execute().unwrap();
+
+        // "b" has more budget, but it gets 429 and the request is sent again with "a"
+        assert_eq!(pool.friends().ids().execute().unwrap().object.ids, vec![1]);
+        let auths: Vec<_> = mock.requests().into_iter().map(|x| x.authorization.unwrap()).collect();
+        assert_eq!(auths, vec!["Bearer a", "Bearer b", "Bearer b", "Bearer a"]);
+        assert!(pool.tracker(1).unwrap().check("/friends/ids").is_err());
+        assert_eq!(pool.tracker(0).unwrap().remaining("/friends/ids"), Some(2));
+
+        pool.tracker(0).unwrap().update("/friends/ids", RateLimitStatus { limit: 15, remaining: 0, reset: reset });
+        assert!(matches!(pool.friends().ids().execute(), Err(TwitterError::RateLimitExceeded(_))));
+    }
+}
@@ -2,7 +2,7 @@ use std::borrow::Cow;
 use hyper::header::Bearer;
 use super::*;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ApplicationOnlyAuthenticator<'a> {
     pub access_token: Cow<'a, str>
 }
@@ -0,0 +1,602 @@
+//! `HttpHandler`s for testing the code which uses `TwitterClient` without connecting to Twitter.
+
+use std::borrow::Cow;
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use base64;
+use hyper::header;
+use hyper::method::Method;
+use hyper::mime::Mime;
+use hyper::status::StatusCode;
+use serde_json;
+use super::*;
+
+#[cfg(feature = "futures")]
+use futures::future;
+
+/// The snapshot of `Request` which a mock handler received.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RecordedRequest {
+    pub method: Method,
+    /// The URL without the query string.
+    pub url: String,
+    /// The text parameters in the query string or the body.
+    pub params: Vec<(String, String)>,
+    /// The file parameters of multipart/form-data.
+    pub files: Vec<(String, Vec<u8>)>,
+    /// The body of `RequestContent::Stream` or `RequestContent::Json`.
+    pub body: Option<Vec<u8>>,
+    /// The value of Authorization header.
+    pub authorization: Option<String>,
+}
+
+impl RecordedRequest {
+    fn new<A: Authenticator>(request: &Request, auth: &A) -> Result<RecordedRequest, TwitterError> {
+        let mut url = request.url.clone();
+        let mut params: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+        url.set_query(None);
+        url.set_fragment(None);
+
+        match request.content {
+            RequestContent::WwwForm(ref x) => {
+                params.extend(x.iter().map(|&(ref k, ref v)| (k.as_ref().to_owned(), v.as_ref().to_owned())));
+            }
+            RequestContent::MultipartFormData(ref x) => {
+                for &(ref k, ref v) in x {
+                    if let ParameterValue::Text(ref v) = *v {
+                        params.push((k.as_ref().to_owned(), v.as_ref().to_owned()));
+                    }
+                }
+            }
+            RequestContent::None | RequestContent::Stream(_) | RequestContent::Json(_) => (),
+        }
+
+        let authorization = try!(auth.try_create_authorization_header(request)).map(|s| {
+            let mut headers = header::Headers::new();
+            headers.set(header::Authorization(s));
+            headers.iter().next().map(|x| x.value_string()).unwrap_or_default()
+        });
+
+        Ok(RecordedRequest {
+            method: request.method.clone(),
+            url: url.into_string(),
+            params: params,
+            files: Vec::new(),
+            body: None,
+            authorization: authorization,
+        })
+    }
+
+    /// Reads the files and the stream in the request.
+    fn read_content(&mut self, content: RequestContent) -> io::Result<()> {
+        match content {
+            RequestContent::MultipartFormData(x) => {
+                for (k, v) in x {
+                    if let ParameterValue::File(r) = v {
+                        let mut buf = Vec::new();
+                        try!(r.read_to_end(&mut buf));
+                        self.files.push((k.into_owned(), buf));
+                    }
+                }
+            }
+            RequestContent::Stream(x) => {
+                let mut buf = Vec::new();
+                try!(x.content.read_to_end(&mut buf));
+                self.body = Some(buf);
+            }
+            RequestContent::Json(x) => self.body = Some(x.into_bytes()),
+            RequestContent::None | RequestContent::WwwForm(_) => (),
+        }
+        Ok(())
+    }
+
+    /// Returns the value of the first parameter named `key`.
+    pub fn param(&self, key: &str) -> Option<&str> {
+        self.params.iter().find(|x| x.0 == key).map(|x| x.1.as_ref())
+    }
+}
+
+/// The content whose files and stream have been read into memory,
+/// so that `RecordingHttpHandler` can record it and send it with the inner handler.
+enum BufferedContent<'a> {
+    None,
+    WwwForm(Cow<'a, [(Cow<'a, str>, Cow<'a, str>)]>),
+    MultipartFormData(Vec<(Cow<'a, str>, BufferedValue<'a>)>),
+    Stream(Mime, io::Cursor<Vec<u8>>),
+    Json(String),
+}
+
+enum BufferedValue<'a> {
+    Text(Cow<'a, str>),
+    File(io::Cursor<Vec<u8>>),
+}
+
+impl<'a> BufferedContent<'a> {
+    fn read(content: RequestContent<'a>) -> io::Result<BufferedContent<'a>> {
+        fn read_all(r: &mut io::Read) -> io::Result<io::Cursor<Vec<u8>>> {
+            let mut buf = Vec::new();
+            try!(r.read_to_end(&mut buf));
+            Ok(io::Cursor::new(buf))
+        }
+
+        Ok(match content {
+            RequestContent::None => BufferedContent::None,
+            RequestContent::WwwForm(x) => BufferedContent::WwwForm(x),
+            RequestContent::MultipartFormData(x) => {
+                let mut values = Vec::with_capacity(x.len());
+                for (k, v) in x {
+                    values.push((k, match v {
+                        ParameterValue::Text(v) => BufferedValue::Text(v),
+                        ParameterValue::File(r) => BufferedValue::File(try!(read_all(r))),
+                    }));
+                }
+                BufferedContent::MultipartFormData(values)
+            }
+            RequestContent::Stream(x) => BufferedContent::Stream(x.content_type, try!(read_all(x.content))),
+            RequestContent::Json(x) => BufferedContent::Json(x),
+        })
+    }
+
+    /// Creates the content which reads the buffers from the beginning.
+    fn as_content(&mut self) -> RequestContent {
+        match *self {
+            BufferedContent::None => RequestContent::None,
+            BufferedContent::WwwForm(ref x) => RequestContent::WwwForm(Cow::Borrowed(x.as_ref())),
+            BufferedContent::MultipartFormData(ref mut x) => RequestContent::MultipartFormData(
+                x.iter_mut()
+                    .map(|&mut (ref k, ref mut v)| (Cow::Borrowed(k.as_ref()), match *v {
+                        BufferedValue::Text(ref v) => ParameterValue::Text(Cow::Borrowed(v.as_ref())),
+                        BufferedValue::File(ref mut r) => {
+                            r.set_position(0);
+                            ParameterValue::File(r)
+                        }
+                    }))
+                    .collect()
+            ),
+            BufferedContent::Stream(ref content_type, ref mut r) => {
+                r.set_position(0);
+                RequestContent::Stream(StreamContent {
+                    content_type: content_type.clone(),
+                    content_length: Some(r.get_ref().len() as u64),
+                    content: r,
+                })
+            }
+            BufferedContent::Json(ref x) => RequestContent::Json(x.clone()),
+        }
+    }
+}
+
+/// The response which `MockHttpHandler` returns.
+#[derive(Clone, Debug)]
+pub struct MockResponse {
+    status: StatusCode,
+    body: String,
+    rate_limit: Option<RateLimitStatus>,
+    io_error: Option<io::ErrorKind>,
+}
+
+impl MockResponse {
+    /// 200 OK with `body`.
+    pub fn ok<B: Into<String>>(body: B) -> MockResponse {
+        MockResponse::with_status(StatusCode::Ok, body)
+    }
+
+    pub fn with_status<B: Into<String>>(status: StatusCode, body: B) -> MockResponse {
+        MockResponse {
+            status: status,
+            body: body.into(),
+            rate_limit: None,
+            io_error: None,
+        }
+    }
+
+    /// Fails with `TwitterError::Io` as if the connection was lost.
+    pub fn io_error(kind: io::ErrorKind) -> MockResponse {
+        MockResponse {
+            io_error: Some(kind),
+            .. MockResponse::with_status(StatusCode::Ok, "")
+        }
+    }
+
+    /// Sets the values of `X-Rate-Limit-*` headers.
+    pub fn rate_limit(mut self, limit: i32, remaining: i32, reset: i64) -> Self {
+        self.rate_limit = Some(RateLimitStatus { limit: limit, remaining: remaining, reset: reset });
+        self
+    }
+
+    fn to_result(&self) -> Result<RawResponse, TwitterError> {
+        match self.io_error {
+            Some(kind) => Err(TwitterError::Io(io::Error::new(kind, "mock I/O error"))),
+            None => to_twitter_result(self.status, self.rate_limit, self.body.clone()),
+        }
+    }
+}
+
+/// The pair of the condition and the response for `MockHttpHandler`.
+#[derive(Clone, Debug)]
+pub struct MockRule {
+    method: Method,
+    url: String,
+    params: Vec<(String, String)>,
+    times: Option<usize>,
+    response: MockResponse,
+}
+
+impl MockRule {
+    /// Matches the requests to `url` ignoring the query string.
+    pub fn new<U: Into<String>>(method: Method, url: U, response: MockResponse) -> MockRule {
+        MockRule {
+            method: method,
+            url: url.into(),
+            params: Vec::new(),
+            times: None,
+            response: response,
+        }
+    }
+
+    /// Matches only the requests which have this parameter.
+    pub fn param<K: Into<String>, V: Into<String>>(mut self, key: K, val: V) -> Self {
+        self.params.push((key.into(), val.into()));
+        self
+    }
+
+    /// Removes this rule after it matches `n` times. The rule never matches if `n` is 0.
+    pub fn times(mut self, n: usize) -> Self {
+        self.times = Some(n);
+        self
+    }
+
+    fn matches(&self, request: &RecordedRequest) -> bool {
+        self.times != Some(0)
+            && self.method == request.method && self.url == request.url
+            && self.params.iter().all(|p| request.params.contains(p))
+    }
+}
+
+/// `HttpHandler` which returns the responses registered by `MockRule`s and records the requests.
+///
+/// The rules are tried in the order they are added. If no rule matches, `TwitterError::Io` with `NotFound` is returned.
+#[derive(Debug, Default)]
+pub struct MockHttpHandler {
+    rules: Mutex<Vec<MockRule>>,
+    requests: Mutex<Vec<RecordedRequest>>,
+}
+
+impl MockHttpHandler {
+    pub fn new() -> MockHttpHandler {
+        Default::default()
+    }
+
+    pub fn add(&self, rule: MockRule) -> &Self {
+        self.rules.lock().unwrap().push(rule);
+        self
+    }
+
+    /// Adds a rule which matches any request to `url` with `method`.
+    pub fn respond<U: Into<String>>(&self, method: Method, url: U, response: MockResponse) -> &Self {
+        self.add(MockRule::new(method, url, response))
+    }
+
+    /// Returns the requests received so far.
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    pub fn last_request(&self) -> Option<RecordedRequest> {
+        self.requests.lock().unwrap().last().cloned()
+    }
+
+    /// Removes all rules and recorded requests.
+    pub fn clear(&self) {
+        self.rules.lock().unwrap().clear();
+        self.requests.lock().unwrap().clear();
+    }
+}
+
+impl HttpHandler for MockHttpHandler {
+    fn send_request<A: Authenticator>(&self, request: Request, auth: &A) -> Result<RawResponse, TwitterError> {
+        let mut recorded = try!(RecordedRequest::new(&request, auth));
+        try!(recorded.read_content(request.content));
+
+        let response = {
+            let mut rules = self.rules.lock().unwrap();
+            let index = rules.iter().position(|x| x.matches(&recorded));
+            index.map(|i| {
+                let response = rules[i].response.clone();
+                let remove = match rules[i].times {
+                    Some(ref mut n) => { *n -= 1; *n == 0 }
+                    None => false,
+                };
+                if remove { rules.remove(i); }
+                response
+            })
+        };
+
+        let msg = format!("no mock rule matches {} {}", recorded.method, recorded.url);
+        self.requests.lock().unwrap().push(recorded);
+
+        match response {
+            Some(x) => x.to_result(),
+            None => Err(TwitterError::Io(io::Error::new(io::ErrorKind::NotFound, msg))),
+        }
+    }
+}
+
+/// Resolves immediately with the same response as `send_request`.
+#[cfg(feature = "futures")]
+impl AsyncHttpHandler for MockHttpHandler {
+    fn send_request_async<'a, A: Authenticator>(&'a self, request: Request<'a>, auth: &A) -> FutureRawResponse<'a> {
+        Box::new(future::result(self.send_request(request, auth)))
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Exchange {
+    method: String,
+    url: String,
+    params: Vec<(String, String)>,
+    /// The files of multipart/form-data encoded in Base64.
+    #[serde(default)]
+    files: Vec<(String, String)>,
+    /// The body of a stream or JSON encoded in Base64.
+    #[serde(default)]
+    body_sent: Option<String>,
+    status: u16,
+    rate_limit: Option<RateLimitStatus>,
+    body: String,
+}
+
+impl Exchange {
+    fn matches(&self, request: &RecordedRequest) -> bool {
+        self.method == request.method.as_ref() && self.url == request.url && sorted(&self.params) == sorted(&request.params)
+            && self.files == encode_files(request) && self.body_sent == encode_body(request)
+    }
+
+    fn to_result(&self) -> Result<RawResponse, TwitterError> {
+        to_twitter_result(StatusCode::from_u16(self.status), self.rate_limit, self.body.clone())
+    }
+}
+
+fn encode_files(request: &RecordedRequest) -> Vec<(String, String)> {
+    request.files.iter().map(|&(ref k, ref v)| (k.clone(), base64::encode(v))).collect()
+}
+
+fn encode_body(request: &RecordedRequest) -> Option<String> {
+    request.body.as_ref().map(base64::encode)
+}
+
+fn sorted(params: &[(String, String)]) -> Vec<&(String, String)> {
+    let mut x: Vec<_> = params.iter().collect();
+    x.sort();
+    x
+}
+
+/// `HttpHandler` which sends the requests with the inner handler and saves the exchanges to a JSON fixture file.
+///
+/// The files and the stream are read into memory before sending. Authorization headers are not saved. The saved file can be played back by `ReplayHttpHandler`.
+pub struct RecordingHttpHandler<H: HttpHandler> {
+    handler: H,
+    path: PathBuf,
+    exchanges: Mutex<Vec<Exchange>>,
+}
+
+impl<H: HttpHandler> RecordingHttpHandler<H> {
+    /// Creates a handler which overwrites the file at `path`.
+    pub fn new<P: AsRef<Path>>(handler: H, path: P) -> RecordingHttpHandler<H> {
+        RecordingHttpHandler {
+            handler: handler,
+            path: path.as_ref().to_owned(),
+            exchanges: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn into_inner(self) -> H {
+        self.handler
+    }
+
+    fn save(&self, exchanges: &[Exchange]) -> Result<(), TwitterError> {
+        let file = try!(File::create(&self.path));
+        serde_json::to_writer_pretty(file, exchanges)
+            .map_err(|e| TwitterError::Io(io::Error::new(io::ErrorKind::Other, e)))
+    }
+}
+
+impl<H: HttpHandler> HttpHandler for RecordingHttpHandler<H> {
+    fn send_request<A: Authenticator>(&self, request: Request, auth: &A) -> Result<RawResponse, TwitterError> {
+        let Request { method, url, content } = request;
+        let mut content = try!(BufferedContent::read(content));
+
+        let mut recorded = try!(RecordedRequest::new(&Request { method: method.clone(), url: url.clone(), content: content.as_content() }, auth));
+        try!(recorded.read_content(content.as_content()));
+
+        let res = self.handler.send_request(Request { method: method, url: url, content: content.as_content() }, auth);
+
+        let (status, rate_limit, body) = match res {
+            Ok(ref x) => (x.status.to_u16(), x.rate_limit, x.raw_response.clone()),
+            Err(TwitterError::ErrorResponse(ref x)) => (x.status.to_u16(), x.rate_limit, x.raw_response.clone()),
+            // The response has not been received
+            Err(_) => return res,
+        };
+
+        let files = encode_files(&recorded);
+        let body_sent = encode_body(&recorded);
+        let mut exchanges = self.exchanges.lock().unwrap();
+        exchanges.push(Exchange {
+            method: recorded.method.to_string(),
+            url: recorded.url,
+            params: recorded.params,
+            files: files,
+            body_sent: body_sent,
+            status: status,
+            rate_limit: rate_limit,
+            body: body,
+        });
+        try!(self.save(&exchanges));
+
+        res
+    }
+}
+
+/// `HttpHandler` which returns the responses saved by `RecordingHttpHandler`.
+///
+/// Each request is answered with the first unused exchange whose method, URL and parameters are the same,
+/// so the same request gets the responses in the recorded order.
+/// If no exchange matches, `TwitterError::Io` with `NotFound` is returned.
+#[derive(Debug)]
+pub struct ReplayHttpHandler {
+    exchanges: Mutex<Vec<Option<Exchange>>>,
+    requests: Mutex<Vec<RecordedRequest>>,
+}
+
+impl ReplayHttpHandler {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<ReplayHttpHandler> {
+        let file = try!(File::open(path));
+        let exchanges: Vec<Exchange> = try!(
+            serde_json::from_reader(file).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        );
+
+        Ok(ReplayHttpHandler {
+            exchanges: Mutex::new(exchanges.into_iter().map(Some).collect()),
+            requests: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Returns the requests received so far.
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Returns the number of the exchanges which have not been played back.
+    pub fn remaining(&self) -> usize {
+        self.exchanges.lock().unwrap().iter().filter(|x| x.is_some()).count()
+    }
+}
+
+impl HttpHandler for ReplayHttpHandler {
+    fn send_request<A: Authenticator>(&self, request: Request, auth: &A) -> Result<RawResponse, TwitterError> {
+        let mut recorded = try!(RecordedRequest::new(&request, auth));
+        try!(recorded.read_content(request.content));
+
+        let exchange = {
+            let mut exchanges = self.exchanges.lock().unwrap();
+            exchanges.iter_mut()
+                .find(|x| x.as_ref().map_or(false, |x| x.matches(&recorded)))
+                .and_then(|x| x.take())
+        };
+
+        let msg = format!("no fixture matches {} {}", recorded.method, recorded.url);
+        self.requests.lock().unwrap().push(recorded);
+
+        match exchange {
+            Some(x) => x.to_result(),
+            None => Err(TwitterError::Io(io::Error::new(io::ErrorKind::NotFound, msg))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use hyper::{Get, Post};
+    use hyper::status::StatusCode;
+    use ::TwitterClient;
+    use ::ApplicationOnlyAuthenticator;
+    use super::*;
+
+    #[test]
+    fn mock_test() {
+        let mock = MockHttpHandler::new();
+        mock.add(
+            MockRule::new(Get, "https://api.twitter.com/1.1/friends/ids.json", MockResponse::ok(r#"{"previous_cursor":0,"next_cursor":0,"ids":[1]}"#).rate_limit(15, 14, 0))
+                .param("count", "1")
+                .times(1)
+        );
+        mock.respond(Get, "https://api.twitter.com/1.1/statuses/show/1.json", MockResponse::with_status(StatusCode::NotFound, ""));
+
+        let client = TwitterClient::new(ApplicationOnlyAuthenticator::new("token"), &mock);
+        let res = client.friends().ids().count(1).execute().unwrap();
+        assert_eq!(res.object.ids, vec![1]);
+        assert_eq!(res.rate_limit.map(|x| x.remaining), Some(14));
+
+        // The rule has been used up
+        assert!(matches!(client.friends().ids().count(1).execute(), Err(TwitterError::Io(_))));
+        mock.add(MockRule::new(Get, "https://api.twitter.com/1.1/friends/ids.json", MockResponse::ok("")).times(0));
+        assert!(matches!(client.friends().ids().count(1).execute(), Err(TwitterError::Io(_))));
+        assert!(matches!(client.statuses().show(1).execute(), Err(TwitterError::ErrorResponse(_))));
+
+        let req = &mock.requests()[0];
+        assert_eq!(req.method, Get);
+        assert_eq!(req.param("count"), Some("1"));
+        assert_eq!(req.authorization.as_ref().map(|x| x.as_ref()), Some("Bearer token"));
+    }
+
+    #[cfg(feature = "futures")]
+    #[test]
+    fn async_mock_test() {
+        use futures::Future;
+
+        let mock = MockHttpHandler::new();
+        mock.respond(Get, "https://api.twitter.com/1.1/friends/ids.json", MockResponse::ok(r#"{"previous_cursor":0,"next_cursor":0,"ids":[1]}"#));
+        mock.respond(Get, "https://api.twitter.com/1.1/statuses/show/1.json", MockResponse::with_status(StatusCode::NotFound, ""));
+
+        let client = TwitterClient::new(ApplicationOnlyAuthenticator::new("token"), &mock);
+        assert_eq!(client.friends().ids().count(1).execute_async().wait().unwrap().object.ids, vec![1]);
+        assert!(matches!(client.statuses().show(1).execute_async().wait(), Err(TwitterError::ErrorResponse(_))));
+        assert_eq!(mock.last_request().unwrap().url, "https://api.twitter.com/1.1/statuses/show/1.json");
+
+        // The futures can be kept after the builders are dropped
+        let ids = client.friends().ids().execute_async();
+        let show = client.statuses().show(1).execute_async();
+        assert_eq!(ids.wait().unwrap().object.ids, vec![1]);
+        assert!(show.wait().is_err());
+    }
+
+    #[test]
+    fn fixture_test() {
+        let path = env::temp_dir().join(format!("tweetust-fixture-{}.json", ::std::process::id()));
+
+        let mock = MockHttpHandler::new();
+        mock.add(MockRule::new(Get, "https://api.twitter.com/1.1/friends/ids.json", MockResponse::ok(r#"{"previous_cursor":0,"next_cursor":0,"ids":[1]}"#)).times(1));
+        mock.respond(Get, "https://api.twitter.com/1.1/friends/ids.json", MockResponse::ok(r#"{"previous_cursor":0,"next_cursor":0,"ids":[2]}"#));
+
+        {
+            let client = TwitterClient::new(ApplicationOnlyAuthenticator::new("token"), RecordingHttpHandler::new(&mock, &path));
+            client.friends().ids().execute().unwrap();
+            client.friends().ids().count(2).execute().unwrap();
+        }
+
+        let replay = ReplayHttpHandler::open(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let client = TwitterClient::new(ApplicationOnlyAuthenticator::new("token"), &replay);
+        assert_eq!(client.friends().ids().count(2).execute().unwrap().object.ids, vec![2]);
+        assert_eq!(client.friends().ids().execute().unwrap().object.ids, vec![1]);
+        assert!(client.friends().ids().execute().is_err());
+        assert_eq!(replay.remaining(), 0);
+    }
+
+    #[test]
+    fn fixture_content_test() {
+        let path = env::temp_dir().join(format!("tweetust-fixture-content-{}.json", ::std::process::id()));
+        let url = "https://api.twitter.com/1.1/direct_messages/events/new.json";
+        let auth = ApplicationOnlyAuthenticator::new("token");
+        let json = |text: &str| Request::new(Post, url, RequestContent::Json(format!(r#"{{"text":"{}"}}"#, text))).unwrap();
+
+        let mock = MockHttpHandler::new();
+        mock.respond(Post, url, MockResponse::with_status(StatusCode::NoContent, ""));
+
+        {
+            let recording = RecordingHttpHandler::new(&mock, &path);
+            recording.send_request(json("a"), &auth).unwrap();
+        }
+        // The inner handler receives the body
+        assert_eq!(mock.last_request().unwrap().body, Some(br#"{"text":"a"}"#.to_vec()));
+
+        let replay = ReplayHttpHandler::open(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(replay.send_request(json("b"), &auth).is_err());
+        assert_eq!(replay.send_request(json("a"), &auth).unwrap().status, StatusCode::NoContent);
+    }
+}
@@ -4,6 +4,7 @@
 use std::any::Any;
 use std::borrow::Cow;
 use std::io::{self, Read};
+use std::time::Duration;
 use hyper::{self, header, mime, Get, Delete, Head};
 use hyper::client::Response;
 use hyper::method::Method;
@@ -14,19 +15,28 @@ use url::{percent_encoding, Url};
 use ::{parse_json, TwitterError};
 use models::*;
 
+#[cfg(feature = "futures")]
+use futures::{future, Future};
+
 #[cfg(feature = "hyper-native-tls")]
 use hyper_native_tls::NativeTlsClient;
 #[cfg(feature = "hyper-native-tls")]
 use hyper_native_tls::native_tls;
 
 pub mod application_only_authenticator;
+pub mod mock_http_handler;
 pub mod oauth_authenticator;
+pub mod rate_limit_tracker;
+pub mod retrying_http_handler;
+pub mod user_bearer_authenticator;
 
 pub enum RequestContent<'a> {
     None,
     WwwForm(Cow<'a, [(Cow<'a, str>, Cow<'a, str>)]>),
     MultipartFormData(Vec<(Cow<'a, str>, ParameterValue<'a>)>),
     Stream(StreamContent<'a>),
+    /// A JSON body, used by the endpoints which don't accept form parameters.
+    Json(String),
 }
 
 impl<'a> RequestContent<'a> {
@@ -64,6 +74,41 @@ pub struct StreamContent<'a> {
     pub content: &'a mut Read,
 }
 
+/// Wraps the body of `ParameterValue::File` or `StreamContent` to observe the progress of sending it.
+///
+/// `on_read` is called with the number of the bytes read so far.
+/// If it returns `Err`, sending the request is aborted with the error.
+pub struct ProgressReader<'a> {
+    inner: &'a mut Read,
+    bytes_read: u64,
+    on_read: &'a mut FnMut(u64) -> io::Result<()>,
+}
+
+impl<'a> ProgressReader<'a> {
+    pub fn new(inner: &'a mut Read, on_read: &'a mut FnMut(u64) -> io::Result<()>) -> ProgressReader<'a> {
+        ProgressReader {
+            inner: inner,
+            bytes_read: 0,
+            on_read: on_read,
+        }
+    }
+
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+}
+
+impl<'a> Read for ProgressReader<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = try!(self.inner.read(buf));
+        if n > 0 {
+            self.bytes_read += n as u64;
+            try!((self.on_read)(self.bytes_read));
+        }
+        Ok(n)
+    }
+}
+
 pub struct Request<'a> {
     pub method: Method,
     pub url: Url,
@@ -94,11 +139,51 @@ impl<'a> Request<'a> {
 
         Ok(Request { method: method, url: request_url, content: content })
     }
+
+    /// Duplicates the request so that it can be sent again.
+    /// Returns `None` if the content is a multipart or a stream, which cannot be read twice.
+    pub fn try_clone(&self) -> Option<Request<'a>> {
+        let content = match self.content {
+            RequestContent::None => RequestContent::None,
+            RequestContent::WwwForm(ref params) => RequestContent::WwwForm(params.clone()),
+            RequestContent::Json(ref body) => RequestContent::Json(body.clone()),
+            RequestContent::MultipartFormData(_) | RequestContent::Stream(_) => return None,
+        };
+
+        Some(Request { method: self.method.clone(), url: self.url.clone(), content: content })
+    }
 }
 
 pub trait Authenticator {
     type Scheme: header::Scheme + Any;
     fn create_authorization_header(&self, request: &Request) -> Option<Self::Scheme>;
+
+    /// Creates the Authorization header, or returns the error which prevented signing the request.
+    /// `HttpHandler`s should call this instead of `create_authorization_header`.
+    fn try_create_authorization_header(&self, request: &Request) -> Result<Option<Self::Scheme>, TwitterError> {
+        Ok(self.create_authorization_header(request))
+    }
+
+    /// Returns true if the credentials should be renewed before sending a request.
+    fn needs_refresh(&self) -> bool {
+        false
+    }
+
+    /// Renews the credentials after the server responded 401 Unauthorized.
+    /// `rejected` is the Authorization header of the failed request. If the credentials have been renewed
+    /// since it was created, they should not be renewed again.
+    /// Returns true if the request should be sent again.
+    fn refresh_credentials<H: HttpHandler>(&self, _rejected: Option<&Self::Scheme>, _handler: &H) -> Result<bool, TwitterError> {
+        Ok(false)
+    }
+
+    /// The non-blocking version of `refresh_credentials`.
+    #[cfg(feature = "futures")]
+    fn refresh_credentials_async<'a, H: AsyncHttpHandler>(&'a self, _rejected: Option<&Self::Scheme>, _handler: &'a H)
+        -> Box<Future<Item = bool, Error = TwitterError> + 'a>
+    {
+        Box::new(future::ok(false))
+    }
 }
 
 pub trait HttpHandler {
@@ -112,18 +197,68 @@ impl<'a, H: HttpHandler + ?Sized> HttpHandler for &'a H {
     }
 }
 
+/// `HttpHandler` which can pass the body of the response to the caller without reading it to the end.
+/// This is used by the Streaming API.
+pub trait StreamingHttpHandler {
+    type Stream: Read;
+
+    /// Sends a request and returns the body of the successful response.
+    /// Reading the body should fail if nothing arrives for a while, so that a stalled stream is reconnected.
+    fn open_stream<A: Authenticator>(&self, request: Request, auth: &A) -> Result<Self::Stream, TwitterError>;
+}
+
+impl<'a, H: StreamingHttpHandler + ?Sized> StreamingHttpHandler for &'a H {
+    type Stream = H::Stream;
+
+    fn open_stream<A: Authenticator>(&self, request: Request, auth: &A) -> Result<Self::Stream, TwitterError> {
+        (**self).open_stream(request, auth)
+    }
+}
+
+/// The future returned by `AsyncHttpHandler`.
+#[cfg(feature = "futures")]
+pub type FutureRawResponse<'a> = Box<Future<Item = RawResponse, Error = TwitterError> + 'a>;
+
+/// The non-blocking version of `HttpHandler`.
+///
+/// Implement this trait on a futures-based transport to use `execute_async` methods of request builders.
+#[cfg(feature = "futures")]
+pub trait AsyncHttpHandler {
+    /// Sends a request and returns a future which resolves to the parsed response.
+    fn send_request_async<'a, A: Authenticator>(&'a self, request: Request<'a>, auth: &A) -> FutureRawResponse<'a>;
+}
+
+#[cfg(feature = "futures")]
+impl<'b, H: AsyncHttpHandler + ?Sized> AsyncHttpHandler for &'b H {
+    fn send_request_async<'a, A: Authenticator>(&'a self, request: Request<'a>, auth: &A) -> FutureRawResponse<'a> {
+        (**self).send_request_async(request, auth)
+    }
+}
+
 pub type DefaultHttpsConnector = hyper::client::Pool<hyper::net::HttpsConnector<NativeTlsClient>>;
 
+/// Twitter sends a blank line every 30 seconds to keep a stream alive,
+/// so a stream which sends nothing for this long is regarded as stalled.
+pub const DEFAULT_STREAM_READ_TIMEOUT_SECS: u64 = 90;
+
 pub struct DefaultHttpHandler<C: hyper::net::NetworkConnector> {
     connector: C,
+    stream_read_timeout: Option<Duration>,
 }
 
 impl<C: hyper::net::NetworkConnector> DefaultHttpHandler<C> {
     pub fn new(connector: C) -> DefaultHttpHandler<C> {
         DefaultHttpHandler {
             connector: connector,
+            stream_read_timeout: Some(Duration::from_secs(DEFAULT_STREAM_READ_TIMEOUT_SECS)),
         }
     }
+
+    /// Sets the read timeout of the streams opened by `open_stream`.
+    /// Reading a stalled stream fails with `WouldBlock` or `TimedOut` after this.
+    pub fn set_stream_read_timeout(&mut self, dur: Option<Duration>) {
+        self.stream_read_timeout = dur;
+    }
 }
 
 #[cfg(feature = "hyper-native-tls")]
@@ -139,13 +274,15 @@ impl DefaultHttpHandler<DefaultHttpsConnector> {
     }
 }
 
-impl<C: hyper::net::NetworkConnector> HttpHandler for DefaultHttpHandler<C> {
-    fn send_request<A: Authenticator>(&self, request: Request, auth: &A) -> Result<RawResponse, TwitterError> {
+impl<C: hyper::net::NetworkConnector> DefaultHttpHandler<C> {
+    fn send<A: Authenticator>(&self, request: Request, auth: &A, read_timeout: Option<Duration>) -> Result<Response, TwitterError> {
         use std::io::Write;
 
-        let scheme = auth.create_authorization_header(&request);
+        let scheme = auth.try_create_authorization_header(&request)?;
         let body;
         let mut req = hyper::client::Request::with_connector(request.method, request.url, &self.connector)?;
+        // Always set so that a pooled connection does not keep the timeout of a stream
+        req.set_read_timeout(read_timeout)?;
 
         if let Some(s) = scheme {
             req.headers_mut().set(header::Authorization(s));
@@ -193,9 +330,49 @@ impl<C: hyper::net::NetworkConnector> HttpHandler for DefaultHttpHandler<C> {
                 io::copy(s.content, &mut req)?;
                 req.send()
             }
+            RequestContent::Json(ref body) => {
+                {
+                    let mut headers = req.headers_mut();
+                    headers.set(header::ContentLength(body.len() as u64));
+                    headers.set(header::ContentType(mime::Mime(
+                        mime::TopLevel::Application,
+                        mime::SubLevel::Json,
+                        Vec::new()
+                    )));
+                }
+                let mut req = req.start()?;
+                req.write_all(body.as_bytes())?;
+                req.send()
+            }
         };
 
-        read_to_twitter_result(res?)
+        Ok(res?)
+    }
+}
+
+impl<C: hyper::net::NetworkConnector> HttpHandler for DefaultHttpHandler<C> {
+    fn send_request<A: Authenticator>(&self, request: Request, auth: &A) -> Result<RawResponse, TwitterError> {
+        read_to_twitter_result(self.send(request, auth, None)?)
+    }
+}
+
+impl<C: hyper::net::NetworkConnector> StreamingHttpHandler for DefaultHttpHandler<C> {
+    type Stream = Response;
+
+    fn open_stream<A: Authenticator>(&self, request: Request, auth: &A) -> Result<Response, TwitterError> {
+        let res = self.send(request, auth, self.stream_read_timeout)?;
+        let status = res.status;
+        match status.class() {
+            StatusClass::Success => Ok(res),
+            _ => match read_to_twitter_result(res) {
+                Err(e) => Err(e),
+                // read_to_twitter_result should have failed with ErrorResponse
+                Ok(_) => Err(TwitterError::Io(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("the stream was not opened: {}", status)
+                ))),
+            },
+        }
     }
 }
 
@@ -228,29 +405,41 @@ struct InternalErrorResponse {
 
 /// Parses the rate limit headers and returns.
 pub fn read_to_twitter_result(mut res: Response) -> Result<RawResponse, TwitterError> {
-    // Parse headers
-    let limit = res.headers.get_raw("X-Rate-Limit-Limit")
+    let rate_limit = parse_rate_limit_headers(&res.headers);
+
+    let mut body = String::new();
+    try!(res.read_to_string(&mut body));
+
+    to_twitter_result(res.status, rate_limit, body)
+}
+
+/// Reads `X-Rate-Limit-*` headers.
+pub fn parse_rate_limit_headers(headers: &header::Headers) -> Option<RateLimitStatus> {
+    let limit = headers.get_raw("X-Rate-Limit-Limit")
         .and_then(|x| x.first())
         .and_then(|x| (&String::from_utf8_lossy(&x[..])[..]).parse().ok());
-    let remaining = res.headers.get_raw("X-Rate-Limit-Remaining")
+    let remaining = headers.get_raw("X-Rate-Limit-Remaining")
         .and_then(|x| x.first())
         .and_then(|x| (&String::from_utf8_lossy(&x[..])[..]).parse().ok());
-    let reset = res.headers.get_raw("X-Rate-Limit-Reset")
+    let reset = headers.get_raw("X-Rate-Limit-Reset")
         .and_then(|x| x.first())
         .and_then(|x| (&String::from_utf8_lossy(&x[..])[..]).parse().ok());
-    let rate_limit = limit.and(remaining).and(reset)
+    limit.and(remaining).and(reset)
         .map(|_| RateLimitStatus {
             limit: limit.unwrap(),
             remaining: remaining.unwrap(),
             reset: reset.unwrap()
-        });
-
-    let mut body = String::new();
-    try!(res.read_to_string(&mut body));
+        })
+}
 
-    match res.status.class() {
+/// Makes `RawResponse` or `ErrorResponse` from the status code and the body.
+///
+/// This is useful when you implement `HttpHandler` or `AsyncHttpHandler` on your own transport.
+pub fn to_twitter_result(status: hyper::status::StatusCode, rate_limit: Option<RateLimitStatus>, body: String) -> Result<RawResponse, TwitterError> {
+    match status.class() {
         // 2xx
         StatusClass::Success => Ok(RawResponse {
+            status: status,
             raw_response: body,
             rate_limit: rate_limit,
         }),
@@ -259,7 +448,7 @@ pub fn read_to_twitter_result(mut res: Response) -> Result<RawResponse, TwitterE
             let dec = parse_json::<InternalErrorResponse>(&body);
             let errors = dec.ok().and_then(|x| x.errors.or(x.error));
             Err(TwitterError::ErrorResponse(ErrorResponse {
-                status: res.status,
+                status: status,
                 errors: errors,
                 raw_response: body,
                 rate_limit: rate_limit,
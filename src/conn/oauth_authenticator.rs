@@ -1,17 +1,226 @@
 use std::borrow::Cow;
+use std::error::Error;
 use std::fmt;
 use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use base64;
 use hyper::header;
-use oauthcli::{OAuthAuthorizationHeader, OAuthAuthorizationHeaderBuilder, ParseOAuthAuthorizationHeaderError, SignatureMethod};
+use oauthcli::{self, OAuthAuthorizationHeader, OAuthAuthorizationHeaderBuilder, ParseOAuthAuthorizationHeaderError, SignatureMethod};
+use rand::Rng;
+use rand::distributions::Alphanumeric;
+use rand::rngs::OsRng;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use url::percent_encoding::utf8_percent_encode;
 use super::*;
 
+/// Computes RSA-SHA1 signatures with a private key.
+pub trait RsaSha1Signer: Send + Sync {
+    /// Signs `data` with RSASSA-PKCS1-v1_5 using SHA-1.
+    fn sign(&self, data: &[u8]) -> Result<Vec<u8>, Box<Error + Send + Sync>>;
+}
+
+#[cfg(feature = "openssl")]
+impl RsaSha1Signer for ::openssl::pkey::PKey<::openssl::pkey::Private> {
+    fn sign(&self, data: &[u8]) -> Result<Vec<u8>, Box<Error + Send + Sync>> {
+        let mut signer = ::openssl::sign::Signer::new(::openssl::hash::MessageDigest::sha1(), self)?;
+        signer.update(data)?;
+        Ok(signer.sign_to_vec()?)
+    }
+}
+
+/// The method to sign requests.
+#[derive(Clone)]
+pub enum OAuthSignatureMethod {
+    HmacSha1,
+    Plaintext,
+    RsaSha1(Arc<RsaSha1Signer>),
+}
+
+impl OAuthSignatureMethod {
+    pub fn rsa_sha1<S: RsaSha1Signer + 'static>(signer: S) -> OAuthSignatureMethod {
+        OAuthSignatureMethod::RsaSha1(Arc::new(signer))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            OAuthSignatureMethod::HmacSha1 => "HMAC-SHA1",
+            OAuthSignatureMethod::Plaintext => "PLAINTEXT",
+            OAuthSignatureMethod::RsaSha1(_) => "RSA-SHA1",
+        }
+    }
+}
+
+impl Default for OAuthSignatureMethod {
+    fn default() -> OAuthSignatureMethod {
+        OAuthSignatureMethod::HmacSha1
+    }
+}
+
+impl fmt::Debug for OAuthSignatureMethod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Serialized as the name. The signer of RSA-SHA1 is not serialized.
+impl Serialize for OAuthSignatureMethod {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+/// RSA-SHA1 cannot be deserialized because the signer is not serialized.
+impl<'de> Deserialize<'de> for OAuthSignatureMethod {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = try!(String::deserialize(deserializer));
+        match &name[..] {
+            "HMAC-SHA1" => Ok(OAuthSignatureMethod::HmacSha1),
+            "PLAINTEXT" => Ok(OAuthSignatureMethod::Plaintext),
+            "RSA-SHA1" => Err(de::Error::custom("RSA-SHA1 requires a signer, which cannot be deserialized")),
+            x => Err(de::Error::unknown_variant(x, &["HMAC-SHA1", "PLAINTEXT", "RSA-SHA1"])),
+        }
+    }
+}
+
+/// The parameters of the Authorization header shared by `OAuthAuthenticator` and the `oauth` module.
+pub(crate) struct OAuthHeaderParams<'a> {
+    pub consumer_key: &'a str,
+    pub consumer_secret: &'a str,
+    pub token: Option<(&'a str, &'a str)>,
+    pub callback: Option<&'a str>,
+    pub verifier: Option<&'a str>,
+    pub signature_method: &'a OAuthSignatureMethod,
+}
+
+impl<'a> OAuthHeaderParams<'a> {
+    pub fn new(consumer_key: &'a str, consumer_secret: &'a str, signature_method: &'a OAuthSignatureMethod) -> OAuthHeaderParams<'a> {
+        OAuthHeaderParams {
+            consumer_key: consumer_key,
+            consumer_secret: consumer_secret,
+            token: None,
+            callback: None,
+            verifier: None,
+            signature_method: signature_method,
+        }
+    }
+
+    pub fn create_header(&self, request: &Request) -> Result<OAuthAuthorizationScheme, TwitterError> {
+        match *self.signature_method {
+            OAuthSignatureMethod::HmacSha1 => Ok(self.create_oauthcli_header(request, SignatureMethod::HmacSha1)),
+            OAuthSignatureMethod::Plaintext => Ok(self.create_oauthcli_header(request, SignatureMethod::Plaintext)),
+            OAuthSignatureMethod::RsaSha1(ref signer) => self.create_rsa_sha1_header(request, &**signer),
+        }
+    }
+
+    fn create_oauthcli_header(&self, request: &Request, signature_method: SignatureMethod) -> OAuthAuthorizationScheme {
+        let mut builder = OAuthAuthorizationHeaderBuilder::new(
+            request.method.as_ref(),
+            &request.url,
+            self.consumer_key,
+            self.consumer_secret,
+            signature_method
+        );
+
+        if let Some((token, token_secret)) = self.token {
+            builder.token(token, token_secret);
+        }
+        if let Some(callback) = self.callback {
+            builder.callback(callback);
+        }
+        if let Some(verifier) = self.verifier {
+            builder.verifier(verifier);
+        }
+
+        if let RequestContent::WwwForm(ref params) = request.content {
+            builder.request_parameters(
+                params.as_ref().iter()
+                    .map(|&(ref key, ref val)| (key.as_ref(), val.as_ref()))
+            );
+        }
+
+        OAuthAuthorizationScheme(builder.finish_for_twitter())
+    }
+
+    /// oauthcli doesn't support RSA-SHA1, so the signature base string is created here.
+    fn create_rsa_sha1_header(&self, request: &Request, signer: &RsaSha1Signer) -> Result<OAuthAuthorizationScheme, TwitterError> {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|x| x.as_secs()).unwrap_or(0);
+
+        let mut oauth_params: Vec<(&str, Cow<str>)> = vec![
+            ("oauth_consumer_key", Cow::Borrowed(self.consumer_key)),
+            ("oauth_nonce", Cow::Owned(nonce())),
+            ("oauth_signature_method", Cow::Borrowed("RSA-SHA1")),
+            ("oauth_timestamp", Cow::Owned(timestamp.to_string())),
+            ("oauth_version", Cow::Borrowed("1.0")),
+        ];
+        if let Some((token, _)) = self.token {
+            oauth_params.push(("oauth_token", Cow::Borrowed(token)));
+        }
+        if let Some(callback) = self.callback {
+            oauth_params.push(("oauth_callback", Cow::Borrowed(callback)));
+        }
+        if let Some(verifier) = self.verifier {
+            oauth_params.push(("oauth_verifier", Cow::Borrowed(verifier)));
+        }
+
+        let base_string = signature_base_string(request, &oauth_params);
+        let signature = base64::encode(&try!(signer.sign(base_string.as_bytes()).map_err(TwitterError::Signature)));
+        oauth_params.push(("oauth_signature", Cow::Owned(signature)));
+
+        let header = oauth_params.iter()
+            .map(|&(key, ref val)| format!("{}=\"{}\"", key, encode(val)))
+            .collect::<Vec<_>>()
+            .join(",");
+
+        OAuthAuthorizationHeader::from_str(&header)
+            .map(OAuthAuthorizationScheme)
+            .map_err(|_| TwitterError::Signature(From::from("failed to parse the created header")))
+    }
+}
+
+fn encode(s: &str) -> String {
+    utf8_percent_encode(s, oauthcli::OAUTH_ENCODE_SET).to_string()
+}
+
+/// Generates 32 random alphanumeric characters, like oauthcli does.
+fn nonce() -> String {
+    OsRng.sample_iter(&Alphanumeric).take(32).map(char::from).collect()
+}
+
+/// Creates the signature base string described in RFC 5849 section 3.4.1.
+fn signature_base_string(request: &Request, oauth_params: &[(&str, Cow<str>)]) -> String {
+    let mut base_url = request.url.clone();
+    base_url.set_query(None);
+    base_url.set_fragment(None);
+
+    let mut params: Vec<(String, String)> = request.url.query_pairs()
+        .map(|(key, val)| (encode(&key), encode(&val)))
+        .collect();
+    if let RequestContent::WwwForm(ref x) = request.content {
+        params.extend(x.iter().map(|&(ref key, ref val)| (encode(key), encode(val))));
+    }
+    params.extend(oauth_params.iter().map(|&(key, ref val)| (encode(key), encode(val))));
+    params.sort();
+
+    let normalized = params.iter()
+        .map(|&(ref key, ref val)| format!("{}={}", key, val))
+        .collect::<Vec<_>>()
+        .join("&");
+
+    format!("{}&{}&{}", request.method.as_ref().to_uppercase(), encode(base_url.as_str()), encode(&normalized))
+}
+
 /// OAuth 1.0 wrapper
-#[derive(Clone, Debug)]
+///
+/// `signature_method` is serialized by its name, so an authenticator using RSA-SHA1 cannot be deserialized.
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct OAuthAuthenticator<'a> {
     pub consumer_key: Cow<'a, str>,
     pub consumer_secret: Cow<'a, str>,
     pub access_token: Cow<'a, str>,
-    pub access_token_secret: Cow<'a, str>
+    pub access_token_secret: Cow<'a, str>,
+    #[serde(default)]
+    pub signature_method: OAuthSignatureMethod,
 }
 
 impl<'a> OAuthAuthenticator<'a> {
@@ -23,33 +232,29 @@ impl<'a> OAuthAuthenticator<'a> {
             consumer_key: consumer_key.into(),
             consumer_secret: consumer_secret.into(),
             access_token: access_token.into(),
-            access_token_secret: access_token_secret.into()
+            access_token_secret: access_token_secret.into(),
+            signature_method: OAuthSignatureMethod::HmacSha1,
         }
     }
+
+    /// Sets the signature method. The default is HMAC-SHA1.
+    pub fn signature_method(mut self, val: OAuthSignatureMethod) -> Self {
+        self.signature_method = val;
+        self
+    }
 }
 
 impl<'a> Authenticator for OAuthAuthenticator<'a> {
     type Scheme = OAuthAuthorizationScheme;
 
     fn create_authorization_header(&self, request: &Request) -> Option<Self::Scheme> {
-        let mut builder = OAuthAuthorizationHeaderBuilder::new(
-            request.method.as_ref(),
-            &request.url,
-            self.consumer_key.as_ref(),
-            self.consumer_secret.as_ref(),
-            SignatureMethod::HmacSha1
-        );
-        builder.token(self.access_token.as_ref(), self.access_token_secret.as_ref());
-
-        if let RequestContent::WwwForm(ref params) = request.content {
-            builder.request_parameters(
-                params.as_ref().iter()
-                    .map(|&(ref key, ref val)| (key.as_ref(), val.as_ref()))
-            );
-        }
+        self.try_create_authorization_header(request).unwrap_or(None)
+    }
 
-        let h = builder.finish_for_twitter();
-        Some(OAuthAuthorizationScheme(h))
+    fn try_create_authorization_header(&self, request: &Request) -> Result<Option<Self::Scheme>, TwitterError> {
+        let mut params = OAuthHeaderParams::new(self.consumer_key.as_ref(), self.consumer_secret.as_ref(), &self.signature_method);
+        params.token = Some((self.access_token.as_ref(), self.access_token_secret.as_ref()));
+        params.create_header(request).map(Some)
     }
 }
 
@@ -75,3 +280,83 @@ impl FromStr for OAuthAuthorizationScheme {
             .map(OAuthAuthorizationScheme)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+    use std::sync::Mutex;
+    use hyper::Get;
+    use conn::*;
+    use conn::mock_http_handler::*;
+    use ::{TwitterClient, TwitterError};
+    use super::*;
+
+    struct FakeSigner(Mutex<Vec<u8>>);
+
+    impl RsaSha1Signer for FakeSigner {
+        fn sign(&self, data: &[u8]) -> Result<Vec<u8>, Box<Error + Send + Sync>> {
+            *self.0.lock().unwrap() = data.to_vec();
+            Ok(b"sig".to_vec())
+        }
+    }
+
+    #[test]
+    fn nonce_test() {
+        let x = nonce();
+        assert_eq!(x.len(), 32);
+        assert!(x.chars().all(|c| c.is_ascii_alphanumeric()));
+        assert_ne!(x, nonce());
+    }
+
+    #[test]
+    fn rsa_sha1_test() {
+        let signer = Arc::new(FakeSigner(Mutex::new(Vec::new())));
+        let auth = OAuthAuthenticator::new("ck", "cs", "at", "ats")
+            .signature_method(OAuthSignatureMethod::RsaSha1(signer.clone()));
+        let req = Request::new(Get, "https://Example.com/r?b=1&a=x%20y", RequestContent::None).unwrap();
+
+        let header = auth.create_authorization_header(&req).unwrap();
+        assert!(header.0.auth_param().contains("oauth_signature=\"c2ln\""));
+        assert!(header.0.auth_param().contains("oauth_signature_method=\"RSA-SHA1\""));
+
+        let base_string = String::from_utf8(signer.0.lock().unwrap().clone()).unwrap();
+        assert!(base_string.starts_with("GET&https%3A%2F%2Fexample.com%2Fr&a%3Dx%2520y%26b%3D1%26oauth_consumer_key%3Dck%26oauth_nonce%3D"));
+        assert!(base_string.ends_with("%26oauth_token%3Dat%26oauth_version%3D1.0"));
+    }
+
+    struct FailingSigner;
+
+    impl RsaSha1Signer for FailingSigner {
+        fn sign(&self, _: &[u8]) -> Result<Vec<u8>, Box<Error + Send + Sync>> {
+            Err(From::from("the key is not available"))
+        }
+    }
+
+    #[test]
+    fn rsa_sha1_error_test() {
+        let auth = OAuthAuthenticator::new("ck", "cs", "at", "ats")
+            .signature_method(OAuthSignatureMethod::rsa_sha1(FailingSigner));
+        let mock = MockHttpHandler::new();
+        mock.respond(Get, "https://api.twitter.com/1.1/friends/ids.json", MockResponse::ok(r#"{"previous_cursor":0,"next_cursor":0,"ids":[]}"#));
+
+        let client = TwitterClient::new(auth, &mock);
+        match client.friends().ids().execute() {
+            Err(TwitterError::Signature(e)) => assert_eq!(e.to_string(), "the key is not available"),
+            x => panic!("unexpected {:?}", x),
+        }
+        assert!(mock.requests().is_empty());
+    }
+
+    #[test]
+    fn plaintext_test() {
+        let auth = OAuthAuthenticator::new("ck", "c&s", "at", "ats")
+            .signature_method(OAuthSignatureMethod::Plaintext);
+        let req = Request::new(Get, "https://api.twitter.com/1.1/friends/ids.json", RequestContent::None).unwrap();
+
+        let header = auth.create_authorization_header(&req).unwrap();
+        assert!(header.0.auth_param().contains("oauth_signature_method=\"PLAINTEXT\""));
+        // The signature is "c%26s&ats", which is encoded again in the header
+        assert!(header.0.auth_param().contains("oauth_signature=\"c%2526s%26ats\""));
+        assert!(header.0.auth_param().contains("oauth_token=\"at\""));
+    }
+}
@@ -0,0 +1,253 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use chrono;
+use url::Url;
+use ::TwitterClient;
+use super::*;
+
+/// Remembers the rate limit status of each endpoint from the responses.
+///
+/// The endpoints are identified by the same form as the keys of `RateLimitStatusResponse.resources`,
+/// e.g. `/statuses/home_timeline` or `/statuses/retweets/:id`.
+#[derive(Debug, Default)]
+pub struct RateLimitTracker {
+    statuses: Mutex<HashMap<String, RateLimitStatus>>,
+}
+
+impl RateLimitTracker {
+    pub fn new() -> RateLimitTracker {
+        Default::default()
+    }
+
+    /// Loads all statuses from the response of `application().rate_limit_status()`.
+    pub fn seed(&self, response: &RateLimitStatusResponse) {
+        let mut statuses = self.statuses.lock().unwrap();
+        for resources in response.resources.values() {
+            for (endpoint, status) in resources {
+                statuses.insert(endpoint.clone(), *status);
+            }
+        }
+    }
+
+    /// Calls `application/rate_limit_status` and loads the result.
+    pub fn seed_from_client<A: Authenticator, H: HttpHandler>(&self, client: &TwitterClient<A, H>) -> Result<(), TwitterError> {
+        let res = try!(client.application().rate_limit_status().execute());
+        self.seed(&res.object);
+        Ok(())
+    }
+
+    /// Records the status of the endpoint.
+    ///
+    /// If the endpoint has not been seeded, the segments which look like IDs are recorded as `:id`
+    /// so that the requests for different IDs share one status.
+    pub fn update(&self, endpoint: &str, status: RateLimitStatus) {
+        let mut statuses = self.statuses.lock().unwrap();
+        let key = find_key(&statuses, endpoint).unwrap_or_else(|| normalize_endpoint(endpoint));
+        statuses.insert(key, status);
+    }
+
+    /// Records the status from the response of the request to `url`.
+    pub fn update_from_url(&self, url: &Url, status: RateLimitStatus) {
+        self.update(&endpoint_path(url), status)
+    }
+
+    /// Returns the last known status of the endpoint.
+    pub fn get(&self, endpoint: &str) -> Option<RateLimitStatus> {
+        let statuses = self.statuses.lock().unwrap();
+        find_key(&statuses, endpoint).and_then(|k| statuses.get(&k).cloned())
+    }
+
+    /// Returns the number of requests which can be sent to the endpoint in the current window.
+    /// Returns `None` if the status is unknown.
+    pub fn remaining(&self, endpoint: &str) -> Option<i32> {
+        self.get(endpoint).map(|x| {
+            if x.reset <= chrono::Utc::now().timestamp() { x.limit } else { x.remaining }
+        })
+    }
+
+    /// Returns `Err` with the status if no request can be sent to the endpoint until the window resets.
+    pub fn check(&self, endpoint: &str) -> Result<(), RateLimitStatus> {
+        match self.get(endpoint) {
+            Some(x) if x.remaining <= 0 && x.reset > chrono::Utc::now().timestamp() => Err(x),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn clear(&self) {
+        self.statuses.lock().unwrap().clear();
+    }
+}
+
+/// Finds the key which matches `endpoint`. `:param` segments in the keys match any segment.
+fn find_key(statuses: &HashMap<String, RateLimitStatus>, endpoint: &str) -> Option<String> {
+    if statuses.contains_key(endpoint) {
+        return Some(endpoint.to_owned());
+    }
+
+    statuses.keys()
+        .find(|k| {
+            let mut x = k.split('/');
+            let mut y = endpoint.split('/');
+            loop {
+                match (x.next(), y.next()) {
+                    (Some(a), Some(b)) => if a != b && !a.starts_with(':') { return false },
+                    (None, None) => return true,
+                    _ => return false,
+                }
+            }
+        })
+        .cloned()
+}
+
+/// Replaces the segments which look like IDs with `:id`.
+fn normalize_endpoint(endpoint: &str) -> String {
+    endpoint.split('/')
+        .map(|x| if is_id_segment(x) { ":id" } else { x })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Numeric IDs and hexadecimal IDs such as place IDs. The names of endpoints never contain digits.
+fn is_id_segment(x: &str) -> bool {
+    x.bytes().any(|b| b.is_ascii_digit()) && x.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Converts a URL to the endpoint form used in `RateLimitStatusResponse.resources`.
+///
+/// `https://api.twitter.com/1.1/statuses/home_timeline.json` becomes `/statuses/home_timeline`.
+pub fn endpoint_path(url: &Url) -> String {
+    let path = url.path();
+    let path = if path.starts_with("/1.1/") { &path[4..] } else { path };
+    let path = if path.ends_with(".json") { &path[..path.len() - 5] } else { path };
+    path.to_owned()
+}
+
+/// What `RateLimitedHttpHandler` does when the limit is exhausted.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum RateLimitMode {
+    /// Sleeps until the window resets, then sends the request.
+    Wait,
+    /// Returns `TwitterError::RateLimitExceeded` without sending the request.
+    FailFast,
+}
+
+/// `HttpHandler` which records the rate limit status of each response
+/// and holds back requests to the endpoints whose limit is exhausted.
+pub struct RateLimitedHttpHandler<H: HttpHandler> {
+    handler: H,
+    tracker: Arc<RateLimitTracker>,
+    mode: RateLimitMode,
+}
+
+impl<H: HttpHandler> RateLimitedHttpHandler<H> {
+    pub fn new(handler: H, mode: RateLimitMode) -> RateLimitedHttpHandler<H> {
+        RateLimitedHttpHandler::with_tracker(handler, mode, Arc::new(RateLimitTracker::new()))
+    }
+
+    /// Creates a handler sharing the tracker with other handlers.
+    pub fn with_tracker(handler: H, mode: RateLimitMode, tracker: Arc<RateLimitTracker>) -> RateLimitedHttpHandler<H> {
+        RateLimitedHttpHandler {
+            handler: handler,
+            tracker: tracker,
+            mode: mode,
+        }
+    }
+
+    pub fn tracker(&self) -> &Arc<RateLimitTracker> {
+        &self.tracker
+    }
+
+    pub fn mode(&self) -> RateLimitMode {
+        self.mode
+    }
+
+    pub fn into_inner(self) -> H {
+        self.handler
+    }
+}
+
+impl<H: HttpHandler> HttpHandler for RateLimitedHttpHandler<H> {
+    fn send_request<A: Authenticator>(&self, request: Request, auth: &A) -> Result<RawResponse, TwitterError> {
+        let endpoint = endpoint_path(&request.url);
+
+        if let Err(status) = self.tracker.check(&endpoint) {
+            match self.mode {
+                RateLimitMode::FailFast => return Err(TwitterError::RateLimitExceeded(status)),
+                RateLimitMode::Wait => {
+                    let secs = status.reset - chrono::Utc::now().timestamp();
+                    if secs > 0 {
+                        // Add a second to make sure the window has been reset
+                        thread::sleep(Duration::from_secs(secs as u64 + 1));
+                    }
+                }
+            }
+        }
+
+        let res = self.handler.send_request(request, auth);
+
+        let rate_limit = match res {
+            Ok(ref x) => x.rate_limit,
+            Err(TwitterError::ErrorResponse(ref x)) => x.rate_limit,
+            Err(_) => None,
+        };
+        if let Some(x) = rate_limit {
+            self.tracker.update(&endpoint, x);
+        }
+
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(remaining: i32, reset: i64) -> RateLimitStatus {
+        RateLimitStatus { limit: 15, remaining: remaining, reset: reset }
+    }
+
+    #[test]
+    fn endpoint_path_test() {
+        let url = Url::parse("https://api.twitter.com/1.1/statuses/home_timeline.json?count=1").unwrap();
+        assert_eq!(endpoint_path(&url), "/statuses/home_timeline");
+    }
+
+    #[test]
+    fn parameterized_endpoint_test() {
+        let t = RateLimitTracker::new();
+        t.update("/statuses/retweets/:id", status(10, 0));
+        assert_eq!(t.get("/statuses/retweets/123").map(|x| x.remaining), Some(10));
+        assert!(t.get("/statuses/retweets").is_none());
+    }
+
+    #[test]
+    fn unseeded_endpoint_test() {
+        let t = RateLimitTracker::new();
+        t.update("/statuses/retweets/123", status(10, 0));
+        t.update("/statuses/retweets/456", status(9, 0));
+        t.update("/geo/id/df51dec6f4ee2b2c", status(8, 0));
+        assert_eq!(t.get("/statuses/retweets/789").map(|x| x.remaining), Some(9));
+        assert_eq!(t.get("/geo/id/:id").map(|x| x.remaining), Some(8));
+        assert_eq!(t.statuses.lock().unwrap().len(), 2);
+
+        // The seeded key is kept
+        t.update("/statuses/show/:id", status(5, 0));
+        t.update("/statuses/show/1", status(4, 0));
+        assert_eq!(t.get("/statuses/show/:id").map(|x| x.remaining), Some(4));
+        assert_eq!(normalize_endpoint("/friends/ids"), "/friends/ids");
+    }
+
+    #[test]
+    fn check_test() {
+        let t = RateLimitTracker::new();
+        let future = chrono::Utc::now().timestamp() + 60;
+        t.update("/friends/ids", status(0, future));
+        t.update("/followers/ids", status(0, 0));
+        assert!(t.check("/friends/ids").is_err());
+        assert!(t.check("/followers/ids").is_ok());
+        assert!(t.check("/users/show").is_ok());
+        assert_eq!(t.remaining("/followers/ids"), Some(15));
+    }
+}
@@ -0,0 +1,246 @@
+use std::cmp;
+use std::thread;
+use std::time::Duration;
+use chrono;
+use hyper::{Get, Delete, Head};
+use hyper::method::Method;
+use hyper::status::StatusCode;
+use rand;
+use super::*;
+
+/// The rules for `RetryingHttpHandler`.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    /// The maximum number of attempts including the first request.
+    pub max_attempts: u32,
+    /// The wait before the first retry. It is doubled on each retry.
+    pub initial_backoff: Duration,
+    /// The upper bound of the exponential backoff.
+    pub max_backoff: Duration,
+    /// Randomizes each backoff between the half and the full of it.
+    pub jitter: bool,
+    /// Waits until `X-Rate-Limit-Reset` and retries when the server returns 429.
+    pub retry_on_rate_limit: bool,
+    /// Gives up instead of waiting for the rate limit reset if it is longer than this.
+    pub max_rate_limit_wait: Duration,
+    /// Retries when the server returns 500, 502, 503 or 504.
+    pub retry_on_server_error: bool,
+    /// Retries when the connection fails.
+    pub retry_on_io_error: bool,
+    /// Also retries POST requests, which may cause duplicate side effects.
+    pub retry_non_idempotent: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            jitter: true,
+            retry_on_rate_limit: true,
+            max_rate_limit_wait: Duration::from_secs(15 * 60),
+            retry_on_server_error: true,
+            retry_on_io_error: true,
+            retry_non_idempotent: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    fn can_retry_method(&self, method: &Method) -> bool {
+        match *method {
+            Get | Delete | Head | Method::Put | Method::Options => true,
+            _ => self.retry_non_idempotent,
+        }
+    }
+
+    /// Returns the duration to wait before the next attempt, or `None` if the error should not be retried.
+    /// `attempt` is the number of the attempts that have been made.
+    pub fn retry_delay(&self, err: &TwitterError, attempt: u32) -> Option<Duration> {
+        if attempt >= self.max_attempts { return None; }
+
+        match *err {
+            TwitterError::ErrorResponse(ref e) => match e.status {
+                StatusCode::TooManyRequests if self.retry_on_rate_limit => {
+                    match e.rate_limit {
+                        Some(ref x) => {
+                            // Add a second to make sure the window has been reset
+                            let secs = cmp::max(x.reset - chrono::Utc::now().timestamp(), 0) as u64 + 1;
+                            let wait = Duration::from_secs(secs);
+                            if wait <= self.max_rate_limit_wait { Some(wait) } else { None }
+                        }
+                        None => Some(self.backoff(attempt)),
+                    }
+                }
+                StatusCode::InternalServerError | StatusCode::BadGateway |
+                StatusCode::ServiceUnavailable | StatusCode::GatewayTimeout
+                    if self.retry_on_server_error => Some(self.backoff(attempt)),
+                _ => None,
+            },
+            TwitterError::Io(_) | TwitterError::Http(::hyper::Error::Io(_))
+                if self.retry_on_io_error => Some(self.backoff(attempt)),
+            _ => None,
+        }
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        let exp = cmp::min(attempt.saturating_sub(1), 31);
+        let d = self.initial_backoff.checked_mul(1 << exp)
+            .map_or(self.max_backoff, |x| cmp::min(x, self.max_backoff));
+
+        if self.jitter {
+            let millis = d.as_secs() * 1000 + (d.subsec_nanos() / 1_000_000) as u64;
+            let half = millis / 2;
+            Duration::from_millis(half + (rand::random::<f64>() * (millis - half) as f64) as u64)
+        } else {
+            d
+        }
+    }
+}
+
+/// `HttpHandler` which resends failed requests according to `RetryPolicy`.
+///
+/// Requests with a multipart or stream body cannot be resent, so they are sent only once.
+pub struct RetryingHttpHandler<H: HttpHandler> {
+    handler: H,
+    policy: RetryPolicy,
+}
+
+impl<H: HttpHandler> RetryingHttpHandler<H> {
+    pub fn new(handler: H) -> RetryingHttpHandler<H> {
+        RetryingHttpHandler::with_policy(handler, Default::default())
+    }
+
+    pub fn with_policy(handler: H, policy: RetryPolicy) -> RetryingHttpHandler<H> {
+        RetryingHttpHandler {
+            handler: handler,
+            policy: policy,
+        }
+    }
+
+    pub fn policy(&self) -> &RetryPolicy {
+        &self.policy
+    }
+
+    pub fn policy_mut(&mut self) -> &mut RetryPolicy {
+        &mut self.policy
+    }
+
+    pub fn into_inner(self) -> H {
+        self.handler
+    }
+}
+
+impl<H: HttpHandler> HttpHandler for RetryingHttpHandler<H> {
+    fn send_request<A: Authenticator>(&self, request: Request, auth: &A) -> Result<RawResponse, TwitterError> {
+        let mut request = request;
+        let mut attempt = 1;
+
+        loop {
+            let next_request = if self.policy.can_retry_method(&request.method) { request.try_clone() } else { None };
+
+            match self.handler.send_request(request, auth) {
+                Err(e) => match (next_request, self.policy.retry_delay(&e, attempt)) {
+                    (Some(r), Some(delay)) => {
+                        thread::sleep(delay);
+                        request = r;
+                        attempt += 1;
+                    }
+                    _ => return Err(e),
+                },
+                x => return x,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::time::{Duration, Instant};
+    use chrono;
+    use hyper::{Get, Post};
+    use hyper::header::Bearer;
+    use hyper::status::StatusCode;
+    use conn::mock_http_handler::*;
+    use super::*;
+
+    struct NoAuth;
+
+    impl Authenticator for NoAuth {
+        type Scheme = Bearer;
+
+        fn create_authorization_header(&self, _: &Request) -> Option<Bearer> {
+            None
+        }
+    }
+
+    struct FailingHandler {
+        count: Cell<u32>,
+        status: StatusCode,
+    }
+
+    impl HttpHandler for FailingHandler {
+        fn send_request<A: Authenticator>(&self, _: Request, _: &A) -> Result<RawResponse, TwitterError> {
+            self.count.set(self.count.get() + 1);
+            to_twitter_result(self.status, None, String::new())
+        }
+    }
+
+    fn handler(status: StatusCode) -> RetryingHttpHandler<FailingHandler> {
+        let mut policy = RetryPolicy::default();
+        policy.initial_backoff = Duration::from_millis(0);
+        RetryingHttpHandler::with_policy(FailingHandler { count: Cell::new(0), status: status }, policy)
+    }
+
+    #[test]
+    fn retries_server_error() {
+        let h = handler(StatusCode::ServiceUnavailable);
+        let req = Request::new(Get, "https://api.twitter.com/1.1/statuses/home_timeline.json", RequestContent::None).unwrap();
+        assert!(h.send_request(req, &NoAuth).is_err());
+        assert_eq!(h.handler.count.get(), 3);
+    }
+
+    #[test]
+    fn does_not_retry_post() {
+        let h = handler(StatusCode::ServiceUnavailable);
+        let req = Request::new(Post, "https://api.twitter.com/1.1/statuses/update.json", RequestContent::None).unwrap();
+        assert!(h.send_request(req, &NoAuth).is_err());
+        assert_eq!(h.handler.count.get(), 1);
+    }
+
+    #[test]
+    fn does_not_retry_client_error() {
+        let h = handler(StatusCode::NotFound);
+        let req = Request::new(Get, "https://api.twitter.com/1.1/statuses/show.json", RequestContent::None).unwrap();
+        assert!(h.send_request(req, &NoAuth).is_err());
+        assert_eq!(h.handler.count.get(), 1);
+    }
+
+    #[test]
+    fn waits_for_rate_limit_reset() {
+        let url = "https://api.twitter.com/1.1/statuses/home_timeline.json";
+        let mock = MockHttpHandler::new();
+        mock.add(MockRule::new(Get, url, MockResponse::with_status(StatusCode::TooManyRequests, "")
+            .rate_limit(15, 0, chrono::Utc::now().timestamp())).times(1));
+        mock.respond(Get, url, MockResponse::ok("[]"));
+
+        let h = RetryingHttpHandler::new(mock);
+        let start = Instant::now();
+        let res = h.send_request(Request::new(Get, url, RequestContent::None).unwrap(), &NoAuth).unwrap();
+        assert_eq!(res.raw_response, "[]");
+        // The window has been reset, so it waits only for the extra second
+        assert!(start.elapsed() >= Duration::from_secs(1));
+        assert_eq!(h.into_inner().requests().len(), 2);
+    }
+
+    #[test]
+    fn jitter_test() {
+        let policy = RetryPolicy::default();
+        for _ in 0..100 {
+            let d = policy.backoff(2);
+            assert!(d >= Duration::from_millis(1000) && d <= Duration::from_millis(2000));
+        }
+    }
+}
@@ -0,0 +1,372 @@
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use chrono::{DateTime, Duration, Utc};
+use hyper::header::Bearer;
+use oauth2::{self, RefreshTokenRequestBuilder, UserTokenResponse};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use super::*;
+
+#[cfg(feature = "futures")]
+use futures::{future, Future};
+
+#[derive(Clone, Debug)]
+struct TokenState {
+    access_token: String,
+    refresh_token: Option<String>,
+    expires_at: Option<DateTime<Utc>>,
+}
+
+impl TokenState {
+    fn new(res: &UserTokenResponse) -> TokenState {
+        TokenState {
+            access_token: res.access_token.clone(),
+            refresh_token: res.refresh_token.clone(),
+            expires_at: res.expires_in.map(|x| Utc::now() + Duration::seconds(x)),
+        }
+    }
+
+    /// Replaces the tokens. The refresh token is kept if the response doesn't have a new one.
+    fn update(&mut self, res: &UserTokenResponse) {
+        let mut new_token = TokenState::new(res);
+        if new_token.refresh_token.is_none() {
+            new_token.refresh_token = self.refresh_token.take();
+        }
+        *self = new_token;
+    }
+}
+
+/// OAuth 2.0 user context authenticator.
+///
+/// The access token is refreshed with the refresh token before sending a request if it has expired,
+/// and when the server responds 401 Unauthorized. After the latter, the request is sent again.
+///
+/// The tokens are serialized with the client, but the function set by `on_refresh` is not.
+pub struct UserBearerAuthenticator {
+    client_id: String,
+    client_secret: Option<String>,
+    token_url: String,
+    token: Mutex<TokenState>,
+    on_refresh: Option<Arc<Fn(&UserTokenResponse) + Send + Sync>>,
+}
+
+impl UserBearerAuthenticator {
+    pub fn new<T: Into<String>>(client_id: T, token: UserTokenResponse) -> UserBearerAuthenticator {
+        UserBearerAuthenticator {
+            client_id: client_id.into(),
+            client_secret: None,
+            token_url: oauth2::TOKEN_URL.to_owned(),
+            token: Mutex::new(TokenState::new(&token)),
+            on_refresh: None,
+        }
+    }
+
+    /// Sets the client secret of a confidential client.
+    pub fn client_secret<T: Into<String>>(mut self, val: T) -> Self {
+        self.client_secret = Some(val.into());
+        self
+    }
+
+    /// Overrides the token endpoint. The default is `oauth2::TOKEN_URL`.
+    pub fn token_url<T: Into<String>>(mut self, val: T) -> Self {
+        self.token_url = val.into();
+        self
+    }
+
+    /// Sets the function called with the new tokens after refreshing.
+    /// The refresh token changes on each refresh, so save it here.
+    pub fn on_refresh<F>(mut self, f: F) -> Self
+        where F: Fn(&UserTokenResponse) + Send + Sync + 'static
+    {
+        self.on_refresh = Some(Arc::new(f));
+        self
+    }
+
+    pub fn client_id(&self) -> &str {
+        &self.client_id
+    }
+
+    pub fn access_token(&self) -> String {
+        self.token.lock().unwrap().access_token.clone()
+    }
+
+    pub fn refresh_token(&self) -> Option<String> {
+        self.token.lock().unwrap().refresh_token.clone()
+    }
+
+    pub fn expires_at(&self) -> Option<DateTime<Utc>> {
+        self.token.lock().unwrap().expires_at
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at().map_or(false, |x| x <= Utc::now())
+    }
+
+    /// Gets a new access token with the refresh token.
+    pub fn refresh<H: HttpHandler>(&self, handler: &H) -> Result<UserTokenResponse, TwitterError> {
+        let res = {
+            // Keep the lock so that the other threads wait for the new token
+            let mut token = self.token.lock().unwrap();
+            try!(self.refresh_locked(&mut token, handler))
+        };
+        self.notify(&res);
+        Ok(res)
+    }
+
+    fn refresh_request<'a>(&'a self, refresh_token: &'a str) -> RefreshTokenRequestBuilder<'a> {
+        let mut builder = oauth2::refresh_token(&self.client_id[..], refresh_token);
+        builder.token_url(&self.token_url[..]);
+        if let Some(ref x) = self.client_secret {
+            builder.client_secret(&x[..]);
+        }
+        builder
+    }
+
+    fn refresh_locked<H: HttpHandler>(&self, token: &mut TokenState, handler: &H) -> Result<UserTokenResponse, TwitterError> {
+        let res = match token.refresh_token {
+            Some(ref x) => try!(self.refresh_request(x).execute(handler)).object,
+            None => return Err(TwitterError::InvalidRequest),
+        };
+        token.update(&res);
+        Ok(res)
+    }
+
+    fn notify(&self, res: &UserTokenResponse) {
+        if let Some(ref f) = self.on_refresh {
+            f(res);
+        }
+    }
+}
+
+impl Clone for UserBearerAuthenticator {
+    fn clone(&self) -> UserBearerAuthenticator {
+        UserBearerAuthenticator {
+            client_id: self.client_id.clone(),
+            client_secret: self.client_secret.clone(),
+            token_url: self.token_url.clone(),
+            token: Mutex::new(self.token.lock().unwrap().clone()),
+            on_refresh: self.on_refresh.clone(),
+        }
+    }
+}
+
+impl fmt::Debug for UserBearerAuthenticator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("UserBearerAuthenticator")
+            .field("client_id", &self.client_id)
+            .field("token_url", &self.token_url)
+            .field("expires_at", &self.expires_at())
+            .finish()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SerializedUserBearerAuthenticator {
+    client_id: String,
+    #[serde(default)]
+    client_secret: Option<String>,
+    token_url: String,
+    access_token: String,
+    #[serde(default)]
+    refresh_token: Option<String>,
+    #[serde(default)]
+    expires_at: Option<DateTime<Utc>>,
+}
+
+impl Serialize for UserBearerAuthenticator {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let token = self.token.lock().unwrap().clone();
+        SerializedUserBearerAuthenticator {
+            client_id: self.client_id.clone(),
+            client_secret: self.client_secret.clone(),
+            token_url: self.token_url.clone(),
+            access_token: token.access_token,
+            refresh_token: token.refresh_token,
+            expires_at: token.expires_at,
+        }.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for UserBearerAuthenticator {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let x = try!(SerializedUserBearerAuthenticator::deserialize(deserializer));
+        Ok(UserBearerAuthenticator {
+            client_id: x.client_id,
+            client_secret: x.client_secret,
+            token_url: x.token_url,
+            token: Mutex::new(TokenState {
+                access_token: x.access_token,
+                refresh_token: x.refresh_token,
+                expires_at: x.expires_at,
+            }),
+            on_refresh: None,
+        })
+    }
+}
+
+impl Authenticator for UserBearerAuthenticator {
+    type Scheme = Bearer;
+
+    fn create_authorization_header(&self, _: &Request) -> Option<Self::Scheme> {
+        Some(Bearer { token: self.access_token() })
+    }
+
+    fn needs_refresh(&self) -> bool {
+        self.is_expired()
+    }
+
+    fn refresh_credentials<H: HttpHandler>(&self, rejected: Option<&Bearer>, handler: &H) -> Result<bool, TwitterError> {
+        let res = {
+            let mut token = self.token.lock().unwrap();
+            if rejected.map_or(false, |x| x.token != token.access_token) {
+                // Another thread has refreshed the token while this thread was waiting for the lock
+                return Ok(true);
+            }
+            if token.refresh_token.is_none() {
+                return Ok(false);
+            }
+            try!(self.refresh_locked(&mut token, handler))
+        };
+        self.notify(&res);
+        Ok(true)
+    }
+
+    #[cfg(feature = "futures")]
+    fn refresh_credentials_async<'a, H: AsyncHttpHandler>(&'a self, rejected: Option<&Bearer>, handler: &'a H)
+        -> Box<Future<Item = bool, Error = TwitterError> + 'a>
+    {
+        let (access_token, refresh_token) = {
+            let token = self.token.lock().unwrap();
+            if rejected.map_or(false, |x| x.token != token.access_token) {
+                return Box::new(future::ok(true));
+            }
+            match token.refresh_token {
+                Some(ref x) => (token.access_token.clone(), x.clone()),
+                None => return Box::new(future::ok(false)),
+            }
+        };
+
+        // The lock cannot be held while waiting for the response
+        Box::new(self.refresh_request(&refresh_token).execute_async(handler).map(move |res| {
+            let updated = {
+                let mut token = self.token.lock().unwrap();
+                // Keep the token if another request has refreshed it in the meantime
+                let updated = token.access_token == access_token;
+                if updated { token.update(&res.object); }
+                updated
+            };
+            if updated { self.notify(&res.object); }
+            true
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use hyper::{Get, Post};
+    use hyper::header::Bearer;
+    use hyper::status::StatusCode;
+    use conn::mock_http_handler::*;
+    use oauth2::UserTokenResponse;
+    use ::TwitterClient;
+    use super::*;
+
+    const IDS_URL: &'static str = "https://api.twitter.com/1.1/friends/ids.json";
+    const IDS: &'static str = r#"{"previous_cursor":0,"next_cursor":0,"ids":[1]}"#;
+
+    fn token(expires_in: i64) -> UserTokenResponse {
+        UserTokenResponse {
+            token_type: "bearer".to_owned(),
+            access_token: "old".to_owned(),
+            expires_in: Some(expires_in),
+            refresh_token: Some("r1".to_owned()),
+            scope: None,
+        }
+    }
+
+    fn token_rule() -> MockRule {
+        MockRule::new(Post, "http://127.0.0.1/token", MockResponse::ok(
+            r#"{"token_type":"bearer","access_token":"new","expires_in":7200,"refresh_token":"r2"}"#
+        )).param("grant_type", "refresh_token").param("refresh_token", "r1").times(1)
+    }
+
+    #[test]
+    fn refresh_test() {
+        let mock = MockHttpHandler::new();
+        mock.add(MockRule::new(Get, IDS_URL, MockResponse::with_status(StatusCode::Unauthorized, "")).times(1));
+        mock.add(token_rule());
+        mock.respond(Get, IDS_URL, MockResponse::ok(IDS));
+
+        let saved = Arc::new(Mutex::new(None));
+        let s = saved.clone();
+        let auth = token(7200).to_authenticator("client")
+            .token_url("http://127.0.0.1/token")
+            .on_refresh(move |x| *s.lock().unwrap() = x.refresh_token.clone());
+        assert!(!auth.is_expired());
+
+        let client = TwitterClient::new(auth, &mock);
+        assert_eq!(client.friends().ids().execute().unwrap().object.ids, vec![1]);
+
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[0].authorization.as_ref().map(|x| x.as_ref()), Some("Bearer old"));
+        assert_eq!(requests[1].param("client_id"), Some("client"));
+        assert_eq!(requests[1].authorization, None);
+        assert_eq!(requests[2].authorization.as_ref().map(|x| x.as_ref()), Some("Bearer new"));
+        assert_eq!(*saved.lock().unwrap(), Some("r2".to_owned()));
+    }
+
+    #[test]
+    fn refreshed_by_other_test() {
+        let mock = MockHttpHandler::new();
+        let auth = UserTokenResponse { access_token: "new".to_owned(), ..token(7200) }.to_authenticator("client");
+
+        // The token has been refreshed since "old" was rejected
+        let rejected = Bearer { token: "old".to_owned() };
+        assert!(auth.refresh_credentials(Some(&rejected), &mock).unwrap());
+        assert!(mock.requests().is_empty());
+        assert_eq!(auth.refresh_token(), Some("r1".to_owned()));
+    }
+
+    #[test]
+    fn expired_test() {
+        let mock = MockHttpHandler::new();
+        mock.add(token_rule());
+        mock.respond(Get, IDS_URL, MockResponse::ok(IDS));
+
+        let saved = Arc::new(Mutex::new(None));
+        let s = saved.clone();
+        let auth = token(0).to_authenticator("client")
+            .token_url("http://127.0.0.1/token")
+            .on_refresh(move |x| *s.lock().unwrap() = x.refresh_token.clone());
+        assert!(auth.is_expired());
+
+        let client = TwitterClient::new(auth, &mock);
+        client.friends().ids().execute().unwrap();
+
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].param("refresh_token"), Some("r1"));
+        assert_eq!(requests[1].authorization.as_ref().map(|x| x.as_ref()), Some("Bearer new"));
+        assert_eq!(*saved.lock().unwrap(), Some("r2".to_owned()));
+    }
+
+    #[cfg(feature = "futures")]
+    #[test]
+    fn refresh_async_test() {
+        use futures::Future;
+
+        let mock = MockHttpHandler::new();
+        mock.add(MockRule::new(Get, IDS_URL, MockResponse::with_status(StatusCode::Unauthorized, "")).times(1));
+        mock.add(token_rule());
+        mock.respond(Get, IDS_URL, MockResponse::ok(IDS));
+
+        let client = TwitterClient::new(token(7200).to_authenticator("client").token_url("http://127.0.0.1/token"), &mock);
+        assert_eq!(client.friends().ids().execute_async().wait().unwrap().object.ids, vec![1]);
+
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[1].param("refresh_token"), Some("r1"));
+        assert_eq!(requests[2].authorization.as_ref().map(|x| x.as_ref()), Some("Bearer new"));
+    }
+}
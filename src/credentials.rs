@@ -0,0 +1,582 @@
+//! Named credential profiles and the stores to save them.
+//!
+//! ```no_run
+//! use tweetust::*;
+//! use tweetust::credentials::{Credential, CredentialStore, FileCredentialStore};
+//!
+//! let store = FileCredentialStore::new("credentials.json").passphrase("secret");
+//! store.save("main", &Credential::from(OAuthAuthenticator::new("API Key", "API Secret", "Access Token", "Access Token Secret"))).unwrap();
+//!
+//! let auth = store.load("main").unwrap().unwrap();
+//! let handler = DefaultHttpHandler::with_https_connector().unwrap();
+//! TwitterClient::new(auth, handler).statuses().update("Hello").execute().unwrap();
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use base64;
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use hyper::header::{self, Bearer};
+use pbkdf2::pbkdf2_hmac;
+use rand::RngCore;
+use rand::rngs::OsRng;
+use serde_json;
+use sha2::Sha256;
+use ::{ApplicationOnlyAuthenticator, OAuthAuthenticator, TwitterError, UserBearerAuthenticator};
+use conn::{Authenticator, HttpHandler, Request};
+use conn::oauth_authenticator::{OAuthAuthorizationScheme, OAuthSignatureMethod, RsaSha1Signer};
+use oauth::AccessTokenResponse;
+
+#[cfg(feature = "futures")]
+use futures::Future;
+#[cfg(feature = "futures")]
+use conn::AsyncHttpHandler;
+
+/// The credentials of a profile. This can be used as an authenticator directly.
+///
+/// The refresh token of `OAuth2User` changes on each refresh. Set `UserBearerAuthenticator::on_refresh`
+/// to save the new one.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Credential {
+    #[serde(rename = "oauth1")]
+    OAuth(OAuthAuthenticator<'static>),
+    #[serde(rename = "application_only")]
+    ApplicationOnly(ApplicationOnlyAuthenticator<'static>),
+    #[serde(rename = "oauth2_user")]
+    OAuth2User(UserBearerAuthenticator),
+}
+
+impl From<OAuthAuthenticator<'static>> for Credential {
+    fn from(x: OAuthAuthenticator<'static>) -> Credential {
+        Credential::OAuth(x)
+    }
+}
+
+impl From<ApplicationOnlyAuthenticator<'static>> for Credential {
+    fn from(x: ApplicationOnlyAuthenticator<'static>) -> Credential {
+        Credential::ApplicationOnly(x)
+    }
+}
+
+impl From<UserBearerAuthenticator> for Credential {
+    fn from(x: UserBearerAuthenticator) -> Credential {
+        Credential::OAuth2User(x)
+    }
+}
+
+impl From<AccessTokenResponse> for Credential {
+    fn from(x: AccessTokenResponse) -> Credential {
+        Credential::OAuth(x.to_authenticator())
+    }
+}
+
+/// The Authorization header created by `Credential`.
+#[derive(Clone, Debug)]
+pub enum CredentialScheme {
+    OAuth(OAuthAuthorizationScheme),
+    Bearer(Bearer),
+}
+
+impl header::Scheme for CredentialScheme {
+    fn scheme() -> Option<&'static str> {
+        // The scheme differs by the variant, so it is written by fmt_scheme
+        None
+    }
+
+    fn fmt_scheme(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CredentialScheme::OAuth(ref x) => {
+                try!(f.write_str("OAuth "));
+                x.fmt_scheme(f)
+            }
+            CredentialScheme::Bearer(ref x) => {
+                try!(f.write_str("Bearer "));
+                x.fmt_scheme(f)
+            }
+        }
+    }
+}
+
+impl FromStr for CredentialScheme {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<CredentialScheme, ()> {
+        if s.starts_with("OAuth ") {
+            OAuthAuthorizationScheme::from_str(&s[6..]).map(CredentialScheme::OAuth).map_err(|_| ())
+        } else if s.starts_with("Bearer ") {
+            Bearer::from_str(&s[7..]).map(CredentialScheme::Bearer).map_err(|_| ())
+        } else {
+            Err(())
+        }
+    }
+}
+
+impl Authenticator for Credential {
+    type Scheme = CredentialScheme;
+
+    fn create_authorization_header(&self, request: &Request) -> Option<Self::Scheme> {
+        match *self {
+            Credential::OAuth(ref x) => x.create_authorization_header(request).map(CredentialScheme::OAuth),
+            Credential::ApplicationOnly(ref x) => x.create_authorization_header(request).map(CredentialScheme::Bearer),
+            Credential::OAuth2User(ref x) => x.create_authorization_header(request).map(CredentialScheme::Bearer),
+        }
+    }
+
+    fn try_create_authorization_header(&self, request: &Request) -> Result<Option<Self::Scheme>, TwitterError> {
+        match *self {
+            Credential::OAuth(ref x) => x.try_create_authorization_header(request).map(|x| x.map(CredentialScheme::OAuth)),
+            _ => Ok(self.create_authorization_header(request)),
+        }
+    }
+
+    fn needs_refresh(&self) -> bool {
+        match *self {
+            Credential::OAuth2User(ref x) => x.needs_refresh(),
+            _ => false,
+        }
+    }
+
+    fn refresh_credentials<H: HttpHandler>(&self, rejected: Option<&CredentialScheme>, handler: &H) -> Result<bool, TwitterError> {
+        match *self {
+            Credential::OAuth2User(ref x) => x.refresh_credentials(bearer(rejected), handler),
+            _ => Ok(false),
+        }
+    }
+
+    #[cfg(feature = "futures")]
+    fn refresh_credentials_async<'a, H: AsyncHttpHandler>(&'a self, rejected: Option<&CredentialScheme>, handler: &'a H)
+        -> Box<Future<Item = bool, Error = TwitterError> + 'a>
+    {
+        match *self {
+            Credential::OAuth2User(ref x) => x.refresh_credentials_async(bearer(rejected), handler),
+            _ => Box::new(::futures::future::ok(false)),
+        }
+    }
+}
+
+fn bearer(scheme: Option<&CredentialScheme>) -> Option<&Bearer> {
+    match scheme {
+        Some(&CredentialScheme::Bearer(ref x)) => Some(x),
+        _ => None,
+    }
+}
+
+/// The storage of named credential profiles.
+pub trait CredentialStore {
+    /// Returns the credential of the profile, or `None` if it doesn't exist.
+    fn load(&self, profile: &str) -> io::Result<Option<Credential>>;
+
+    /// Adds or replaces the profile.
+    fn save(&self, profile: &str, credential: &Credential) -> io::Result<()>;
+
+    /// Removes the profile. Returns false if it doesn't exist.
+    fn remove(&self, profile: &str) -> io::Result<bool>;
+
+    /// Returns the names of the profiles.
+    fn profiles(&self) -> io::Result<Vec<String>>;
+}
+
+/// The default iteration count of PBKDF2.
+pub const DEFAULT_PBKDF2_ITERATIONS: u32 = 100_000;
+
+/// The version of the encrypted file format. Version 2 uses PBKDF2-HMAC-SHA256 and ChaCha20-Poly1305.
+const ENCRYPTION_VERSION: u32 = 2;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// The profiles are kept as JSON values so that a profile which cannot be loaded doesn't affect the others.
+#[derive(Default, Serialize, Deserialize)]
+struct CredentialFile {
+    profiles: BTreeMap<String, serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct EncryptedCredentialFile {
+    version: u32,
+    iterations: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+impl EncryptedCredentialFile {
+    /// The header is authenticated with the ciphertext.
+    fn associated_data(&self) -> String {
+        format!("tweetust-credentials:{}:{}:{}", self.version, self.iterations, self.salt)
+    }
+}
+
+fn invalid_data<E: Into<Box<::std::error::Error + Send + Sync>>>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+fn random_bytes(len: usize) -> io::Result<Vec<u8>> {
+    let mut buf = vec![0; len];
+    try!(OsRng.try_fill_bytes(&mut buf).map_err(|e| io::Error::new(io::ErrorKind::Other, e)));
+    Ok(buf)
+}
+
+/// `CredentialStore` which saves the profiles in a JSON file.
+///
+/// If the passphrase is set, the file is encrypted with ChaCha20-Poly1305
+/// and the key derived from the passphrase by PBKDF2-HMAC-SHA256.
+///
+/// A profile signed with RSA-SHA1 can be loaded only if the signer is given by `rsa_sha1_signer`.
+///
+/// `save` and `remove` hold `<path>.lock` while they rewrite the file, so several processes can share the file.
+/// A lock file left by a crashed process is ignored after `LOCK_TIMEOUT_SECS` seconds.
+#[derive(Clone, Debug)]
+pub struct FileCredentialStore {
+    path: PathBuf,
+    passphrase: Option<String>,
+    iterations: u32,
+    rsa_sha1: Option<OAuthSignatureMethod>,
+}
+
+impl FileCredentialStore {
+    pub fn new<P: Into<PathBuf>>(path: P) -> FileCredentialStore {
+        FileCredentialStore {
+            path: path.into(),
+            passphrase: None,
+            iterations: DEFAULT_PBKDF2_ITERATIONS,
+            rsa_sha1: None,
+        }
+    }
+
+    /// Encrypts the file with the passphrase.
+    pub fn passphrase<T: Into<String>>(mut self, val: T) -> Self {
+        self.passphrase = Some(val.into());
+        self
+    }
+
+    /// Sets the iteration count of PBKDF2 for the next save.
+    pub fn pbkdf2_iterations(mut self, val: u32) -> Self {
+        self.iterations = val;
+        self
+    }
+
+    /// Sets the signer of the profiles signed with RSA-SHA1.
+    pub fn rsa_sha1_signer<S: RsaSha1Signer + 'static>(mut self, signer: S) -> Self {
+        self.rsa_sha1 = Some(OAuthSignatureMethod::rsa_sha1(signer));
+        self
+    }
+
+    fn read_file(&self) -> io::Result<CredentialFile> {
+        let mut content = Vec::new();
+        match fs::File::open(&self.path) {
+            Ok(mut f) => { try!(f.read_to_end(&mut content)); }
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(CredentialFile::default()),
+            Err(e) => return Err(e),
+        }
+
+        let value: serde_json::Value = try!(serde_json::from_slice(&content).map_err(invalid_data));
+        if value.get("ciphertext").is_none() {
+            return serde_json::from_value(value).map_err(invalid_data);
+        }
+
+        let passphrase = match self.passphrase {
+            Some(ref x) => x,
+            None => return Err(io::Error::new(io::ErrorKind::InvalidInput, "the credential file is encrypted but no passphrase is given")),
+        };
+
+        let encrypted: EncryptedCredentialFile = try!(serde_json::from_value(value).map_err(invalid_data));
+        if encrypted.version != ENCRYPTION_VERSION {
+            return Err(invalid_data(format!("unsupported credential file version {}", encrypted.version)));
+        }
+        if encrypted.iterations == 0 {
+            return Err(invalid_data("the iteration count is zero"));
+        }
+
+        let decode = |x: &str| base64::decode(x).map_err(invalid_data);
+        let salt = try!(decode(&encrypted.salt));
+        let nonce = try!(decode(&encrypted.nonce));
+        let ciphertext = try!(decode(&encrypted.ciphertext));
+        if nonce.len() != NONCE_LEN {
+            return Err(invalid_data("invalid nonce length"));
+        }
+
+        let key = derive_key(passphrase, &salt, encrypted.iterations);
+        let aad = encrypted.associated_data();
+        let content = try!(
+            ChaCha20Poly1305::new(Key::from_slice(&key))
+                .decrypt(Nonce::from_slice(&nonce), Payload { msg: &ciphertext, aad: aad.as_bytes() })
+                .map_err(|_| invalid_data("wrong passphrase or the credential file is corrupted"))
+        );
+
+        serde_json::from_slice(&content).map_err(invalid_data)
+    }
+
+    fn write_file(&self, file: &CredentialFile) -> io::Result<()> {
+        let mut content = try!(serde_json::to_vec_pretty(file).map_err(invalid_data));
+
+        if let Some(ref passphrase) = self.passphrase {
+            if self.iterations == 0 {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "the iteration count of PBKDF2 must be positive"));
+            }
+
+            let salt = try!(random_bytes(SALT_LEN));
+            let nonce = try!(random_bytes(NONCE_LEN));
+            let mut encrypted = EncryptedCredentialFile {
+                version: ENCRYPTION_VERSION,
+                iterations: self.iterations,
+                salt: base64::encode(&salt),
+                nonce: base64::encode(&nonce),
+                ciphertext: String::new(),
+            };
+
+            let key = derive_key(passphrase, &salt, self.iterations);
+            let aad = encrypted.associated_data();
+            let ciphertext = try!(
+                ChaCha20Poly1305::new(Key::from_slice(&key))
+                    .encrypt(Nonce::from_slice(&nonce), Payload { msg: &content, aad: aad.as_bytes() })
+                    .map_err(|_| invalid_data("failed to encrypt the credentials"))
+            );
+            encrypted.ciphertext = base64::encode(&ciphertext);
+
+            content = try!(serde_json::to_vec_pretty(&encrypted).map_err(invalid_data));
+        }
+
+        // Write to a temporary file and rename it so that the file is never half-written.
+        // The name is unique so that a writer ignoring the lock cannot truncate our file.
+        let mut tmp_path = self.path.clone().into_os_string();
+        tmp_path.push(format!(".{}.{:016x}.tmp", ::std::process::id(), OsRng.next_u64()));
+        let res = create_private_file(tmp_path.as_ref())
+            .and_then(|mut f| f.write_all(&content).and_then(|_| f.sync_all()))
+            .and_then(|_| fs::rename(&tmp_path, &self.path));
+        if res.is_err() {
+            let _ = fs::remove_file(&tmp_path);
+        }
+        res
+    }
+
+    fn lock(&self) -> io::Result<FileLock> {
+        let mut path = self.path.clone().into_os_string();
+        path.push(".lock");
+        FileLock::acquire(path.into())
+    }
+
+    fn to_credential(&self, mut value: serde_json::Value) -> io::Result<Credential> {
+        // The signer is not saved, so RSA-SHA1 is restored with the given one
+        let rsa_sha1 = value.get("signature_method").and_then(|x| x.as_str()) == Some("RSA-SHA1");
+        if rsa_sha1 {
+            if let Some(x) = value.as_object_mut() {
+                x.remove("signature_method");
+            }
+        }
+
+        let mut credential: Credential = try!(serde_json::from_value(value).map_err(invalid_data));
+        if rsa_sha1 {
+            match (&mut credential, &self.rsa_sha1) {
+                (&mut Credential::OAuth(ref mut x), &Some(ref signature_method)) => x.signature_method = signature_method.clone(),
+                _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, "the profile is signed with RSA-SHA1 but no signer is given")),
+            }
+        }
+        Ok(credential)
+    }
+}
+
+const LOCK_TIMEOUT_SECS: u64 = 10;
+
+/// Advisory lock held by creating a file exclusively. The file is removed on drop.
+struct FileLock {
+    path: PathBuf,
+}
+
+impl FileLock {
+    fn acquire(path: PathBuf) -> io::Result<FileLock> {
+        let timeout = Duration::from_secs(LOCK_TIMEOUT_SECS);
+        let start = Instant::now();
+        loop {
+            match fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => return Ok(FileLock { path: path }),
+                Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => (),
+                Err(e) => return Err(e),
+            }
+
+            let stale = fs::metadata(&path)
+                .and_then(|x| x.modified())
+                .ok()
+                .and_then(|x| SystemTime::now().duration_since(x).ok())
+                .map_or(false, |x| x > timeout);
+            if stale {
+                // The owner has probably crashed
+                let _ = fs::remove_file(&path);
+            } else if start.elapsed() > timeout {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "timed out waiting for the lock of the credential file"));
+            } else {
+                thread::sleep(Duration::from_millis(10));
+            }
+        }
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(unix)]
+fn create_private_file(path: &::std::path::Path) -> io::Result<fs::File> {
+    use std::os::unix::fs::OpenOptionsExt;
+    fs::OpenOptions::new().write(true).create(true).truncate(true).mode(0o600).open(path)
+}
+
+#[cfg(not(unix))]
+fn create_private_file(path: &::std::path::Path) -> io::Result<fs::File> {
+    fs::File::create(path)
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], iterations: u32) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, iterations, &mut key);
+    key
+}
+
+impl CredentialStore for FileCredentialStore {
+    fn load(&self, profile: &str) -> io::Result<Option<Credential>> {
+        match try!(self.read_file()).profiles.remove(profile) {
+            Some(x) => self.to_credential(x).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn save(&self, profile: &str, credential: &Credential) -> io::Result<()> {
+        let _lock = try!(self.lock());
+        let mut file = try!(self.read_file());
+        file.profiles.insert(profile.to_owned(), try!(serde_json::to_value(credential).map_err(invalid_data)));
+        self.write_file(&file)
+    }
+
+    fn remove(&self, profile: &str) -> io::Result<bool> {
+        let _lock = try!(self.lock());
+        let mut file = try!(self.read_file());
+        if file.profiles.remove(profile).is_none() {
+            return Ok(false);
+        }
+        try!(self.write_file(&file));
+        Ok(true)
+    }
+
+    fn profiles(&self) -> io::Result<Vec<String>> {
+        Ok(try!(self.read_file()).profiles.into_iter().map(|x| x.0).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::error::Error;
+    use std::fs;
+    use std::io;
+    use ::{ApplicationOnlyAuthenticator, OAuthAuthenticator};
+    use conn::oauth_authenticator::{OAuthSignatureMethod, RsaSha1Signer};
+    use oauth2::UserTokenResponse;
+    use super::*;
+
+    struct FakeSigner;
+
+    impl RsaSha1Signer for FakeSigner {
+        fn sign(&self, _: &[u8]) -> Result<Vec<u8>, Box<Error + Send + Sync>> {
+            Ok(b"sig".to_vec())
+        }
+    }
+
+    #[test]
+    fn file_store_test() {
+        let path = env::temp_dir().join(format!("tweetust-credentials-{}.json", ::std::process::id()));
+        let store = FileCredentialStore::new(&path).passphrase("secret").pbkdf2_iterations(10);
+
+        store.save("user", &Credential::from(OAuthAuthenticator::new("ck", "cs", "at", "ats"))).unwrap();
+        store.save("app", &Credential::from(ApplicationOnlyAuthenticator::new("token"))).unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(!content.contains("ats"));
+        assert!(content.contains("\"version\": 2"));
+
+        assert_eq!(store.profiles().unwrap(), vec!["app".to_owned(), "user".to_owned()]);
+        match store.load("user").unwrap() {
+            Some(Credential::OAuth(x)) => assert_eq!(x.access_token_secret, "ats"),
+            x => panic!("unexpected {:?}", x),
+        }
+
+        let wrong = FileCredentialStore::new(&path).passphrase("wrong");
+        assert_eq!(wrong.load("user").unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        assert!(store.remove("app").unwrap());
+        assert!(store.load("app").unwrap().is_none());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn profile_kinds_test() {
+        let path = env::temp_dir().join(format!("tweetust-credentials-kinds-{}.json", ::std::process::id()));
+        let store = FileCredentialStore::new(&path);
+
+        let rsa = OAuthAuthenticator::new("ck", "cs", "at", "ats").signature_method(OAuthSignatureMethod::rsa_sha1(FakeSigner));
+        store.save("rsa", &Credential::from(rsa)).unwrap();
+        let token = UserTokenResponse {
+            token_type: "bearer".to_owned(),
+            access_token: "access".to_owned(),
+            expires_in: Some(7200),
+            refresh_token: Some("refresh".to_owned()),
+            scope: None,
+        };
+        store.save("oauth2", &Credential::from(token.to_authenticator("client"))).unwrap();
+
+        match store.load("oauth2").unwrap() {
+            Some(Credential::OAuth2User(x)) => {
+                assert_eq!(x.access_token(), "access");
+                assert_eq!(x.refresh_token(), Some("refresh".to_owned()));
+                assert!(x.expires_at().is_some());
+            }
+            x => panic!("unexpected {:?}", x),
+        }
+
+        // The signer is not saved
+        assert_eq!(store.load("rsa").unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        match store.clone().rsa_sha1_signer(FakeSigner).load("rsa").unwrap() {
+            Some(Credential::OAuth(x)) => assert_eq!(x.signature_method.name(), "RSA-SHA1"),
+            x => panic!("unexpected {:?}", x),
+        }
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn concurrent_save_test() {
+        let path = env::temp_dir().join(format!("tweetust-credentials-concurrent-{}.json", ::std::process::id()));
+        let threads: Vec<_> = (0..8)
+            .map(|i| {
+                let store = FileCredentialStore::new(&path);
+                ::std::thread::spawn(move || {
+                    store.save(&format!("app{}", i), &Credential::from(ApplicationOnlyAuthenticator::new("token"))).unwrap();
+                })
+            })
+            .collect();
+        for x in threads {
+            x.join().unwrap();
+        }
+
+        // No update is lost and no temporary file is left
+        assert_eq!(FileCredentialStore::new(&path).profiles().unwrap().len(), 8);
+        let prefix = path.file_name().unwrap().to_str().unwrap().to_owned();
+        let leftovers = fs::read_dir(env::temp_dir()).unwrap()
+            .filter_map(|x| x.ok())
+            .map(|x| x.file_name().to_string_lossy().into_owned())
+            .filter(|x| x.starts_with(&prefix) && x.len() > prefix.len())
+            .count();
+        assert_eq!(leftovers, 0);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
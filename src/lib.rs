@@ -34,34 +34,56 @@
 
 #![warn(unused_import_braces)]
 
+extern crate base64;
+extern crate chacha20poly1305;
 pub extern crate chrono;
+extern crate crossbeam_utils;
 pub extern crate hyper;
 extern crate multipart;
 extern crate oauthcli;
+extern crate pbkdf2;
+extern crate rand;
 extern crate serde;
 #[macro_use] extern crate serde_derive;
-extern crate serde_json;
+pub extern crate serde_json;
+extern crate sha2;
+extern crate unicode_normalization;
 extern crate url;
 
+#[cfg(test)] #[macro_use]
+extern crate matches;
+
+#[cfg(feature = "futures")]
+pub extern crate futures;
 #[cfg(feature = "hyper-native-tls")]
 pub extern crate hyper_native_tls;
+#[cfg(feature = "openssl")]
+pub extern crate openssl;
 
 use std::error::Error;
 use std::fmt;
 use std::io;
-use models::{RawResponse, TwitterResponse};
-use models::ErrorResponse;
+use models::{RateLimitStatus, RawResponse, TwitterResponse};
+use hyper::status::StatusCode;
+use models::{ErrorResponse, MediaProcessingError, TwitterErrorCode};
 
 pub use clients::TwitterClient;
 pub use conn::DefaultHttpHandler;
 pub use conn::application_only_authenticator::ApplicationOnlyAuthenticator;
+pub use conn::mock_http_handler::MockHttpHandler;
 pub use conn::oauth_authenticator::OAuthAuthenticator;
+pub use conn::rate_limit_tracker::{RateLimitedHttpHandler, RateLimitTracker};
+pub use conn::retrying_http_handler::RetryingHttpHandler;
+pub use conn::user_bearer_authenticator::UserBearerAuthenticator;
 
 pub mod clients;
 pub mod conn;
+pub mod credentials;
 pub mod models;
 pub mod oauth;
 pub mod oauth2;
+pub mod streaming;
+pub mod text;
 
 #[derive(Debug)]
 pub enum TwitterError {
@@ -71,6 +93,83 @@ pub enum TwitterError {
     Io(io::Error),
     Http(hyper::Error),
     ParseResponse(Option<serde_json::Error>, RawResponse),
+    /// The request was not sent because the rate limit of the endpoint is exhausted.
+    RateLimitExceeded(RateLimitStatus),
+    /// The uploaded media was rejected after FINALIZE command.
+    MediaProcessing(MediaProcessingError),
+    /// The operation was aborted by `CancellationToken`.
+    Cancelled,
+    /// The format of the media could not be detected.
+    UnsupportedMedia,
+    /// The media is larger than the limit of its type.
+    MediaTooLarge { size: u64, limit: u64 },
+    /// The authenticator failed to sign the request.
+    Signature(Box<Error + Send + Sync>),
+}
+
+impl TwitterError {
+    /// Returns the error codes in the error response, or an empty `Vec` for the other errors.
+    pub fn error_codes(&self) -> Vec<TwitterErrorCode> {
+        match *self {
+            TwitterError::ErrorResponse(ref e) => e.error_codes(),
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn has_error_code(&self, code: TwitterErrorCode) -> bool {
+        match *self {
+            TwitterError::ErrorResponse(ref e) => e.has_error_code(code),
+            _ => false,
+        }
+    }
+
+    fn status(&self) -> Option<StatusCode> {
+        match *self {
+            TwitterError::ErrorResponse(ref e) => Some(e.status),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the request was refused because of the rate limit.
+    pub fn is_rate_limited(&self) -> bool {
+        match *self {
+            TwitterError::RateLimitExceeded(_) => true,
+            _ => self.status() == Some(StatusCode::TooManyRequests)
+                || self.has_error_code(TwitterErrorCode::RateLimitExceeded),
+        }
+    }
+
+    /// Returns true if the credentials are invalid, expired or not allowed to access the endpoint.
+    pub fn is_auth_error(&self) -> bool {
+        self.status() == Some(StatusCode::Unauthorized)
+            || self.error_codes().iter().any(|x| match *x {
+                TwitterErrorCode::CouldNotAuthenticate | TwitterErrorCode::InvalidOrExpiredToken |
+                TwitterErrorCode::UnableToVerifyCredentials | TwitterErrorCode::TimestampOutOfBounds |
+                TwitterErrorCode::BadAuthenticationData | TwitterErrorCode::CredentialsNotAllowed |
+                TwitterErrorCode::ApplicationCannotWrite => true,
+                _ => false,
+            })
+    }
+
+    /// Returns true if the status is the same as the last one.
+    pub fn is_duplicate_status(&self) -> bool {
+        self.has_error_code(TwitterErrorCode::DuplicateStatus)
+    }
+
+    /// Returns true if sending the same request later may succeed.
+    pub fn is_retryable(&self) -> bool {
+        match *self {
+            TwitterError::Io(_) | TwitterError::Http(hyper::Error::Io(_)) => true,
+            TwitterError::ErrorResponse(ref e) => match e.status {
+                StatusCode::TooManyRequests | StatusCode::InternalServerError | StatusCode::BadGateway |
+                StatusCode::ServiceUnavailable | StatusCode::GatewayTimeout => true,
+                _ => self.is_rate_limited()
+                    || self.has_error_code(TwitterErrorCode::OverCapacity)
+                    || self.has_error_code(TwitterErrorCode::InternalError),
+            },
+            _ => self.is_rate_limited(),
+        }
+    }
 }
 
 impl Error for TwitterError {
@@ -87,6 +186,12 @@ impl Error for TwitterError {
             TwitterError::Http(ref e) => Some(e),
             TwitterError::ParseResponse(Some(ref e), _) => Some(e),
             TwitterError::ParseResponse(None, _) => None,
+            TwitterError::RateLimitExceeded(_) => None,
+            TwitterError::MediaProcessing(ref e) => Some(e),
+            TwitterError::Cancelled => None,
+            TwitterError::UnsupportedMedia => None,
+            TwitterError::MediaTooLarge { .. } => None,
+            TwitterError::Signature(ref e) => Some(&**e),
         }
     }
 }
@@ -100,6 +205,12 @@ impl fmt::Display for TwitterError {
             TwitterError::Io(ref e) => fmt::Display::fmt(e, f),
             TwitterError::Http(ref e) => fmt::Display::fmt(e, f),
             TwitterError::ParseResponse(_, ref res) => write!(f, "invalid response body: {}", res.raw_response),
+            TwitterError::RateLimitExceeded(ref x) => write!(f, "rate limit exceeded until {}", x.reset_date_time()),
+            TwitterError::MediaProcessing(ref e) => write!(f, "media processing failed: {}", e),
+            TwitterError::Cancelled => f.write_str("cancelled"),
+            TwitterError::UnsupportedMedia => f.write_str("unsupported media type"),
+            TwitterError::MediaTooLarge { size, limit } => write!(f, "media too large: {} bytes (limit {} bytes)", size, limit),
+            TwitterError::Signature(ref e) => write!(f, "failed to sign the request: {}", e),
         }
     }
 }
@@ -130,6 +241,10 @@ impl From<io::Error> for TwitterError {
 
 pub type TwitterResult<T> = Result<TwitterResponse<T>, TwitterError>;
 
+/// The future version of `TwitterResult`, returned by `execute_async` methods.
+#[cfg(feature = "futures")]
+pub type FutureTwitterResult<'a, T> = Box<futures::Future<Item = TwitterResponse<T>, Error = TwitterError> + 'a>;
+
 fn parse_json<T: serde::de::DeserializeOwned>(s: &str) -> serde_json::Result<T> {
     serde_json::from_str(s)
 }
@@ -2,5 +2,33 @@
 pub struct CursorIds {
     pub previous_cursor: i64,
     pub next_cursor: i64,
-    pub ids: Vec<i64>
+    pub ids: Vec<i64>,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
+
+/// The response which is paged by `cursor` parameter.
+pub trait CursorPage {
+    type Item;
+
+    fn previous_cursor(&self) -> i64;
+    fn next_cursor(&self) -> i64;
+    fn into_items(self) -> Vec<Self::Item>;
+}
+
+macro_rules! cursor_page {
+    ($t:ty, $item:ty, $field:ident) => (
+        impl CursorPage for $t {
+            type Item = $item;
+
+            fn previous_cursor(&self) -> i64 { self.previous_cursor }
+            fn next_cursor(&self) -> i64 { self.next_cursor }
+            fn into_items(self) -> Vec<$item> { self.$field }
+        }
+    )
+}
+
+cursor_page!(CursorIds, i64, ids);
+cursor_page!(CursorUsers, User, users);
+cursor_page!(CursorLists, List, lists);
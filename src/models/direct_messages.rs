@@ -9,5 +9,13 @@ pub struct DirectMessage {
     pub recipient_id: i64,
     pub recipient_screen_name: String,
     pub created_at: CreatedAt,
-    pub entities: Option<Box<Entities>>
+    pub entities: Option<Box<Entities>>,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+
+impl TimelineItem for DirectMessage {
+    fn id(&self) -> i64 { self.id }
+    fn created_at(&self) -> &CreatedAt { &self.created_at }
 }
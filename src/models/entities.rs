@@ -4,23 +4,35 @@ pub struct Entities {
     pub symbols: Vec<SymbolEntity>,
     pub media: Option<Vec<MediaEntity>>,
     pub urls: Vec<UrlEntity>,
-    pub user_mentions: Vec<UserMentionEntity>
+    pub user_mentions: Vec<UserMentionEntity>,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ExtendedEntities {
-    pub media: Vec<MediaEntity>
+    pub media: Vec<MediaEntity>,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct UserEntities {
     pub url: Option<UserEntitiesField>,
-    pub description: UserEntitiesField
+    pub description: UserEntitiesField,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct UserEntitiesField {
-    pub urls: Vec<UrlEntity>
+    pub urls: Vec<UrlEntity>,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
@@ -62,7 +74,10 @@ impl<'x> Deserialize<'x> for TextRange {
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct SymbolEntity {
     pub indices: TextRange,
-    pub text: String
+    pub text: String,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -79,14 +94,20 @@ pub struct MediaEntity {
     #[serde(rename = "type")]
     pub media_type: String,
     pub url: String,
-    pub video_info: Option<Box<VideoInfo>>
+    pub video_info: Option<Box<VideoInfo>>,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct MediaSize {
     pub h: u32,
     pub resize: String,
-    pub w: u32
+    pub w: u32,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -94,21 +115,30 @@ pub struct MediaSizes {
     pub thumb: Option<MediaSize>,
     pub large: Option<MediaSize>,
     pub medium: Option<MediaSize>,
-    pub small: Option<MediaSize>
+    pub small: Option<MediaSize>,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct VideoInfo {
     pub aspect_ratio: Vec<u32>,
     pub duration_millis: Option<u32>,
-    pub variants: Vec<VideoVariant>
+    pub variants: Vec<VideoVariant>,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct VideoVariant {
     pub bitrate: Option<u32>,
     pub content_type: String,
-    pub url: String
+    pub url: String,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -116,7 +146,10 @@ pub struct UrlEntity {
     pub display_url: Option<String>,
     pub expanded_url: Option<String>,
     pub indices: TextRange,
-    pub url: String
+    pub url: String,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -124,5 +157,8 @@ pub struct UserMentionEntity {
     pub id: i64,
     pub indices: TextRange,
     pub name: String,
-    pub screen_name: String
+    pub screen_name: String,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
@@ -1,7 +1,10 @@
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Error {
     pub code: i32,
-    pub message: String
+    pub message: String,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug)]
@@ -12,6 +15,23 @@ pub struct ErrorResponse {
     pub rate_limit: Option<RateLimitStatus>
 }
 
+impl Error {
+    pub fn error_code(&self) -> TwitterErrorCode {
+        TwitterErrorCode::from_code(self.code)
+    }
+}
+
+impl ErrorResponse {
+    /// Returns the codes of `errors`.
+    pub fn error_codes(&self) -> Vec<TwitterErrorCode> {
+        self.errors.iter().flat_map(|x| x.iter()).map(|x| x.error_code()).collect()
+    }
+
+    pub fn has_error_code(&self, code: TwitterErrorCode) -> bool {
+        self.errors.iter().flat_map(|x| x.iter()).any(|x| x.code == code.code())
+    }
+}
+
 impl std::error::Error for ErrorResponse {
     fn description(&self) -> &str {
         "the server returned an error response"
@@ -21,11 +41,99 @@ impl std::error::Error for ErrorResponse {
 impl std::fmt::Display for ErrorResponse {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self.errors {
-            Some(ref x) => {
-                let ref e = x[0];
-                write!(f, "{}: {} {}", self.status, e.code, e.message)
+            Some(ref x) if !x.is_empty() => {
+                try!(write!(f, "{}:", self.status));
+                for (i, e) in x.iter().enumerate() {
+                    try!(write!(f, "{} {} {}", if i == 0 { "" } else { ";" }, e.code, e.message));
+                }
+                Ok(())
             },
-            None => write!(f, "{}: {}", self.status, self.raw_response)
+            _ => write!(f, "{}: {}", self.status, self.raw_response)
         }
     }
 }
+
+macro_rules! error_codes {
+    ($name:ident { $($variant:ident($code:expr), )* }) => {
+        /// The error codes documented in https://developer.twitter.com/en/docs/basics/response-codes
+        #[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+        pub enum $name {
+            $($variant,)*
+            Other(i32)
+        }
+
+        impl $name {
+            pub fn from_code(code: i32) -> $name {
+                match code {
+                    $( $code => $name::$variant, )*
+                    x => $name::Other(x),
+                }
+            }
+
+            pub fn code(&self) -> i32 {
+                match *self {
+                    $( $name::$variant => $code, )*
+                    $name::Other(x) => x,
+                }
+            }
+        }
+    }
+}
+
+error_codes!(TwitterErrorCode {
+    InvalidCoordinates(3),
+    NoLocationAssociatedWithIp(13),
+    NoUserMatchesSpecifiedTerms(17),
+    CouldNotAuthenticate(32),
+    PageDoesNotExist(34),
+    CannotReportYourselfForSpam(36),
+    ParameterMissing(38),
+    AttachmentUrlInvalid(44),
+    UserNotFound(50),
+    UserSuspended(63),
+    AccountSuspended(64),
+    EndpointDeprecated(68),
+    ClientNotPermitted(87),
+    RateLimitExceeded(88),
+    InvalidOrExpiredToken(89),
+    SslRequired(92),
+    DirectMessagesNotPermitted(93),
+    UnableToVerifyCredentials(99),
+    AccountUpdateFailed(120),
+    OverCapacity(130),
+    InternalError(131),
+    TimestampOutOfBounds(135),
+    AlreadyFavorited(139),
+    NoStatusFound(144),
+    CannotSendMessagesToNonFollowers(150),
+    ErrorSendingMessage(151),
+    FollowAlreadyRequested(160),
+    FollowLimitReached(161),
+    NotAuthorizedToSeeStatus(179),
+    StatusUpdateLimitReached(185),
+    StatusTooLong(186),
+    DuplicateStatus(187),
+    SpamReportLimitReached(205),
+    BadAuthenticationData(215),
+    CredentialsNotAllowed(220),
+    AutomatedRequest(226),
+    LoginVerificationNeeded(231),
+    EndpointRetired(251),
+    ApplicationCannotWrite(261),
+    CannotMuteYourself(271),
+    NotMuting(272),
+    AnimatedGifWithOtherImages(323),
+    MediaIdValidationFailed(324),
+    MediaIdNotFound(325),
+    AccountTemporarilyLocked(326),
+    AlreadyRetweeted(327),
+    CannotSendMessagesToUser(349),
+    DirectMessageTooLong(354),
+    AlreadySubscribed(355),
+    ReplyToUnavailableStatus(385),
+    TooManyAttachmentTypes(386),
+    UrlCannotBeResolved(407),
+    CallbackUrlNotApproved(415),
+    ApplicationSuspended(416),
+    OobOnlyForDesktopApplications(417),
+});
@@ -3,7 +3,10 @@ pub struct Connections {
     pub name: String,
     pub screen_name: String,
     pub id: i64,
-    pub connections: Vec<String>
+    pub connections: Vec<String>,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -21,7 +24,10 @@ pub struct RelationshipSource {
     pub muting: Option<bool>,
     pub want_retweets: Option<bool>,
     pub all_replies: Option<bool>,
-    pub marked_spam: Option<bool>
+    pub marked_spam: Option<bool>,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -31,18 +37,27 @@ pub struct RelationshipTarget {
     pub following: bool,
     pub followed_by: bool,
     pub following_received: Option<bool>,
-    pub following_requested: Option<bool>
+    pub following_requested: Option<bool>,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Relationship {
     pub target: RelationshipTarget,
-    pub source: RelationshipSource
+    pub source: RelationshipSource,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct FriendshipResponse {
-    pub relationship: Relationship
+    pub relationship: Relationship,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -50,5 +65,8 @@ pub struct Friendship {
     pub id: i64,
     pub screen_name: String,
     pub name: String,
-    pub connections: Vec<String>
+    pub connections: Vec<String>,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
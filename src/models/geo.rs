@@ -2,11 +2,17 @@
 pub struct GeoResponse {
     pub result: GeoResult,
     pub query: GeoQuery,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct GeoResult {
     pub places: Vec<Place>,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -15,6 +21,9 @@ pub struct GeoQuery {
     #[serde(rename = "type")]
     pub query_type: String,
     pub params: GeoQueryParams,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -25,4 +34,7 @@ pub struct GeoQueryParams {
     pub autocomplete: Option<bool>,
     pub trim_place: Option<bool>,
     pub coordinates: TweetCoordinates,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
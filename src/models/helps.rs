@@ -7,22 +7,34 @@ pub struct Configuration {
     pub photo_size_limit: u32,
     pub short_url_length: u32,
     pub short_url_length_https: u32,
-    pub photo_sizes: MediaSizes
+    pub photo_sizes: MediaSizes,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Language {
     pub code: String,
     pub name: String,
-    pub status: String
+    pub status: String,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PrivacyResponse {
-    pub privacy: String
+    pub privacy: String,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TosResponse {
-    pub tos: String
+    pub tos: String,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
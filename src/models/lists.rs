@@ -12,6 +12,9 @@ pub struct List {
     pub description: String,
     pub user: User,
     pub following: bool,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 enum_str!(ListMode {
@@ -24,4 +27,7 @@ pub struct CursorLists {
     pub previous_cursor: i64,
     pub next_cursor: i64,
     pub lists: Vec<List>,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
@@ -5,6 +5,9 @@ pub struct MediaUploadResponse {
     pub media_id: i64,
     pub size: u64,
     pub video: Option<UploadedVideo>,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -12,17 +15,26 @@ pub struct UploadedImage {
     pub w: u32,
     pub h: u32,
     pub image_type: String,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct UploadedVideo {
     pub video_type: String,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct UploadInitCommandResponse {
     pub expires_after_secs: u32,
     pub media_id: i64,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -31,13 +43,31 @@ pub struct ProcessingInfo {
     pub check_after_secs: Option<u32>,
     pub progress_percent: Option<u8>,
     pub error: Option<MediaProcessingError>,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct MediaProcessingError {
     pub code: i32,
     pub name: String,
     pub message: String,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+
+impl std::error::Error for MediaProcessingError {
+    fn description(&self) -> &str {
+        "the media processing failed"
+    }
+}
+
+impl std::fmt::Display for MediaProcessingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} {}: {}", self.code, self.name, self.message)
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -48,10 +78,16 @@ pub struct UploadFinalizeCommandResponse {
     pub processing_info: Option<ProcessingInfo>,
     pub size: u64,
     pub video: Option<UploadedVideo>,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct UploadStatusCommandResponse {
     pub media_id: i64,
     pub processing_info: ProcessingInfo,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
@@ -14,8 +14,17 @@ pub struct TwitterResponse<T> {
     pub rate_limit: Option<RateLimitStatus>,
 }
 
+impl<T> TwitterResponse<T> {
+    /// Decodes `raw_response` into another type, e.g. your own model which has the fields this crate does not know.
+    pub fn parse_raw_as<U: de::DeserializeOwned>(&self) -> ::serde_json::Result<U> {
+        parse_json(&self.raw_response)
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct RawResponse {
+    /// The status code of the successful response, e.g. 204 for APPEND command of media/upload.
+    pub status: ::hyper::status::StatusCode,
     pub raw_response: String,
     pub rate_limit: Option<RateLimitStatus>,
 }
@@ -41,6 +50,13 @@ impl RawResponse {
     }
 }
 
+/// The fields in the JSON which are not declared in the model.
+///
+/// With `extra-fields` feature, every model which can be deserialized has `extra` field of this type,
+/// so that the fields added to the API can be read without waiting for the update of this crate.
+#[cfg(feature = "extra-fields")]
+pub type ExtraFields = ::serde_json::Map<String, ::serde_json::Value>;
+
 // https://serde.rs/enum-str.html
 macro_rules! enum_str {
     ($name:ident { $($variant:ident($str:expr), )* }) => {
@@ -50,15 +66,21 @@ macro_rules! enum_str {
             Other(String)
         }
 
+        impl $name {
+            pub fn as_str(&self) -> &str {
+                match *self {
+                    $( $name::$variant => $str, )*
+                    $name::Other(ref x) => x.as_ref(),
+                }
+            }
+        }
+
         impl ::serde::Serialize for $name {
             fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
                 where S: ::serde::Serializer,
             {
                 // Serialize the enum as a string.
-                serializer.serialize_str(match *self {
-                    $( $name::$variant => $str, )*
-                    $name::Other(ref x) => x.as_ref(),
-                })
+                serializer.serialize_str(self.as_str())
             }
         }
 
@@ -105,6 +127,7 @@ include!("places.rs");
 include!("rate_limit.rs");
 include!("search.rs");
 include!("settings.rs");
+include!("streaming.rs");
 include!("tweets.rs");
 include!("users.rs");
 
@@ -250,6 +273,7 @@ mod test {
 "##;
 
         let resp = RawResponse {
+            status: ::hyper::status::StatusCode::Ok,
             raw_response: response_json.to_string(),
             rate_limit: None,
         };
@@ -258,4 +282,38 @@ mod test {
         assert!(twitter_result.is_ok());
     }
 
+    #[cfg(feature = "extra-fields")]
+    #[test]
+    fn keeps_unknown_fields() {
+        let resp = RawResponse {
+            status: ::hyper::status::StatusCode::Ok,
+            raw_response: r#"{"created_at":"Fri May 25 21:32:47 +0000 2018","id":1,"retweet_count":0,"source":"x","scopes":{"followers":false}}"#.to_owned(),
+            rate_limit: None,
+        };
+        let tweet: Tweet = resp.parse_to_object().unwrap().object;
+        assert_eq!(tweet.extra["scopes"]["followers"], false);
+        assert!(!tweet.extra.contains_key("id"));
+    }
+
+    #[test]
+    fn error_code_test() {
+        use super::{Error, ErrorResponse, TwitterErrorCode};
+        use ::hyper::status::StatusCode;
+
+        let e = ErrorResponse {
+            status: StatusCode::Forbidden,
+            errors: Some(::serde_json::from_str::<Vec<Error>>(
+                r#"[{"code":187,"message":"Status is a duplicate."},{"code":1000,"message":"Unknown"}]"#
+            ).unwrap()),
+            raw_response: String::new(),
+            rate_limit: None,
+        };
+        assert_eq!(e.error_codes(), vec![TwitterErrorCode::DuplicateStatus, TwitterErrorCode::Other(1000)]);
+        assert_eq!(e.to_string(), "403 Forbidden: 187 Status is a duplicate.; 1000 Unknown");
+
+        let e = ::TwitterError::ErrorResponse(e);
+        assert!(e.is_duplicate_status());
+        assert!(!e.is_retryable());
+        assert!(!e.is_auth_error());
+    }
 }
\ No newline at end of file
@@ -13,7 +13,10 @@ pub struct Place {
     pub place_type: String,
     //pub polylines: Option<Vec<?>>,
     pub url: String,
-    pub vendor_info: Option<GeoVendorInfo>
+    pub vendor_info: Option<GeoVendorInfo>,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -21,6 +24,9 @@ pub struct BoundingBox {
     pub coordinates: Vec<Vec<Coordinates>>,
     #[serde(rename = "type")]
     pub box_type: String,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -28,6 +34,9 @@ pub struct Geometry {
     #[serde(rename = "type")]
     pub geometry_type: String,
     pub coordinates: Coordinates,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -69,6 +78,9 @@ impl<'x> Deserialize<'x> for Coordinates {
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct GeoVendorInfo {
     pub yelp: Option<Box<VendorYelp>>,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -76,6 +88,9 @@ pub struct VendorYelp {
     pub business_id: String,
     pub mobile_url: String,
     pub url: String,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -89,12 +104,18 @@ pub struct TrendPlace {
     pub place_type: PlaceType,
     pub url: String,
     pub woeid: i64,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PlaceType {
     pub code: i32,
     pub name: String,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -103,12 +124,18 @@ pub struct TrendsResult {
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub locations: Vec<TrendLocation>,
     pub trends: Vec<Trend>,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TrendLocation {
     pub name: String,
     pub woeid: i64,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -118,4 +145,7 @@ pub struct Trend {
     // pub promoted_content: Option<?>,
     pub query: String,
     pub tweet_volume: Option<u32>,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
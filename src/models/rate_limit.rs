@@ -16,9 +16,15 @@ impl RateLimitStatus {
 pub struct RateLimitStatusResponse {
     pub rate_limit_context: RateLimitContext,
     pub resources: HashMap<String, HashMap<String, RateLimitStatus>>,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct RateLimitContext {
     pub access_token: String,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
@@ -2,6 +2,9 @@
 pub struct SearchResponse {
     pub statuses: Vec<Tweet>,
     pub search_metadata: SearchMetadata,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -13,6 +16,9 @@ pub struct SearchMetadata {
     pub count: i32,
     pub completed_in: f32,
     pub query: String,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -22,4 +28,13 @@ pub struct SavedSearch {
     pub name: String,
     pub query: String,
     // pub position: Option<?>
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+
+impl TimelinePage for SearchResponse {
+    type Item = Tweet;
+
+    fn into_items(self) -> Vec<Tweet> { self.statuses }
 }
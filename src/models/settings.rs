@@ -17,6 +17,9 @@ pub struct AccountSettings {
     pub translator_type: String,
     pub trend_location: Vec<TrendPlace>,
     pub use_cookie_personalization: bool,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -24,6 +27,9 @@ pub struct SleepTime {
     pub enabled: bool,
     pub end_time: Option<i32>,
     pub start_time: Option<i32>,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -31,4 +37,7 @@ pub struct TimeZone {
     pub name: String,
     pub tzinfo_name: String,
     pub utc_offset: i32,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
@@ -0,0 +1,100 @@
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StatusDeletion {
+    pub id: i64,
+    pub user_id: i64,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScrubGeo {
+    pub user_id: i64,
+    pub up_to_status_id: i64,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StreamLimit {
+    pub track: u64,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StreamDisconnect {
+    pub code: i32,
+    pub stream_name: Option<String>,
+    pub reason: String,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StreamWarning {
+    pub code: String,
+    pub message: String,
+    pub percent_full: Option<u32>,
+    pub user_id: Option<i64>,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StatusWithheld {
+    pub id: i64,
+    pub user_id: i64,
+    pub withheld_in_countries: Vec<String>,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UserWithheld {
+    pub id: i64,
+    pub withheld_in_countries: Vec<String>,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StreamEvent {
+    pub event: StreamEventKind,
+    pub created_at: CreatedAt,
+    pub source: User,
+    pub target: User,
+    pub target_object: Option<::serde_json::Value>,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
+}
+
+enum_str!(StreamEventKind {
+    AccessRevoked("access_revoked"),
+    Block("block"),
+    Unblock("unblock"),
+    Favorite("favorite"),
+    Unfavorite("unfavorite"),
+    Follow("follow"),
+    Unfollow("unfollow"),
+    ListCreated("list_created"),
+    ListDestroyed("list_destroyed"),
+    ListUpdated("list_updated"),
+    ListMemberAdded("list_member_added"),
+    ListMemberRemoved("list_member_removed"),
+    ListUserSubscribed("list_user_subscribed"),
+    ListUserUnsubscribed("list_user_unsubscribed"),
+    QuotedTweet("quoted_tweet"),
+    UserUpdate("user_update"),
+    Mute("mute"),
+    Unmute("unmute"),
+    FavoritedRetweet("favorited_retweet"),
+    RetweetedRetweet("retweeted_retweet"),
+});
@@ -33,20 +33,29 @@ pub struct Tweet {
     pub user: Option<Box<User>>,
     pub withheld_copyright: Option<bool>,
     pub withheld_in_countries: Option<Vec<String>>,
-    pub withheld_scope: Option<String>
+    pub withheld_scope: Option<String>,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Contributor {
     pub id: i64,
-    pub screen_name: String
+    pub screen_name: String,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TweetCoordinates {
     pub coordinates: Coordinates,
     #[serde(rename = "type")]
-    pub coordinates_type: String
+    pub coordinates_type: String,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Copy, Debug, Serialize, Deserialize)]
@@ -56,7 +65,10 @@ pub struct CurrentUserRetweet {
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct LookupMap {
-    pub id: HashMap<String, Option<Tweet>>
+    pub id: HashMap<String, Option<Tweet>>,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -72,7 +84,10 @@ pub struct OEmbed {
     pub content_type: String,
     pub html: String,
     pub height: Option<i32>,
-    pub width: Option<i32>
+    pub width: Option<i32>,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -81,6 +96,9 @@ pub struct CompatExtendedTweet {
     pub display_text_range: TextRange,
     pub entities: Entities,
     pub extended_entities: Option<ExtendedEntities>,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 enum_str!(FilterLevel {
@@ -88,3 +106,27 @@ enum_str!(FilterLevel {
     Low("low"),
     Medium("medium"),
 });
+
+/// The item of the timelines paged by `max_id` and `since_id`.
+pub trait TimelineItem {
+    fn id(&self) -> i64;
+    fn created_at(&self) -> &CreatedAt;
+}
+
+impl TimelineItem for Tweet {
+    fn id(&self) -> i64 { self.id }
+    fn created_at(&self) -> &CreatedAt { &self.created_at }
+}
+
+/// The response which is paged by `max_id` and `since_id`.
+pub trait TimelinePage {
+    type Item: TimelineItem;
+
+    fn into_items(self) -> Vec<Self::Item>;
+}
+
+impl<T: TimelineItem> TimelinePage for Vec<T> {
+    type Item = T;
+
+    fn into_items(self) -> Vec<T> { self }
+}
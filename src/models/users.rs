@@ -48,6 +48,9 @@ pub struct User {
     pub verified: bool,
     pub withheld_in_countries: Option<String>,
     pub withheld_scope: Option<String>,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -55,6 +58,9 @@ pub struct CursorUsers {
     pub previous_cursor: i64,
     pub next_cursor: i64,
     pub users: Vec<User>,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -62,6 +68,9 @@ pub struct UserCategory {
     pub name: String,
     pub slug: String,
     pub size: u32,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -70,11 +79,17 @@ pub struct SuggestedUsers {
     pub slug: String,
     pub size: u32,
     pub users: Vec<User>,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ProfileBannerSizes {
     pub sizes: HashMap<String, ProfileBannerSize>,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -82,4 +97,7 @@ pub struct ProfileBannerSize {
     pub w: u32,
     pub h: u32,
     pub url: String,
+    #[cfg(feature = "extra-fields")]
+    #[serde(flatten)]
+    pub extra: ExtraFields,
 }
@@ -0,0 +1,269 @@
+use std::io::{self, Read, Write};
+use std::net::{Ipv4Addr, SocketAddrV4, TcpListener, TcpStream};
+use std::thread;
+use std::time::{Duration, Instant};
+use url::Url;
+use ::{TwitterError, TwitterResult};
+use conn::HttpHandler;
+use super::{request_token, AccessTokenResponse, AuthorizeUrlBuilder, RequestTokenResponse};
+
+const DEFAULT_TIMEOUT_SECS: u64 = 300;
+const CALLBACK_PATH: &'static str = "/callback";
+const MAX_REQUEST_SIZE: usize = 8192;
+
+/// The parameters of the redirect to `oauth_callback`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OAuthCallback {
+    pub oauth_token: String,
+    pub oauth_verifier: String,
+}
+
+/// A short-lived HTTP listener on 127.0.0.1 which receives the redirect after the authorization.
+#[derive(Debug)]
+pub struct CallbackServer {
+    listener: TcpListener,
+    timeout: Duration,
+}
+
+impl CallbackServer {
+    /// Listens on a port chosen by the OS.
+    pub fn bind() -> io::Result<CallbackServer> {
+        CallbackServer::bind_port(0)
+    }
+
+    pub fn bind_port(port: u16) -> io::Result<CallbackServer> {
+        let listener = try!(TcpListener::bind(SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), port)));
+        try!(listener.set_nonblocking(true));
+        Ok(CallbackServer {
+            listener: listener,
+            timeout: Duration::from_secs(DEFAULT_TIMEOUT_SECS),
+        })
+    }
+
+    /// Sets how long `wait` waits for the redirect. The default is 5 minutes.
+    pub fn timeout(mut self, val: Duration) -> Self {
+        self.timeout = val;
+        self
+    }
+
+    /// Returns the URL to pass as `oauth_callback`.
+    pub fn callback_url(&self) -> io::Result<String> {
+        let addr = try!(self.listener.local_addr());
+        Ok(format!("http://{}{}", addr, CALLBACK_PATH))
+    }
+
+    /// Waits for the redirect and returns `oauth_token` and `oauth_verifier`.
+    ///
+    /// Returns `io::ErrorKind::PermissionDenied` if the user denied the authorization,
+    /// or `io::ErrorKind::TimedOut` if the redirect didn't come in time.
+    pub fn wait(&self) -> io::Result<OAuthCallback> {
+        self.wait_core(None)
+    }
+
+    /// Waits for the redirect for `oauth_token`, ignoring the redirects for other request tokens
+    /// such as the ones left in the browser from a previous attempt.
+    ///
+    /// Returns `io::ErrorKind::PermissionDenied` if the user denied this authorization,
+    /// or `io::ErrorKind::TimedOut` if the redirect didn't come in time.
+    pub fn wait_for(&self, oauth_token: &str) -> io::Result<OAuthCallback> {
+        self.wait_core(Some(oauth_token))
+    }
+
+    fn wait_core(&self, oauth_token: Option<&str>) -> io::Result<OAuthCallback> {
+        let deadline = Instant::now() + self.timeout;
+
+        loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    if let Some(x) = try!(handle_connection(stream, oauth_token)) {
+                        return Ok(x);
+                    }
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    if Instant::now() >= deadline {
+                        return Err(io::Error::new(io::ErrorKind::TimedOut, "the callback didn't come in time"));
+                    }
+                    thread::sleep(Duration::from_millis(100));
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// Reads a request and returns the callback parameters if it is the redirect for `oauth_token`.
+fn handle_connection(mut stream: TcpStream, oauth_token: Option<&str>) -> io::Result<Option<OAuthCallback>> {
+    try!(stream.set_nonblocking(false));
+    try!(stream.set_read_timeout(Some(Duration::from_secs(5))));
+
+    let mut buf = Vec::new();
+    let mut chunk = [0; 1024];
+    while !buf.windows(4).any(|x| x == b"\r\n\r\n") && buf.len() < MAX_REQUEST_SIZE {
+        match stream.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+            // Ignore broken connections such as preconnects of browsers
+            Err(_) => return Ok(None),
+        }
+    }
+
+    let target = String::from_utf8_lossy(&buf).lines().next()
+        .and_then(|line| {
+            let mut parts = line.split(' ');
+            match (parts.next(), parts.next()) {
+                (Some("GET"), Some(target)) => Some(target.to_owned()),
+                _ => None,
+            }
+        });
+    let url = target.and_then(|x| Url::parse(&format!("http://127.0.0.1{}", x)).ok());
+
+    let url = match url {
+        Some(ref x) if x.path() == CALLBACK_PATH => x,
+        _ => {
+            let _ = respond(&mut stream, "404 Not Found", "Not Found");
+            return Ok(None);
+        }
+    };
+
+    let param = |key: &str| url.query_pairs().find(|x| x.0 == key).map(|x| x.1.into_owned());
+    let is_expected = |token: &str| oauth_token.map_or(true, |x| x == token);
+
+    // `denied` is set to the request token
+    if let Some(denied) = param("denied") {
+        if !is_expected(&denied) {
+            let _ = respond(&mut stream, "400 Bad Request", "The request token doesn't match.");
+            return Ok(None);
+        }
+        let _ = respond(&mut stream, "200 OK", "The authorization was denied. You can close this window.");
+        return Err(io::Error::new(io::ErrorKind::PermissionDenied, "the user denied the authorization"));
+    }
+
+    match (param("oauth_token"), param("oauth_verifier")) {
+        (Some(ref token), Some(_)) if !is_expected(token) => {
+            let _ = respond(&mut stream, "400 Bad Request", "The request token doesn't match.");
+            Ok(None)
+        }
+        (Some(oauth_token), Some(oauth_verifier)) => {
+            let _ = respond(&mut stream, "200 OK", "The authorization is complete. You can close this window.");
+            Ok(Some(OAuthCallback { oauth_token: oauth_token, oauth_verifier: oauth_verifier }))
+        }
+        _ => {
+            let _ = respond(&mut stream, "400 Bad Request", "oauth_token and oauth_verifier are required.");
+            Ok(None)
+        }
+    }
+}
+
+fn respond(stream: &mut TcpStream, status: &str, body: &str) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status, body.len(), body
+    )
+}
+
+/// 3-legged OAuth without asking the user to copy the PIN.
+///
+/// ```no_run
+/// use tweetust::*;
+///
+/// let handler = DefaultHttpHandler::with_https_connector().unwrap();
+/// let auth = oauth::LoopbackAuthorization::start("API Key", "API Secret", &handler).unwrap();
+/// println!("Open {}", auth.authorize_url());
+/// let access_token = auth.wait(&handler).unwrap().object;
+/// ```
+#[derive(Debug)]
+pub struct LoopbackAuthorization {
+    server: CallbackServer,
+    request_token: RequestTokenResponse,
+}
+
+impl LoopbackAuthorization {
+    /// Starts the callback server and gets the request token with its URL as `oauth_callback`.
+    pub fn start<H: HttpHandler>(consumer_key: &str, consumer_secret: &str, handler: &H) -> Result<LoopbackAuthorization, TwitterError> {
+        LoopbackAuthorization::with_server(try!(CallbackServer::bind()), consumer_key, consumer_secret, handler)
+    }
+
+    pub fn with_server<H: HttpHandler>(server: CallbackServer, consumer_key: &str, consumer_secret: &str, handler: &H) -> Result<LoopbackAuthorization, TwitterError> {
+        let callback_url = try!(server.callback_url());
+        let request_token = try!(request_token(consumer_key, consumer_secret, callback_url).execute(handler)).object;
+        Ok(LoopbackAuthorization {
+            server: server,
+            request_token: request_token,
+        })
+    }
+
+    pub fn request_token(&self) -> &RequestTokenResponse {
+        &self.request_token
+    }
+
+    /// Returns the URL to open in the browser.
+    pub fn authorize_url(&self) -> AuthorizeUrlBuilder {
+        self.request_token.authorize_url()
+    }
+
+    /// Waits for the redirect for the request token and gets the access token.
+    /// The redirects for other request tokens are ignored until the timeout.
+    pub fn wait<H: HttpHandler>(self, handler: &H) -> TwitterResult<AccessTokenResponse> {
+        let callback = try!(self.server.wait_for(&self.request_token.oauth_token));
+        self.request_token.access_token(&callback.oauth_verifier[..]).execute(handler)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::thread;
+    use std::time::Duration;
+    use super::*;
+
+    fn get(url: &str, path: &str) -> String {
+        let addr = &url["http://".len()..url.len() - CALLBACK_PATH.len()];
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: {}\r\n\r\n", path, addr).unwrap();
+        let mut res = String::new();
+        stream.read_to_string(&mut res).unwrap();
+        res
+    }
+
+    #[test]
+    fn callback_test() {
+        let server = CallbackServer::bind().unwrap().timeout(Duration::from_secs(10));
+        let url = server.callback_url().unwrap();
+
+        let t = thread::spawn(move || {
+            assert!(get(&url, "/favicon.ico").starts_with("HTTP/1.1 404"));
+            assert!(get(&url, "/callback?oauth_token=a&oauth_verifier=b%20c").starts_with("HTTP/1.1 200"));
+        });
+
+        let res = server.wait().unwrap();
+        t.join().unwrap();
+        assert_eq!(res, OAuthCallback { oauth_token: "a".to_owned(), oauth_verifier: "b c".to_owned() });
+
+        let server = server.timeout(Duration::from_millis(0));
+        assert_eq!(server.wait().unwrap_err().kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn wait_for_test() {
+        let server = CallbackServer::bind().unwrap().timeout(Duration::from_secs(10));
+        let url = server.callback_url().unwrap();
+
+        let t = thread::spawn(move || {
+            // The redirects for another request token are ignored
+            assert!(get(&url, "/callback?oauth_token=old&oauth_verifier=v").starts_with("HTTP/1.1 400"));
+            assert!(get(&url, "/callback?denied=old").starts_with("HTTP/1.1 400"));
+            assert!(get(&url, "/callback?oauth_token=a&oauth_verifier=b").starts_with("HTTP/1.1 200"));
+        });
+
+        let res = server.wait_for("a").unwrap();
+        t.join().unwrap();
+        assert_eq!(res, OAuthCallback { oauth_token: "a".to_owned(), oauth_verifier: "b".to_owned() });
+
+        let url = server.callback_url().unwrap();
+        let t = thread::spawn(move || get(&url, "/callback?denied=a"));
+        assert_eq!(server.wait_for("a").unwrap_err().kind(), io::ErrorKind::PermissionDenied);
+        t.join().unwrap();
+    }
+}
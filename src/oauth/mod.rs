@@ -0,0 +1,500 @@
+//! The functions to get your access token for
+//! [3-legged OAuth](https://dev.twitter.com/oauth/3-legged) or
+//! [PIN-based OAuth](https://dev.twitter.com/oauth/pin-based),
+//! and to get or invalidate your access token with xAuth.
+
+use std::borrow::Cow;
+use std::fmt;
+use hyper::Post;
+use url::{form_urlencoded, Url};
+use ::{OAuthAuthenticator, TwitterError, TwitterResult};
+use conn::*;
+use conn::oauth_authenticator::{OAuthAuthorizationScheme, OAuthHeaderParams, OAuthSignatureMethod};
+use models::{RawResponse, TwitterResponse};
+use oauth2::InvalidateTokenResponse;
+
+pub use self::loopback::{CallbackServer, LoopbackAuthorization, OAuthCallback};
+
+mod loopback;
+
+#[derive(Clone, Debug)]
+pub struct RequestTokenResponse {
+    consumer_key: String,
+    consumer_secret: String,
+    signature_method: OAuthSignatureMethod,
+    pub oauth_token: String,
+    pub oauth_token_secret: String,
+    pub oauth_callback_confirmed: bool
+}
+
+impl RequestTokenResponse {
+    pub fn access_token<'a, V: Into<Cow<'a, str>>>(&'a self, oauth_verifier: V) -> AccessTokenRequestBuilder<'a> {
+        let mut builder = access_token(
+            &self.consumer_key[..],
+            &self.consumer_secret[..],
+            &self.oauth_token[..],
+            &self.oauth_token_secret[..],
+            oauth_verifier
+        );
+        builder.signature_method = self.signature_method.clone();
+        builder
+    }
+
+    /// Creates the URL of `oauth/authorize`, which always asks the user to authorize the app.
+    pub fn authorize_url(&self) -> AuthorizeUrlBuilder {
+        AuthorizeUrlBuilder::new(&self.oauth_token, false)
+    }
+
+    /// Creates the URL of `oauth/authenticate` for "Sign in with Twitter".
+    /// The user who has already authorized the app is redirected immediately.
+    pub fn authenticate_url(&self) -> AuthorizeUrlBuilder {
+        AuthorizeUrlBuilder::new(&self.oauth_token, true)
+    }
+}
+
+/// The URL to redirect the user to after getting the request token.
+#[derive(Clone, Debug)]
+pub struct AuthorizeUrlBuilder<'a> {
+    oauth_token: &'a str,
+    authenticate: bool,
+    force_login: bool,
+    screen_name: Option<Cow<'a, str>>
+}
+
+impl<'a> AuthorizeUrlBuilder<'a> {
+    fn new(oauth_token: &'a str, authenticate: bool) -> AuthorizeUrlBuilder<'a> {
+        AuthorizeUrlBuilder {
+            oauth_token: oauth_token,
+            authenticate: authenticate,
+            force_login: false,
+            screen_name: None
+        }
+    }
+
+    /// Uses `oauth/authenticate` instead of `oauth/authorize` if true.
+    pub fn authenticate(&mut self, val: bool) -> &mut Self {
+        self.authenticate = val;
+        self
+    }
+
+    /// Forces the user to enter the credentials even if already signed in.
+    pub fn force_login(&mut self, val: bool) -> &mut Self {
+        self.force_login = val;
+        self
+    }
+
+    /// Prefills the username input box.
+    pub fn screen_name<T: Into<Cow<'a, str>>>(&mut self, val: T) -> &mut Self {
+        self.screen_name = Some(val.into());
+        self
+    }
+
+    pub fn build(&self) -> Url {
+        let mut url = Url::parse(
+            if self.authenticate { "https://api.twitter.com/oauth/authenticate" }
+            else { "https://api.twitter.com/oauth/authorize" }
+        ).unwrap();
+
+        {
+            let mut query = url.query_pairs_mut();
+            query.append_pair("oauth_token", self.oauth_token);
+            if self.force_login {
+                query.append_pair("force_login", "true");
+            }
+            if let Some(ref x) = self.screen_name {
+                query.append_pair("screen_name", x.as_ref());
+            }
+        }
+
+        url
+    }
+}
+
+impl<'a> fmt::Display for AuthorizeUrlBuilder<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.build().as_str())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RequestTokenRequestBuilder<'a> {
+    consumer_key: Cow<'a, str>,
+    consumer_secret: Cow<'a, str>,
+    oauth_callback: Cow<'a, str>,
+    x_auth_access_type: Option<Cow<'a, str>>,
+    signature_method: OAuthSignatureMethod
+}
+
+impl<'a> RequestTokenRequestBuilder<'a> {
+    pub fn x_auth_access_type<T: Into<Cow<'a, str>>>(&mut self, val: T) -> &mut Self {
+        self.x_auth_access_type = Some(val.into());
+        self
+    }
+
+    /// Sets the signature method. The default is HMAC-SHA1.
+    pub fn signature_method(&mut self, val: OAuthSignatureMethod) -> &mut Self {
+        self.signature_method = val;
+        self
+    }
+
+    pub fn execute<H: HttpHandler>(&self, handler: &H) -> TwitterResult<RequestTokenResponse> {
+        struct RequestTokenAuthenticator<'a> { b: &'a RequestTokenRequestBuilder<'a> }
+        impl<'a> Authenticator for RequestTokenAuthenticator<'a> {
+            type Scheme = OAuthAuthorizationScheme;
+
+            fn create_authorization_header(&self, request: &Request) -> Option<Self::Scheme> {
+                self.try_create_authorization_header(request).unwrap_or(None)
+            }
+
+            fn try_create_authorization_header(&self, request: &Request) -> Result<Option<Self::Scheme>, TwitterError> {
+                let mut params = OAuthHeaderParams::new(self.b.consumer_key.as_ref(), self.b.consumer_secret.as_ref(), &self.b.signature_method);
+                params.callback = Some(self.b.oauth_callback.as_ref());
+                params.create_header(request).map(Some)
+            }
+        }
+
+        let mut params = Vec::new();
+        if let Some(ref x) = self.x_auth_access_type {
+            params.push((Cow::Borrowed("x_auth_access_type"), Cow::Borrowed(x.as_ref())))
+        }
+
+        let req = try!(Request::new(Post, "https://api.twitter.com/oauth/request_token", RequestContent::WwwForm(Cow::Owned(params))));
+        let res = try!(handler.send_request(req, &RequestTokenAuthenticator{ b: self }));
+
+        let (oauth_token, oauth_token_secret, oauth_callback_confirmed) = {
+            let v = form_urlencoded::parse(res.raw_response.as_bytes()).collect::<Vec<_>>();
+            (
+                v.iter().find(|x| x.0 == "oauth_token").map(|&(_, ref val)| val.as_ref().to_owned()),
+                v.iter().find(|x| x.0 == "oauth_token_secret").map(|&(_, ref val)| val.as_ref().to_owned()),
+                v.iter().find(|x| x.0 == "oauth_callback_confirmed").and_then(|&(_, ref val)| val.parse().ok())
+            )
+        };
+
+        match (oauth_token, oauth_token_secret) {
+            (Some(oauth_token), Some(oauth_token_secret)) =>
+                Ok(TwitterResponse {
+                    object: RequestTokenResponse {
+                        consumer_key: self.consumer_key.as_ref().to_owned(),
+                        consumer_secret: self.consumer_secret.as_ref().to_owned(),
+                        signature_method: self.signature_method.clone(),
+                        oauth_token: oauth_token,
+                        oauth_token_secret: oauth_token_secret,
+                        oauth_callback_confirmed: oauth_callback_confirmed.unwrap_or(false)
+                    },
+                    raw_response: res.raw_response,
+                    rate_limit: res.rate_limit
+                }),
+            _ => Err(TwitterError::ParseResponse(None, res))
+        }
+    }
+}
+
+pub fn request_token<'a, CK, CS, CB>(consumer_key: CK, consumer_secret: CS, oauth_callback: CB) -> RequestTokenRequestBuilder<'a>
+    where CK: Into<Cow<'a, str>>, CS: Into<Cow<'a, str>>, CB: Into<Cow<'a, str>>
+{
+    RequestTokenRequestBuilder {
+        consumer_key: consumer_key.into(),
+        consumer_secret: consumer_secret.into(),
+        oauth_callback: oauth_callback.into(),
+        x_auth_access_type: None,
+        signature_method: OAuthSignatureMethod::HmacSha1
+    }
+}
+
+/// `signature_method` is serialized by its name as `OAuthAuthenticator` does,
+/// so a response using RSA-SHA1 cannot be deserialized.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AccessTokenResponse {
+    consumer_key: String,
+    consumer_secret: String,
+    #[serde(default)]
+    signature_method: OAuthSignatureMethod,
+    pub oauth_token: String,
+    pub oauth_token_secret: String,
+    pub user_id: i64,
+    pub screen_name: String
+}
+
+impl AccessTokenResponse {
+    pub fn consumer_key(&self) -> &str {
+        &self.consumer_key
+    }
+
+    pub fn consumer_secret(&self) -> &str {
+        &self.consumer_secret
+    }
+
+    pub fn to_authenticator<'a>(self) -> OAuthAuthenticator<'a> {
+        OAuthAuthenticator::new(
+            self.consumer_key,
+            self.consumer_secret,
+            self.oauth_token,
+            self.oauth_token_secret
+        ).signature_method(self.signature_method)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct AccessTokenRequestBuilder<'a> {
+    consumer_key: Cow<'a, str>,
+    consumer_secret: Cow<'a, str>,
+    oauth_token: Cow<'a, str>,
+    oauth_token_secret: Cow<'a, str>,
+    oauth_verifier: Cow<'a, str>,
+    signature_method: OAuthSignatureMethod
+}
+
+impl<'a> AccessTokenRequestBuilder<'a> {
+    /// Sets the signature method. The default is HMAC-SHA1.
+    pub fn signature_method(&mut self, val: OAuthSignatureMethod) -> &mut Self {
+        self.signature_method = val;
+        self
+    }
+
+    pub fn execute<H: HttpHandler>(&self, handler: &H) -> TwitterResult<AccessTokenResponse> {
+        struct AccessTokenAuthenticator<'a> { b: &'a AccessTokenRequestBuilder<'a> }
+        impl<'a> Authenticator for AccessTokenAuthenticator<'a> {
+            type Scheme = OAuthAuthorizationScheme;
+
+            fn create_authorization_header(&self, request: &Request) -> Option<Self::Scheme> {
+                self.try_create_authorization_header(request).unwrap_or(None)
+            }
+
+            fn try_create_authorization_header(&self, request: &Request) -> Result<Option<Self::Scheme>, TwitterError> {
+                let mut params = OAuthHeaderParams::new(self.b.consumer_key.as_ref(), self.b.consumer_secret.as_ref(), &self.b.signature_method);
+                params.token = Some((self.b.oauth_token.as_ref(), self.b.oauth_token_secret.as_ref()));
+                params.verifier = Some(self.b.oauth_verifier.as_ref());
+                params.create_header(request).map(Some)
+            }
+        }
+
+        let req = try!(Request::new(Post, "https://api.twitter.com/oauth/access_token", RequestContent::None));
+        let res = try!(handler.send_request(req, &AccessTokenAuthenticator{ b: self }));
+
+        parse_access_token_response(res, &self.consumer_key, &self.consumer_secret, &self.signature_method)
+    }
+}
+
+fn parse_access_token_response(res: RawResponse, consumer_key: &str, consumer_secret: &str,
+    signature_method: &OAuthSignatureMethod) -> TwitterResult<AccessTokenResponse>
+{
+    let t = {
+        let v = form_urlencoded::parse(res.raw_response.as_bytes()).collect::<Vec<_>>();
+        (
+            v.iter().find(|x| x.0 == "oauth_token").map(|&(_, ref val)| val.as_ref().to_owned()),
+            v.iter().find(|x| x.0 == "oauth_token_secret").map(|&(_, ref val)| val.as_ref().to_owned()),
+            v.iter().find(|x| x.0 == "user_id").and_then(|&(_, ref val)| val.parse().ok()),
+            v.iter().find(|x| x.0 == "screen_name").map(|&(_, ref val)| val.as_ref().to_owned())
+        )
+    };
+
+    match t {
+        (Some(oauth_token), Some(oauth_token_secret), Some(user_id), Some(screen_name)) =>
+            Ok(TwitterResponse {
+                object: AccessTokenResponse {
+                    consumer_key: consumer_key.to_owned(),
+                    consumer_secret: consumer_secret.to_owned(),
+                    signature_method: signature_method.clone(),
+                    oauth_token: oauth_token,
+                    oauth_token_secret: oauth_token_secret,
+                    user_id: user_id,
+                    screen_name: screen_name
+                },
+                raw_response: res.raw_response,
+                rate_limit: res.rate_limit
+            }),
+        _ => Err(TwitterError::ParseResponse(None, res))
+    }
+}
+
+pub fn access_token<'a, CK, CS, T, TS, V>(consumer_key: CK, consumer_secret: CS,
+    oauth_token: T, oauth_token_secret: TS, oauth_verifier: V)
+    -> AccessTokenRequestBuilder<'a>
+    where CK: Into<Cow<'a, str>>, CS: Into<Cow<'a, str>>, T: Into<Cow<'a, str>>, TS: Into<Cow<'a, str>>, V: Into<Cow<'a, str>>
+{
+    AccessTokenRequestBuilder {
+        consumer_key: consumer_key.into(),
+        consumer_secret: consumer_secret.into(),
+        oauth_token: oauth_token.into(),
+        oauth_token_secret: oauth_token_secret.into(),
+        oauth_verifier: oauth_verifier.into(),
+        signature_method: OAuthSignatureMethod::HmacSha1
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct XAuthAccessTokenRequestBuilder<'a> {
+    consumer_key: Cow<'a, str>,
+    consumer_secret: Cow<'a, str>,
+    x_auth_username: Cow<'a, str>,
+    x_auth_password: Cow<'a, str>,
+    signature_method: OAuthSignatureMethod
+}
+
+impl<'a> XAuthAccessTokenRequestBuilder<'a> {
+    /// Sets the signature method. The default is HMAC-SHA1.
+    pub fn signature_method(&mut self, val: OAuthSignatureMethod) -> &mut Self {
+        self.signature_method = val;
+        self
+    }
+
+    pub fn execute<H: HttpHandler>(&self, handler: &H) -> TwitterResult<AccessTokenResponse> {
+        struct XAuthAuthenticator<'a> { b: &'a XAuthAccessTokenRequestBuilder<'a> }
+        impl<'a> Authenticator for XAuthAuthenticator<'a> {
+            type Scheme = OAuthAuthorizationScheme;
+
+            fn create_authorization_header(&self, request: &Request) -> Option<Self::Scheme> {
+                self.try_create_authorization_header(request).unwrap_or(None)
+            }
+
+            fn try_create_authorization_header(&self, request: &Request) -> Result<Option<Self::Scheme>, TwitterError> {
+                OAuthHeaderParams::new(self.b.consumer_key.as_ref(), self.b.consumer_secret.as_ref(), &self.b.signature_method)
+                    .create_header(request).map(Some)
+            }
+        }
+
+        let params = [
+            (Cow::Borrowed("x_auth_username"), Cow::Borrowed(self.x_auth_username.as_ref())),
+            (Cow::Borrowed("x_auth_password"), Cow::Borrowed(self.x_auth_password.as_ref())),
+            (Cow::Borrowed("x_auth_mode"), Cow::Borrowed("client_auth"))
+        ];
+
+        let req = try!(Request::new(Post, "https://api.twitter.com/oauth/access_token", RequestContent::WwwForm(Cow::Borrowed(&params))));
+        let res = try!(handler.send_request(req, &XAuthAuthenticator{ b: self }));
+        parse_access_token_response(res, &self.consumer_key, &self.consumer_secret, &self.signature_method)
+    }
+}
+
+/// Gets the access token with the username and password.
+/// [xAuth](https://developer.twitter.com/en/docs/basics/authentication/overview/xauth) is available only for the approved apps.
+pub fn xauth_access_token<'a, CK, CS, U, P>(consumer_key: CK, consumer_secret: CS, x_auth_username: U, x_auth_password: P)
+    -> XAuthAccessTokenRequestBuilder<'a>
+    where CK: Into<Cow<'a, str>>, CS: Into<Cow<'a, str>>, U: Into<Cow<'a, str>>, P: Into<Cow<'a, str>>
+{
+    XAuthAccessTokenRequestBuilder {
+        consumer_key: consumer_key.into(),
+        consumer_secret: consumer_secret.into(),
+        x_auth_username: x_auth_username.into(),
+        x_auth_password: x_auth_password.into(),
+        signature_method: OAuthSignatureMethod::HmacSha1
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct InvalidateTokenRequestBuilder<'a> {
+    authenticator: OAuthAuthenticator<'a>
+}
+
+impl<'a> InvalidateTokenRequestBuilder<'a> {
+    /// Sets the signature method. The default is HMAC-SHA1.
+    pub fn signature_method(&mut self, val: OAuthSignatureMethod) -> &mut Self {
+        self.authenticator.signature_method = val;
+        self
+    }
+
+    pub fn execute<H: HttpHandler>(&self, handler: &H) -> TwitterResult<InvalidateTokenResponse> {
+        let req = try!(Request::new(Post, "https://api.twitter.com/1.1/oauth/invalidate_token", RequestContent::None));
+        try!(handler.send_request(req, &self.authenticator)).parse_to_object()
+    }
+}
+
+/// Revokes the access token so that it can no longer be used.
+pub fn invalidate_token<'a, CK, CS, T, TS>(consumer_key: CK, consumer_secret: CS, access_token: T, access_token_secret: TS)
+    -> InvalidateTokenRequestBuilder<'a>
+    where CK: Into<Cow<'a, str>>, CS: Into<Cow<'a, str>>, T: Into<Cow<'a, str>>, TS: Into<Cow<'a, str>>
+{
+    InvalidateTokenRequestBuilder {
+        authenticator: OAuthAuthenticator::new(consumer_key, consumer_secret, access_token, access_token_secret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::Post;
+    use serde_json;
+    use conn::mock_http_handler::*;
+    use super::*;
+
+    #[test]
+    fn authorize_url_test() {
+        let res = RequestTokenResponse {
+            consumer_key: "ck".to_owned(),
+            consumer_secret: "cs".to_owned(),
+            signature_method: OAuthSignatureMethod::HmacSha1,
+            oauth_token: "a+b/c".to_owned(),
+            oauth_token_secret: "ts".to_owned(),
+            oauth_callback_confirmed: true
+        };
+
+        assert_eq!(res.authorize_url().to_string(), "https://api.twitter.com/oauth/authorize?oauth_token=a%2Bb%2Fc");
+        assert_eq!(
+            res.authenticate_url().force_login(true).screen_name("a&b").build().as_str(),
+            "https://api.twitter.com/oauth/authenticate?oauth_token=a%2Bb%2Fc&force_login=true&screen_name=a%26b"
+        );
+    }
+
+    #[test]
+    fn access_token_serde_test() {
+        let res = AccessTokenResponse {
+            consumer_key: "ck".to_owned(),
+            consumer_secret: "cs".to_owned(),
+            signature_method: OAuthSignatureMethod::Plaintext,
+            oauth_token: "t".to_owned(),
+            oauth_token_secret: "ts".to_owned(),
+            user_id: 1,
+            screen_name: "a".to_owned()
+        };
+
+        let json = serde_json::to_string(&res).unwrap();
+        assert!(json.contains(r#""signature_method":"PLAINTEXT""#));
+        let res: AccessTokenResponse = serde_json::from_str(&json).unwrap();
+        assert_eq!(res.to_authenticator().signature_method.name(), "PLAINTEXT");
+
+        let json = json.replace("PLAINTEXT", "RSA-SHA1");
+        assert!(serde_json::from_str::<AccessTokenResponse>(&json).is_err());
+        // The files saved before signature_method was serialized
+        let json = r#"{"consumer_key":"ck","consumer_secret":"cs","oauth_token":"t","oauth_token_secret":"ts","user_id":1,"screen_name":"a"}"#;
+        let res: AccessTokenResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(res.to_authenticator().signature_method.name(), "HMAC-SHA1");
+    }
+
+    #[test]
+    fn xauth_test() {
+        let mock = MockHttpHandler::new();
+        mock.add(
+            MockRule::new(Post, "https://api.twitter.com/oauth/access_token", MockResponse::ok("oauth_token=t&oauth_token_secret=ts&user_id=1&screen_name=a"))
+                .param("x_auth_mode", "client_auth")
+        );
+
+        let res = xauth_access_token("ck", "cs", "user", "pass")
+            .signature_method(OAuthSignatureMethod::Plaintext)
+            .execute(&mock).unwrap();
+        assert_eq!(res.object.oauth_token, "t");
+        assert_eq!(res.object.user_id, 1);
+
+        let req = mock.last_request().unwrap();
+        assert_eq!(req.param("x_auth_username"), Some("user"));
+        assert_eq!(req.param("x_auth_password"), Some("pass"));
+        let authorization = req.authorization.unwrap();
+        assert!(authorization.contains(r#"oauth_signature_method="PLAINTEXT""#));
+        assert!(authorization.contains(r#"oauth_signature="cs%26""#));
+        assert!(!authorization.contains("oauth_token="));
+    }
+
+    #[test]
+    fn invalidate_token_test() {
+        let mock = MockHttpHandler::new();
+        mock.respond(Post, "https://api.twitter.com/1.1/oauth/invalidate_token", MockResponse::ok(r#"{"access_token":"t"}"#));
+
+        let res = invalidate_token("ck", "cs", "t", "ts")
+            .signature_method(OAuthSignatureMethod::Plaintext)
+            .execute(&mock).unwrap();
+        assert_eq!(res.object.access_token, "t");
+
+        let req = mock.last_request().unwrap();
+        assert!(req.params.is_empty());
+        let authorization = req.authorization.unwrap();
+        assert!(authorization.contains(r#"oauth_token="t""#));
+        assert!(authorization.contains(r#"oauth_signature="cs%26ts""#));
+    }
+}
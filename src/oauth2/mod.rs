@@ -0,0 +1,364 @@
+//! The functions to get and invalidate your access token for
+//! [Application-only authentication](https://dev.twitter.com/oauth/application-only),
+//! and to get the user context access token with the authorization code flow and PKCE.
+
+use std::borrow::Cow;
+use hyper::Post;
+use hyper::header::Basic;
+use url::{percent_encoding, Url};
+use ::{ApplicationOnlyAuthenticator, TwitterResult, UserBearerAuthenticator};
+use conn::*;
+
+#[cfg(feature = "futures")]
+use futures::{future, Future};
+#[cfg(feature = "futures")]
+use ::FutureTwitterResult;
+
+pub use self::pkce::{generate_state, PkceChallenge};
+
+mod pkce;
+
+pub const AUTHORIZE_URL: &'static str = "https://twitter.com/i/oauth2/authorize";
+pub const TOKEN_URL: &'static str = "https://api.twitter.com/2/oauth2/token";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TokenResponse {
+    pub token_type: String,
+    pub access_token: String
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct InvalidateTokenResponse {
+    pub access_token: String
+}
+
+impl TokenResponse {
+    pub fn to_authenticator<'a>(self) -> ApplicationOnlyAuthenticator<'a> {
+        ApplicationOnlyAuthenticator::new(self.access_token)
+    }
+}
+
+struct CkCsBasicAuthenticator<'a> {
+    consumer_key: &'a str,
+    consumer_secret: &'a str,
+}
+
+impl<'a> CkCsBasicAuthenticator<'a> {
+    fn new(consumer_key: &'a str, consumer_secret: &'a str) -> CkCsBasicAuthenticator<'a> {
+        CkCsBasicAuthenticator {
+            consumer_key: consumer_key,
+            consumer_secret: consumer_secret,
+        }
+    }
+}
+
+impl<'a> Authenticator for CkCsBasicAuthenticator<'a> {
+    type Scheme = Basic;
+
+    fn create_authorization_header(&self, _: &Request) -> Option<Self::Scheme> {
+        Some(Basic {
+            username: self.consumer_key.to_owned(),
+            password: Some(self.consumer_secret.to_owned())
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TokenRequestBuilder<'a> {
+    consumer_key: Cow<'a, str>,
+    consumer_secret: Cow<'a, str>,
+    grant_type: Cow<'a, str>
+}
+
+impl<'a> TokenRequestBuilder<'a> {
+    pub fn grant_type<T: Into<Cow<'a, str>>>(&mut self, val: T) -> &mut Self {
+        self.grant_type = val.into();
+        self
+    }
+
+    pub fn execute<H: HttpHandler>(&self, handler: &H) -> TwitterResult<TokenResponse> {
+        let params = [(Cow::Borrowed("grant_type"), Cow::Borrowed(self.grant_type.as_ref()))];
+
+        let req = try!(Request::new(
+            Post,
+            "https://api.twitter.com/oauth2/token",
+            RequestContent::WwwForm(Cow::Borrowed(&params))
+        ));
+
+        try!(handler.send_request(
+            req,
+            &CkCsBasicAuthenticator::new(&self.consumer_key, &self.consumer_secret)
+        )).parse_to_object()
+    }
+}
+
+pub fn token<'a, CK, CS>(consumer_key: CK, consumer_secret: CS) -> TokenRequestBuilder<'a>
+    where CK: Into<Cow<'a, str>>, CS: Into<Cow<'a, str>>
+{
+    TokenRequestBuilder {
+        consumer_key: consumer_key.into(),
+        consumer_secret: consumer_secret.into(),
+        grant_type: Cow::Borrowed("client_credentials")
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct InvalidateTokenRequestBuilder<'a> {
+    consumer_key: Cow<'a, str>,
+    consumer_secret: Cow<'a, str>,
+    access_token: Cow<'a, str>
+}
+
+impl<'a> InvalidateTokenRequestBuilder<'a> {
+    pub fn execute<H: HttpHandler>(&self, handler: &H) -> TwitterResult<InvalidateTokenResponse> {
+        let access_token = percent_encoding::percent_decode(self.access_token.as_ref().as_bytes());
+        let params = [(Cow::Borrowed("access_token"), access_token.decode_utf8_lossy())];
+
+        let req = try!(Request::new(
+            Post,
+            "https://api.twitter.com/oauth2/invalidate_token",
+            RequestContent::WwwForm(Cow::Borrowed(&params))
+        ));
+
+        try!(handler.send_request(
+            req,
+            &CkCsBasicAuthenticator::new(&self.consumer_key, &self.consumer_secret)
+        )).parse_to_object()
+    }
+}
+
+pub fn invalidate_token<'a, CK, CS, T>(consumer_key: CK, consumer_secret: CS, access_token: T)
+    -> InvalidateTokenRequestBuilder<'a>
+    where CK: Into<Cow<'a, str>>, CS: Into<Cow<'a, str>>, T: Into<Cow<'a, str>>
+{
+    InvalidateTokenRequestBuilder {
+        consumer_key: consumer_key.into(),
+        consumer_secret: consumer_secret.into(),
+        access_token: access_token.into()
+    }
+}
+
+/// The response of the token endpoint for the user context.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UserTokenResponse {
+    pub token_type: String,
+    pub access_token: String,
+    /// The lifetime of the access token in seconds.
+    #[serde(default)]
+    pub expires_in: Option<i64>,
+    /// Returned when `offline.access` scope is granted.
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+    #[serde(default)]
+    pub scope: Option<String>,
+}
+
+impl UserTokenResponse {
+    pub fn to_authenticator<T: Into<String>>(self, client_id: T) -> UserBearerAuthenticator {
+        UserBearerAuthenticator::new(client_id, self)
+    }
+}
+
+/// Sends the client credentials with Basic authentication if the client is confidential.
+struct ClientAuthenticator<'a>(Option<CkCsBasicAuthenticator<'a>>);
+
+impl<'a> ClientAuthenticator<'a> {
+    fn new(client_id: &'a str, client_secret: Option<&'a str>) -> ClientAuthenticator<'a> {
+        ClientAuthenticator(client_secret.map(|x| CkCsBasicAuthenticator::new(client_id, x)))
+    }
+}
+
+impl<'a> Authenticator for ClientAuthenticator<'a> {
+    type Scheme = Basic;
+
+    fn create_authorization_header(&self, request: &Request) -> Option<Self::Scheme> {
+        self.0.as_ref().and_then(|x| x.create_authorization_header(request))
+    }
+}
+
+fn execute_user_token_request<H: HttpHandler>(handler: &H, token_url: &str, client_id: &str, client_secret: Option<&str>,
+    params: &[(Cow<str>, Cow<str>)]) -> TwitterResult<UserTokenResponse>
+{
+    let req = try!(Request::new(Post, token_url, RequestContent::WwwForm(Cow::Borrowed(params))));
+    try!(handler.send_request(req, &ClientAuthenticator::new(client_id, client_secret))).parse_to_object()
+}
+
+#[cfg(feature = "futures")]
+fn execute_user_token_request_async<'a, H: AsyncHttpHandler>(handler: &'a H, token_url: &str, client_id: &str,
+    client_secret: Option<&str>, params: Vec<(Cow<'a, str>, Cow<'a, str>)>) -> FutureTwitterResult<'a, UserTokenResponse>
+{
+    match Request::new(Post, token_url, RequestContent::WwwForm(Cow::Owned(params))) {
+        Ok(req) => Box::new(
+            handler.send_request_async(req, &ClientAuthenticator::new(client_id, client_secret))
+                .and_then(|x| x.parse_to_object())
+        ),
+        Err(e) => Box::new(future::err(e)),
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct AuthorizeUrlBuilder<'a> {
+    client_id: Cow<'a, str>,
+    redirect_uri: Cow<'a, str>,
+    state: Cow<'a, str>,
+    pkce: &'a PkceChallenge,
+    scopes: Vec<Cow<'a, str>>,
+    authorize_url: Cow<'a, str>,
+}
+
+impl<'a> AuthorizeUrlBuilder<'a> {
+    /// Sets the scopes such as `tweet.read`, `users.read` and `offline.access`.
+    pub fn scopes<I, T>(&mut self, val: I) -> &mut Self
+        where I: IntoIterator<Item = T>, T: Into<Cow<'a, str>>
+    {
+        self.scopes = val.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Overrides the authorization endpoint. The default is `AUTHORIZE_URL`.
+    pub fn authorize_url<T: Into<Cow<'a, str>>>(&mut self, val: T) -> &mut Self {
+        self.authorize_url = val.into();
+        self
+    }
+
+    pub fn build(&self) -> Result<Url, ::url::ParseError> {
+        let mut url = try!(Url::parse(self.authorize_url.as_ref()));
+        {
+            let mut query = url.query_pairs_mut();
+            query.append_pair("response_type", "code")
+                .append_pair("client_id", self.client_id.as_ref())
+                .append_pair("redirect_uri", self.redirect_uri.as_ref());
+            if !self.scopes.is_empty() {
+                let scope = self.scopes.iter().map(|x| x.as_ref()).collect::<Vec<_>>().join(" ");
+                query.append_pair("scope", &scope);
+            }
+            query.append_pair("state", self.state.as_ref())
+                .append_pair("code_challenge", &self.pkce.code_challenge)
+                .append_pair("code_challenge_method", &self.pkce.code_challenge_method);
+        }
+        Ok(url)
+    }
+}
+
+/// Creates the URL to redirect the user to. `state` should be a value generated by `generate_state`.
+pub fn authorize_url<'a, CI, R, S>(client_id: CI, redirect_uri: R, state: S, pkce: &'a PkceChallenge) -> AuthorizeUrlBuilder<'a>
+    where CI: Into<Cow<'a, str>>, R: Into<Cow<'a, str>>, S: Into<Cow<'a, str>>
+{
+    AuthorizeUrlBuilder {
+        client_id: client_id.into(),
+        redirect_uri: redirect_uri.into(),
+        state: state.into(),
+        pkce: pkce,
+        scopes: Vec::new(),
+        authorize_url: Cow::Borrowed(AUTHORIZE_URL),
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct AuthorizationCodeRequestBuilder<'a> {
+    client_id: Cow<'a, str>,
+    code: Cow<'a, str>,
+    redirect_uri: Cow<'a, str>,
+    code_verifier: Cow<'a, str>,
+    client_secret: Option<Cow<'a, str>>,
+    token_url: Cow<'a, str>,
+}
+
+impl<'a> AuthorizationCodeRequestBuilder<'a> {
+    /// Sets the client secret of a confidential client.
+    pub fn client_secret<T: Into<Cow<'a, str>>>(&mut self, val: T) -> &mut Self {
+        self.client_secret = Some(val.into());
+        self
+    }
+
+    /// Overrides the token endpoint. The default is `TOKEN_URL`.
+    pub fn token_url<T: Into<Cow<'a, str>>>(&mut self, val: T) -> &mut Self {
+        self.token_url = val.into();
+        self
+    }
+
+    pub fn execute<H: HttpHandler>(&self, handler: &H) -> TwitterResult<UserTokenResponse> {
+        let params = [
+            (Cow::Borrowed("grant_type"), Cow::Borrowed("authorization_code")),
+            (Cow::Borrowed("code"), Cow::Borrowed(self.code.as_ref())),
+            (Cow::Borrowed("redirect_uri"), Cow::Borrowed(self.redirect_uri.as_ref())),
+            (Cow::Borrowed("code_verifier"), Cow::Borrowed(self.code_verifier.as_ref())),
+            (Cow::Borrowed("client_id"), Cow::Borrowed(self.client_id.as_ref())),
+        ];
+
+        execute_user_token_request(handler, &self.token_url, &self.client_id,
+            self.client_secret.as_ref().map(|x| x.as_ref()), &params)
+    }
+}
+
+/// Exchanges the authorization code for the access token.
+pub fn authorization_code<'a, CI, C, R, V>(client_id: CI, code: C, redirect_uri: R, code_verifier: V) -> AuthorizationCodeRequestBuilder<'a>
+    where CI: Into<Cow<'a, str>>, C: Into<Cow<'a, str>>, R: Into<Cow<'a, str>>, V: Into<Cow<'a, str>>
+{
+    AuthorizationCodeRequestBuilder {
+        client_id: client_id.into(),
+        code: code.into(),
+        redirect_uri: redirect_uri.into(),
+        code_verifier: code_verifier.into(),
+        client_secret: None,
+        token_url: Cow::Borrowed(TOKEN_URL),
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RefreshTokenRequestBuilder<'a> {
+    client_id: Cow<'a, str>,
+    refresh_token: Cow<'a, str>,
+    client_secret: Option<Cow<'a, str>>,
+    token_url: Cow<'a, str>,
+}
+
+impl<'a> RefreshTokenRequestBuilder<'a> {
+    /// Sets the client secret of a confidential client.
+    pub fn client_secret<T: Into<Cow<'a, str>>>(&mut self, val: T) -> &mut Self {
+        self.client_secret = Some(val.into());
+        self
+    }
+
+    /// Overrides the token endpoint. The default is `TOKEN_URL`.
+    pub fn token_url<T: Into<Cow<'a, str>>>(&mut self, val: T) -> &mut Self {
+        self.token_url = val.into();
+        self
+    }
+
+    pub fn execute<H: HttpHandler>(&self, handler: &H) -> TwitterResult<UserTokenResponse> {
+        let params = [
+            (Cow::Borrowed("grant_type"), Cow::Borrowed("refresh_token")),
+            (Cow::Borrowed("refresh_token"), Cow::Borrowed(self.refresh_token.as_ref())),
+            (Cow::Borrowed("client_id"), Cow::Borrowed(self.client_id.as_ref())),
+        ];
+
+        execute_user_token_request(handler, &self.token_url, &self.client_id,
+            self.client_secret.as_ref().map(|x| x.as_ref()), &params)
+    }
+
+    /// The returned future doesn't borrow the builder.
+    #[cfg(feature = "futures")]
+    pub fn execute_async<'h, H: AsyncHttpHandler>(&self, handler: &'h H) -> FutureTwitterResult<'h, UserTokenResponse> {
+        let params = vec![
+            (Cow::Borrowed("grant_type"), Cow::Borrowed("refresh_token")),
+            (Cow::Borrowed("refresh_token"), Cow::Owned(self.refresh_token.as_ref().to_owned())),
+            (Cow::Borrowed("client_id"), Cow::Owned(self.client_id.as_ref().to_owned())),
+        ];
+
+        execute_user_token_request_async(handler, &self.token_url, &self.client_id,
+            self.client_secret.as_ref().map(|x| x.as_ref()), params)
+    }
+}
+
+/// Gets a new access token with the refresh token.
+pub fn refresh_token<'a, CI, T>(client_id: CI, refresh_token: T) -> RefreshTokenRequestBuilder<'a>
+    where CI: Into<Cow<'a, str>>, T: Into<Cow<'a, str>>
+{
+    RefreshTokenRequestBuilder {
+        client_id: client_id.into(),
+        refresh_token: refresh_token.into(),
+        client_secret: None,
+        token_url: Cow::Borrowed(TOKEN_URL),
+    }
+}
@@ -0,0 +1,75 @@
+use base64;
+use rand::Rng;
+use rand::rngs::OsRng;
+use sha2::{Digest, Sha256};
+
+const UNRESERVED: &'static [u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-._~";
+
+/// A code verifier and code challenge of [PKCE](https://tools.ietf.org/html/rfc7636).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PkceChallenge {
+    pub code_verifier: String,
+    pub code_challenge: String,
+    /// `S256` or `plain`.
+    pub code_challenge_method: String,
+}
+
+impl PkceChallenge {
+    /// Generates a random code verifier and its S256 challenge.
+    pub fn new() -> PkceChallenge {
+        PkceChallenge::s256(random_string(64))
+    }
+
+    pub fn s256<T: Into<String>>(code_verifier: T) -> PkceChallenge {
+        let code_verifier = code_verifier.into();
+        let code_challenge = base64::encode_config(&Sha256::digest(code_verifier.as_bytes()), base64::URL_SAFE_NO_PAD);
+        PkceChallenge {
+            code_verifier: code_verifier,
+            code_challenge: code_challenge,
+            code_challenge_method: "S256".to_owned(),
+        }
+    }
+
+    pub fn plain<T: Into<String>>(code_verifier: T) -> PkceChallenge {
+        let code_verifier = code_verifier.into();
+        PkceChallenge {
+            code_challenge: code_verifier.clone(),
+            code_verifier: code_verifier,
+            code_challenge_method: "plain".to_owned(),
+        }
+    }
+}
+
+impl Default for PkceChallenge {
+    fn default() -> PkceChallenge {
+        PkceChallenge::new()
+    }
+}
+
+/// Generates a random value for `state` parameter.
+pub fn generate_state() -> String {
+    random_string(32)
+}
+
+fn random_string(len: usize) -> String {
+    let mut rng = OsRng;
+    (0..len)
+        .map(|_| UNRESERVED[rng.gen_range(0..UNRESERVED.len())] as char)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn s256_test() {
+        // The example in RFC 7636 Appendix B
+        let x = PkceChallenge::s256("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk");
+        assert_eq!(x.code_challenge, "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM");
+
+        let y = PkceChallenge::new();
+        assert_eq!(y.code_verifier.len(), 64);
+        assert_ne!(y.code_verifier, PkceChallenge::new().code_verifier);
+    }
+}
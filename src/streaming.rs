@@ -0,0 +1,588 @@
+//! The client for [Streaming API](https://dev.twitter.com/streaming/overview).
+//!
+//! ```no_run
+//! use tweetust::*;
+//!
+//! let handler = DefaultHttpHandler::with_https_connector().unwrap();
+//! let auth = OAuthAuthenticator::new("API Key", "API Secret", "Access Token", "Access Token Secret");
+//!
+//! let mut builder = streaming::filter();
+//! builder.track(&["rust"]);
+//!
+//! for message in builder.listen(&handler, &auth) {
+//!     if let Ok(streaming::StreamMessage::Tweet(x)) = message {
+//!         println!("{:?}", x.text);
+//!     }
+//! }
+//! ```
+
+use std::borrow::Cow;
+use std::cmp;
+use std::io::{self, BufRead, BufReader, Read};
+use std::mem;
+use std::str;
+use std::thread;
+use std::time::Duration;
+use hyper::{Get, Post};
+use hyper::method::Method;
+use hyper::status::StatusCode;
+use serde_json::{self, Value};
+use ::TwitterError;
+use conn::*;
+use models::*;
+
+/// A message from Streaming API.
+#[derive(Clone, Debug)]
+pub enum StreamMessage {
+    Tweet(Box<Tweet>),
+    Delete(StatusDeletion),
+    ScrubGeo(ScrubGeo),
+    Limit(StreamLimit),
+    StatusWithheld(StatusWithheld),
+    UserWithheld(UserWithheld),
+    Disconnect(StreamDisconnect),
+    Warning(StreamWarning),
+    /// The IDs of the friends sent at the beginning of a user stream.
+    Friends(Vec<i64>),
+    Event(Box<StreamEvent>),
+    DirectMessage(Box<DirectMessage>),
+    /// A message whose type is not known by tweetust.
+    Unknown(Value),
+}
+
+impl StreamMessage {
+    pub fn parse(s: &str) -> serde_json::Result<StreamMessage> {
+        StreamMessage::from_value(try!(serde_json::from_str(s)))
+    }
+
+    pub fn from_value(mut v: Value) -> serde_json::Result<StreamMessage> {
+        const ENVELOPES: [&'static str; 10] = [
+            "delete", "scrub_geo", "limit", "status_withheld", "user_withheld",
+            "disconnect", "warning", "friends", "friends_str", "direct_message",
+        ];
+
+        let kind = match v.as_object() {
+            Some(obj) => {
+                if obj.contains_key("event") { "event" }
+                else if obj.contains_key("text") || obj.contains_key("full_text") { "tweet" }
+                else { ENVELOPES.iter().find(|x| obj.contains_key(**x)).map_or("", |x| *x) }
+            }
+            None => "",
+        };
+
+        // Takes the value at the pointer out of `v`
+        let mut take = |pointer: &str| v.pointer_mut(pointer).map_or(Value::Null, |x| mem::replace(x, Value::Null));
+
+        Ok(match kind {
+            "tweet" => StreamMessage::Tweet(try!(serde_json::from_value(take("")))),
+            "event" => StreamMessage::Event(try!(serde_json::from_value(take("")))),
+            "delete" => StreamMessage::Delete(try!(serde_json::from_value(take("/delete/status")))),
+            "scrub_geo" => StreamMessage::ScrubGeo(try!(serde_json::from_value(take("/scrub_geo")))),
+            "limit" => StreamMessage::Limit(try!(serde_json::from_value(take("/limit")))),
+            "status_withheld" => StreamMessage::StatusWithheld(try!(serde_json::from_value(take("/status_withheld")))),
+            "user_withheld" => StreamMessage::UserWithheld(try!(serde_json::from_value(take("/user_withheld")))),
+            "disconnect" => StreamMessage::Disconnect(try!(serde_json::from_value(take("/disconnect")))),
+            "warning" => StreamMessage::Warning(try!(serde_json::from_value(take("/warning")))),
+            "friends" => StreamMessage::Friends(try!(serde_json::from_value(take("/friends")))),
+            "friends_str" => {
+                let ids: Vec<String> = try!(serde_json::from_value(take("/friends_str")));
+                StreamMessage::Friends(ids.iter().filter_map(|x| x.parse().ok()).collect())
+            }
+            "direct_message" => StreamMessage::DirectMessage(try!(serde_json::from_value(take("/direct_message")))),
+            _ => StreamMessage::Unknown(take("")),
+        })
+    }
+}
+
+/// Splits a stream into messages.
+///
+/// Both newline-delimited messages and `delimited=length` messages are supported.
+/// Blank lines, which are sent as keep-alive, are skipped.
+pub struct MessageReader<R: Read> {
+    reader: BufReader<R>,
+    buf: Vec<u8>,
+}
+
+impl<R: Read> MessageReader<R> {
+    pub fn new(inner: R) -> MessageReader<R> {
+        MessageReader {
+            reader: BufReader::new(inner),
+            buf: Vec::new(),
+        }
+    }
+
+    /// Returns `Ok(None)` if the stream is closed.
+    pub fn read_message(&mut self) -> io::Result<Option<String>> {
+        fn invalid_data<E>(e: E) -> io::Error
+            where E: Into<Box<::std::error::Error + Send + Sync>>
+        {
+            io::Error::new(io::ErrorKind::InvalidData, e)
+        }
+
+        loop {
+            self.buf.clear();
+            if try!(self.reader.read_until(b'\n', &mut self.buf)) == 0 {
+                return Ok(None);
+            }
+
+            let line = {
+                let s = try!(str::from_utf8(&self.buf).map_err(invalid_data));
+                s.trim()
+            };
+
+            if line.is_empty() { continue; }
+
+            if line.bytes().all(|x| x.is_ascii_digit()) {
+                // delimited=length: the line is the byte length of the following message
+                let len: usize = try!(line.parse().map_err(invalid_data));
+                let mut msg = vec![0; len];
+                try!(self.reader.read_exact(&mut msg));
+                return String::from_utf8(msg).map(Some).map_err(invalid_data);
+            }
+
+            return Ok(Some(line.to_owned()));
+        }
+    }
+}
+
+/// A connection to Streaming API. This ends when the connection is closed.
+pub struct TwitterStream<R: Read> {
+    reader: MessageReader<R>,
+    closed: bool,
+}
+
+impl<R: Read> TwitterStream<R> {
+    pub fn new(inner: R) -> TwitterStream<R> {
+        TwitterStream {
+            reader: MessageReader::new(inner),
+            closed: false,
+        }
+    }
+}
+
+impl<R: Read> Iterator for TwitterStream<R> {
+    type Item = Result<StreamMessage, TwitterError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.closed { return None; }
+
+        match self.reader.read_message() {
+            Ok(Some(x)) => Some(match StreamMessage::parse(&x) {
+                Ok(msg) => Ok(msg),
+                Err(e) => Err(TwitterError::ParseResponse(Some(e), RawResponse { status: StatusCode::Ok, raw_response: x, rate_limit: None })),
+            }),
+            Ok(None) => {
+                self.closed = true;
+                None
+            }
+            Err(e) => {
+                self.closed = true;
+                Some(Err(TwitterError::Io(e)))
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct StreamRequestBuilder<'a> {
+    method: Method,
+    url: &'static str,
+    params: Vec<(Cow<'a, str>, Cow<'a, str>)>,
+}
+
+/// Returns public statuses that match the filter predicates.
+/// At least one of `follow`, `track` or `locations` must be specified.
+pub fn filter<'a>() -> StreamRequestBuilder<'a> {
+    StreamRequestBuilder {
+        method: Post,
+        url: "https://stream.twitter.com/1.1/statuses/filter.json",
+        params: Vec::new(),
+    }
+}
+
+/// Returns a small random sample of all public statuses.
+pub fn sample<'a>() -> StreamRequestBuilder<'a> {
+    StreamRequestBuilder {
+        method: Get,
+        url: "https://stream.twitter.com/1.1/statuses/sample.json",
+        params: Vec::new(),
+    }
+}
+
+/// Returns the messages for the authenticating user.
+pub fn user<'a>() -> StreamRequestBuilder<'a> {
+    StreamRequestBuilder {
+        method: Get,
+        url: "https://userstream.twitter.com/1.1/user.json",
+        params: Vec::new(),
+    }
+}
+
+impl<'a> StreamRequestBuilder<'a> {
+    /// Sets any parameter.
+    pub fn param<K, V>(&mut self, key: K, val: V) -> &mut Self
+        where K: Into<Cow<'a, str>>, V: Into<Cow<'a, str>>
+    {
+        let key = key.into();
+        self.params.retain(|x| x.0 != key);
+        self.params.push((key, val.into()));
+        self
+    }
+
+    pub fn track<I, S>(&mut self, keywords: I) -> &mut Self
+        where I: IntoIterator<Item = S>, S: AsRef<str>
+    {
+        let val = join(keywords.into_iter().map(|x| x.as_ref().to_owned()));
+        self.param("track", val)
+    }
+
+    pub fn follow<I: IntoIterator<Item = i64>>(&mut self, user_ids: I) -> &mut Self {
+        let val = join(user_ids.into_iter().map(|x| x.to_string()));
+        self.param("follow", val)
+    }
+
+    /// Sets the bounding boxes. Each box is a pair of the south-west and the north-east corners.
+    pub fn locations<I: IntoIterator<Item = (Coordinates, Coordinates)>>(&mut self, boxes: I) -> &mut Self {
+        let val = join(boxes.into_iter().map(|(sw, ne)| {
+            format!("{},{},{},{}", sw.longitude, sw.latitude, ne.longitude, ne.latitude)
+        }));
+        self.param("locations", val)
+    }
+
+    pub fn language<T: Into<Cow<'a, str>>>(&mut self, val: T) -> &mut Self {
+        self.param("language", val)
+    }
+
+    pub fn filter_level(&mut self, val: FilterLevel) -> &mut Self {
+        let val = val.as_str().to_owned();
+        self.param("filter_level", val)
+    }
+
+    pub fn stall_warnings(&mut self, val: bool) -> &mut Self {
+        self.param("stall_warnings", if val { "true" } else { "false" })
+    }
+
+    /// Requests `delimited=length`.
+    pub fn delimited(&mut self, val: bool) -> &mut Self {
+        if val {
+            self.param("delimited", "length")
+        } else {
+            self.params.retain(|x| x.0 != "delimited");
+            self
+        }
+    }
+
+    /// `with` parameter of user streams: `user` or `followings`.
+    pub fn with<T: Into<Cow<'a, str>>>(&mut self, val: T) -> &mut Self {
+        self.param("with", val)
+    }
+
+    /// `replies` parameter of user streams: `all` to receive all replies.
+    pub fn replies<T: Into<Cow<'a, str>>>(&mut self, val: T) -> &mut Self {
+        self.param("replies", val)
+    }
+
+    pub fn stringify_friend_ids(&mut self, val: bool) -> &mut Self {
+        self.param("stringify_friend_ids", if val { "true" } else { "false" })
+    }
+
+    /// Connects to the stream once.
+    pub fn open<H, A>(&self, handler: &H, auth: &A) -> Result<TwitterStream<H::Stream>, TwitterError>
+        where H: StreamingHttpHandler, A: Authenticator
+    {
+        let params = self.params.iter()
+            .map(|&(ref k, ref v)| (Cow::Borrowed(k.as_ref()), Cow::Borrowed(v.as_ref())))
+            .collect::<Vec<_>>();
+        let content = if params.is_empty() { RequestContent::None } else { RequestContent::WwwForm(Cow::Owned(params)) };
+        let req = try!(Request::new(self.method.clone(), self.url, content));
+        handler.open_stream(req, auth).map(TwitterStream::new)
+    }
+
+    /// Connects to the stream and reconnects with backoff when the connection is lost.
+    /// A stream is also reconnected when reading it times out, e.g. after
+    /// `DEFAULT_STREAM_READ_TIMEOUT_SECS` seconds without any data with `DefaultHttpHandler`.
+    ///
+    /// The iterator ends after returning an error which cannot be recovered by reconnecting,
+    /// such as 401 Unauthorized.
+    pub fn listen<'b, H, A>(&'b self, handler: &'b H, auth: &'b A) -> ReconnectingStream<'b, H, A>
+        where H: StreamingHttpHandler, A: Authenticator
+    {
+        ReconnectingStream {
+            builder: self,
+            handler: handler,
+            auth: auth,
+            stream: None,
+            backoff: Backoff::default(),
+            finished: false,
+        }
+    }
+}
+
+fn join<I: Iterator<Item = String>>(mut iter: I) -> String {
+    let mut dest = iter.next().unwrap_or_else(String::new);
+    for x in iter {
+        dest.push(',');
+        dest.push_str(&x);
+    }
+    dest
+}
+
+/// Counts the consecutive failures to compute the wait before reconnecting.
+///
+/// The strategy follows [the document](https://dev.twitter.com/streaming/overview/connecting).
+#[derive(Clone, Copy, Debug, Default)]
+struct Backoff {
+    network_errors: u32,
+    http_errors: u32,
+    rate_limited: u32,
+}
+
+impl Backoff {
+    /// Back off linearly for TCP/IP level network errors, up to 16 seconds.
+    fn network_error(&mut self) -> Duration {
+        self.network_errors += 1;
+        Duration::from_millis(cmp::min(250 * self.network_errors as u64, 16000))
+    }
+
+    /// Back off exponentially for HTTP errors, starting at 5 seconds up to 320 seconds.
+    fn http_error(&mut self) -> Duration {
+        self.http_errors += 1;
+        Duration::from_secs(5 << cmp::min(self.http_errors - 1, 6))
+    }
+
+    /// Back off exponentially for HTTP 420 errors, starting at 1 minute up to 15 minutes.
+    fn rate_limited(&mut self) -> Duration {
+        self.rate_limited += 1;
+        Duration::from_secs(cmp::min(60 << cmp::min(self.rate_limited - 1, 4), 900))
+    }
+
+    fn reset(&mut self) {
+        *self = Backoff::default();
+    }
+}
+
+/// The iterator returned by `StreamRequestBuilder::listen`.
+pub struct ReconnectingStream<'a, H: 'a + StreamingHttpHandler, A: 'a + Authenticator> {
+    builder: &'a StreamRequestBuilder<'a>,
+    handler: &'a H,
+    auth: &'a A,
+    stream: Option<TwitterStream<H::Stream>>,
+    backoff: Backoff,
+    finished: bool,
+}
+
+impl<'a, H: StreamingHttpHandler, A: Authenticator> Iterator for ReconnectingStream<'a, H, A> {
+    type Item = Result<StreamMessage, TwitterError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.finished {
+            if self.stream.is_none() {
+                match self.builder.open(self.handler, self.auth) {
+                    Ok(x) => {
+                        self.stream = Some(x);
+                        self.backoff.reset();
+                    }
+                    Err(e) => {
+                        let delay = match e {
+                            TwitterError::Io(_) | TwitterError::Http(_) => self.backoff.network_error(),
+                            TwitterError::ErrorResponse(ref x) => match x.status {
+                                StatusCode::Unregistered(420) | StatusCode::TooManyRequests => self.backoff.rate_limited(),
+                                ref s if s.is_server_error() => self.backoff.http_error(),
+                                _ => {
+                                    self.finished = true;
+                                    return Some(Err(e));
+                                }
+                            },
+                            _ => {
+                                self.finished = true;
+                                return Some(Err(e));
+                            }
+                        };
+                        thread::sleep(delay);
+                        continue;
+                    }
+                }
+            }
+
+            match self.stream.as_mut().and_then(|x| x.next()) {
+                Some(Ok(x)) => return Some(Ok(x)),
+                // A broken message does not break the connection
+                Some(Err(e @ TwitterError::ParseResponse(..))) => return Some(Err(e)),
+                _ => {
+                    // The connection has been closed, or has stalled and timed out
+                    self.stream = None;
+                    thread::sleep(self.backoff.network_error());
+                }
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, BufRead, BufReader, Read, Write};
+    use std::net::{SocketAddr, TcpListener, TcpStream};
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use hyper;
+    use hyper::net::{HttpStream, NetworkConnector};
+    use ::ApplicationOnlyAuthenticator;
+    use conn::DefaultHttpHandler;
+    use super::*;
+
+    /// Connects to the fake server whatever the URL is.
+    struct LocalConnector(SocketAddr);
+
+    impl NetworkConnector for LocalConnector {
+        type Stream = HttpStream;
+
+        fn connect(&self, _: &str, _: u16, _: &str) -> hyper::Result<HttpStream> {
+            Ok(HttpStream(try!(TcpStream::connect(self.0))))
+        }
+    }
+
+    /// Starts a server which responds to each connection with `responses` in order.
+    fn fake_server(responses: Vec<String>) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        thread::spawn(move || {
+            for res in responses {
+                let mut stream = listener.accept().unwrap().0;
+
+                // Skip the request
+                {
+                    let mut reader = BufReader::new(&stream);
+                    let mut line = String::new();
+                    let mut content_length = 0;
+                    loop {
+                        line.clear();
+                        reader.read_line(&mut line).unwrap();
+                        if line == "\r\n" || line.is_empty() { break; }
+                        if line.to_lowercase().starts_with("content-length:") {
+                            content_length = line[15..].trim().parse().unwrap();
+                        }
+                    }
+                    let mut body = vec![0; content_length];
+                    reader.read_exact(&mut body).unwrap();
+                }
+
+                let _ = stream.write_all(res.as_bytes());
+            }
+        });
+
+        addr
+    }
+
+    /// Starts a server which sends `response` to the first connection and keeps it open for `hold`,
+    /// then responds to the next connection with `next`.
+    fn stalling_server(response: String, hold: Duration, next: String) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        thread::spawn(move || {
+            let mut stalled = listener.accept().unwrap().0;
+            let _ = stalled.write_all(response.as_bytes());
+            let mut next_stream = listener.accept().unwrap().0;
+            let _ = next_stream.write_all(next.as_bytes());
+            drop(next_stream);
+            thread::sleep(hold);
+        });
+
+        addr
+    }
+
+    fn ok_response(body: &str) -> String {
+        format!("HTTP/1.1 200 OK\r\nConnection: close\r\n\r\n{}", body)
+    }
+
+    const TWEET: &'static str = r#"{"created_at":"Fri May 25 21:32:47 +0000 2018","id":1,"retweet_count":0,"source":"web","text":"hello"}"#;
+
+    #[test]
+    fn message_reader_test() {
+        let delimited = r#"{"limit":{"track":5}}"#;
+        let input = format!("\r\n{}\r\n\r\n{}\r\n{}", TWEET, delimited.len(), delimited);
+        let mut reader = MessageReader::new(io::Cursor::new(input));
+        assert_eq!(reader.read_message().unwrap().as_ref().map(|x| x.as_ref()), Some(TWEET));
+        assert_eq!(reader.read_message().unwrap().as_ref().map(|x| x.as_ref()), Some(delimited));
+        assert!(reader.read_message().unwrap().is_none());
+    }
+
+    #[test]
+    fn parse_test() {
+        assert_matches!(StreamMessage::parse(TWEET), Ok(StreamMessage::Tweet(ref x)) if x.id == 1);
+        assert_matches!(
+            StreamMessage::parse(r#"{"delete":{"status":{"id":2,"id_str":"2","user_id":3,"user_id_str":"3"}}}"#),
+            Ok(StreamMessage::Delete(StatusDeletion { id: 2, user_id: 3, .. }))
+        );
+        assert_matches!(
+            StreamMessage::parse(r#"{"friends_str":["4","5"]}"#),
+            Ok(StreamMessage::Friends(ref x)) if x == &[4, 5]
+        );
+        assert_matches!(StreamMessage::parse(r#"{"foo":1}"#), Ok(StreamMessage::Unknown(_)));
+    }
+
+    #[test]
+    fn reconnect_test() {
+        let addr = fake_server(vec![
+            ok_response(&format!("{}\r\n\r\n{}\r\n", TWEET, r#"{"limit":{"track":5}}"#)),
+            ok_response(r#"{"disconnect":{"code":4,"stream_name":"test","reason":"test"}}"#),
+        ]);
+        let handler = DefaultHttpHandler::new(LocalConnector(addr));
+        let auth = ApplicationOnlyAuthenticator::new("token");
+
+        let builder = sample();
+        let messages = builder.listen(&handler, &auth).take(3).collect::<Vec<_>>();
+
+        assert_matches!(messages[0], Ok(StreamMessage::Tweet(_)));
+        assert_matches!(messages[1], Ok(StreamMessage::Limit(StreamLimit { track: 5, .. })));
+        assert_matches!(messages[2], Ok(StreamMessage::Disconnect(StreamDisconnect { code: 4, .. })));
+    }
+
+    #[test]
+    fn unauthorized_test() {
+        let addr = fake_server(vec![
+            "HTTP/1.1 401 Unauthorized\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_owned(),
+        ]);
+        let handler = DefaultHttpHandler::new(LocalConnector(addr));
+        let auth = ApplicationOnlyAuthenticator::new("token");
+
+        let mut builder = filter();
+        builder.track(&["rust"]);
+        let messages = builder.listen(&handler, &auth).collect::<Vec<_>>();
+
+        assert_eq!(messages.len(), 1);
+        assert_matches!(messages[0], Err(TwitterError::ErrorResponse(_)));
+    }
+
+    #[test]
+    fn stall_test() {
+        let addr = stalling_server(
+            ok_response(&format!("{}\r\n", TWEET)),
+            Duration::from_secs(10),
+            ok_response(r#"{"disconnect":{"code":4,"stream_name":"test","reason":"test"}}"#),
+        );
+        let mut handler = DefaultHttpHandler::new(LocalConnector(addr));
+        handler.set_stream_read_timeout(Some(Duration::from_millis(200)));
+        let auth = ApplicationOnlyAuthenticator::new("token");
+
+        // The first connection is kept open without any data, and the stream is reconnected after the timeout
+        let start = Instant::now();
+        let builder = sample();
+        let messages = builder.listen(&handler, &auth).take(2).collect::<Vec<_>>();
+        assert_matches!(messages[0], Ok(StreamMessage::Tweet(_)));
+        assert_matches!(messages[1], Ok(StreamMessage::Disconnect(_)));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn backoff_test() {
+        let mut backoff = Backoff::default();
+        let secs: Vec<u64> = (0..7).map(|_| backoff.rate_limited().as_secs()).collect();
+        assert_eq!(secs, vec![60, 120, 240, 480, 900, 900, 900]);
+        backoff.reset();
+        assert_eq!(backoff.rate_limited().as_secs(), 60);
+    }
+}
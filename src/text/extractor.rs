@@ -0,0 +1,495 @@
+//! Extracts the entities from plain text in the same way as the server.
+//!
+//! ```
+//! use tweetust::text::extractor;
+//!
+//! let entities = extractor::extract_entities("@twitter 日本語 #ハッシュタグ $TWTR https://example.com/");
+//! assert_eq!(entities.user_mentions[0].screen_name, "twitter");
+//! assert_eq!(entities.hashtags[0].text, "ハッシュタグ");
+//! assert_eq!((entities.hashtags[0].indices.start, entities.hashtags[0].indices.end), (13, 20));
+//! assert_eq!(entities.symbols[0].text, "TWTR");
+//! assert_eq!(entities.urls[0].url, "https://example.com/");
+//! ```
+
+use std::ops::Range;
+use models::{SymbolEntity, TextRange, UrlEntity};
+use super::tld;
+
+fn is_invalid_url_preceding_char(c: char) -> bool {
+    match c {
+        'A'..='Z' | 'a'..='z' | '0'..='9' | '@' | '＠' | '$' | '#' | '＃' | '\u{202A}'..='\u{202E}' => true,
+        _ => false,
+    }
+}
+
+fn is_latin_accent(c: char) -> bool {
+    match c {
+        '\u{C0}'..='\u{D6}' | '\u{D8}'..='\u{F6}' | '\u{F8}'..='\u{24F}' |
+        '\u{300}'..='\u{36F}' | '\u{1E00}'..='\u{1EFF}' => true,
+        _ => false,
+    }
+}
+
+fn is_cyrillic(c: char) -> bool {
+    c >= '\u{400}' && c <= '\u{4FF}'
+}
+
+fn is_path_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || is_latin_accent(c) || is_cyrillic(c) || "!*';:=+,.$/%#[]-_~&|@\u{2013}".contains(c)
+}
+
+fn is_path_ending_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || is_latin_accent(c) || is_cyrillic(c) || "=_#/+-".contains(c)
+}
+
+fn is_query_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "!?*'();:&=+$/%#[]-_.,~|@".contains(c)
+}
+
+fn is_query_ending_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "_&=#/-".contains(c)
+}
+
+fn is_domain_char(c: char, allow_unicode: bool) -> bool {
+    c.is_ascii_alphanumeric() || c == '-' || c == '_' || (allow_unicode && !c.is_ascii() && c.is_alphanumeric())
+}
+
+fn next_char(text: &str, pos: usize) -> Option<char> {
+    text[pos..].chars().next()
+}
+
+/// Returns the end of the balanced parentheses starting at `pos`.
+fn balanced_parens_end(text: &str, pos: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in text[pos..].char_indices() {
+        match c {
+            '(' => {
+                depth += 1;
+                // Only one level of nesting is allowed
+                if depth > 2 { return None; }
+            }
+            ')' => {
+                depth -= 1;
+                if depth == 0 { return Some(pos + i + 1); }
+            }
+            _ if is_path_char(c) => (),
+            _ => return None,
+        }
+    }
+    None
+}
+
+/// Matches the domain starting at `pos` and returns the end of it and whether the TLD is a ccTLD
+/// without a subdomain.
+fn match_domain(text: &str, pos: usize, has_protocol: bool) -> Option<(usize, bool)> {
+    let mut labels = Vec::new();
+    let mut i = pos;
+
+    loop {
+        let start = i;
+        while let Some(c) = next_char(text, i) {
+            if !is_domain_char(c, has_protocol) { break; }
+            i += c.len_utf8();
+        }
+        if start == i { break; }
+        labels.push(start..i);
+
+        match (next_char(text, i), text.get(i + 1..).and_then(|x| x.chars().next())) {
+            (Some('.'), Some(c)) if is_domain_char(c, has_protocol) => i += 1,
+            _ => break,
+        }
+    }
+
+    // Find the longest domain which ends with a known TLD
+    for k in (2..labels.len() + 1).rev() {
+        let tld_range = labels[k - 1].clone();
+        let tld = text[tld_range.clone()].to_ascii_lowercase();
+        if !tld::is_valid_tld(&tld) { continue; }
+
+        match next_char(text, tld_range.end) {
+            Some(c) if c.is_ascii_alphanumeric() || "@+-_".contains(c) => continue,
+            _ => (),
+        }
+
+        let valid_labels = labels[..k].iter().all(|x| {
+            let label = &text[x.clone()];
+            !label.starts_with('-') && !label.ends_with('-')
+        });
+        if !valid_labels { continue; }
+
+        let short_cctld = k == 2 && tld::is_cctld(&tld)
+            && !text[labels[0].start..tld_range.end].eq_ignore_ascii_case("t.co");
+        return Some((tld_range.end, short_cctld));
+    }
+
+    None
+}
+
+/// Matches the path starting with `/` at `pos` and returns the end of it.
+fn match_path(text: &str, pos: usize) -> usize {
+    let mut i = pos + 1;
+    let mut end = i;
+
+    while let Some(c) = next_char(text, i) {
+        if c == '(' {
+            match balanced_parens_end(text, i) {
+                Some(x) => { i = x; end = x; continue; }
+                None => break,
+            }
+        }
+        if !is_path_char(c) { break; }
+        i += c.len_utf8();
+        if is_path_ending_char(c) { end = i; }
+    }
+
+    end
+}
+
+/// Matches the query starting with `?` at `pos` and returns the end of it.
+fn match_query(text: &str, pos: usize) -> usize {
+    let mut i = pos + 1;
+    let mut end = pos;
+
+    while let Some(c) = next_char(text, i) {
+        if !is_query_char(c) { break; }
+        i += c.len_utf8();
+        if is_query_ending_char(c) { end = i; }
+    }
+
+    end
+}
+
+fn match_url(text: &str, pos: usize) -> Option<usize> {
+    let preceding = text[..pos].chars().next_back();
+    if preceding.map_or(false, is_invalid_url_preceding_char) { return None; }
+
+    let protocol_len = ["https://", "http://"].iter()
+        .find(|p| text.get(pos..pos + p.len()).map_or(false, |x| x.eq_ignore_ascii_case(p)))
+        .map(|p| p.len());
+
+    if protocol_len.is_none() {
+        if preceding.map_or(false, |c| "-_./".contains(c)) { return None; }
+        if !next_char(text, pos).map_or(false, |c| c.is_ascii_alphanumeric()) { return None; }
+    }
+
+    let (mut end, short_cctld) = match match_domain(text, pos + protocol_len.unwrap_or(0), protocol_len.is_some()) {
+        Some(x) => x,
+        None => return None,
+    };
+    let domain_end = end;
+
+    if text[end..].starts_with(':') {
+        let digits = text[end + 1..].bytes().take_while(|x| x.is_ascii_digit()).count();
+        if digits > 0 { end += 1 + digits; }
+    }
+
+    if text[end..].starts_with('/') {
+        end = match_path(text, end);
+    }
+
+    if text[end..].starts_with('?') {
+        end = match_query(text, end);
+    }
+
+    // A domain with a ccTLD such as "example.jp" is not a URL unless it has the protocol or the path
+    if protocol_len.is_none() && short_cctld && end == domain_end {
+        return None;
+    }
+
+    Some(end)
+}
+
+/// Returns the byte ranges of the URLs in `text`, with or without the protocol.
+pub fn find_urls(text: &str) -> Vec<Range<usize>> {
+    let mut result = Vec::new();
+    let mut pos = 0;
+
+    while let Some(c) = next_char(text, pos) {
+        match match_url(text, pos) {
+            Some(end) => {
+                result.push(pos..end);
+                pos = end;
+            }
+            None => pos += c.len_utf8(),
+        }
+    }
+
+    result
+}
+
+fn is_hashtag_alpha(c: char) -> bool {
+    if c.is_ascii() {
+        return c.is_ascii_alphabetic();
+    }
+    c.is_alphabetic() || match c {
+        // Combining marks
+        '\u{300}'..='\u{36F}' | '\u{483}'..='\u{489}' | '\u{591}'..='\u{5C7}' | '\u{610}'..='\u{61A}' |
+        '\u{64B}'..='\u{65F}' | '\u{670}' | '\u{6D6}'..='\u{6ED}' | '\u{900}'..='\u{963}' |
+        '\u{966}'..='\u{DFF}' | '\u{E31}' | '\u{E34}'..='\u{E3A}' | '\u{E47}'..='\u{E4E}' |
+        '\u{1AB0}'..='\u{1AFF}' | '\u{1DC0}'..='\u{1DFF}' | '\u{20D0}'..='\u{20FF}' |
+        '\u{3099}'..='\u{309A}' | '\u{FE20}'..='\u{FE2F}' => true,
+        _ => false,
+    }
+}
+
+fn is_hashtag_char(c: char) -> bool {
+    is_hashtag_alpha(c) || c.is_numeric() || match c {
+        '_' | '\u{200C}' | '\u{200D}' | '\u{A67E}' | '\u{5BE}' | '\u{5F3}' | '\u{5F4}' | '\u{FF5E}' |
+        '\u{301C}' | '\u{309B}' | '\u{309C}' | '\u{30A0}' | '\u{30FB}' | '\u{3003}' | '\u{F0B}' |
+        '\u{F0C}' | '\u{B7}' => true,
+        _ => false,
+    }
+}
+
+fn is_mention_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+fn is_invalid_mention_preceding_char(c: char) -> bool {
+    is_mention_char(c) || "!#$%&*@＠".contains(c)
+}
+
+/// Matches the hashtag starting with `#` at `pos` and returns the end of it.
+fn match_hashtag(text: &str, pos: usize) -> Option<usize> {
+    match text[..pos].chars().next_back() {
+        Some('\u{FE0E}') | Some('\u{FE0F}') | None => (),
+        Some(c) if c == '&' || is_hashtag_char(c) => return None,
+        Some(_) => (),
+    }
+
+    let start = pos + next_char(text, pos).unwrap().len_utf8();
+    // "#" followed by these is a keycap emoji
+    match next_char(text, start) {
+        Some('\u{FE0F}') | Some('\u{20E3}') => return None,
+        _ => (),
+    }
+
+    let mut end = start;
+    let mut has_alpha = false;
+    while let Some(c) = next_char(text, end) {
+        if !is_hashtag_char(c) { break; }
+        has_alpha = has_alpha || is_hashtag_alpha(c);
+        end += c.len_utf8();
+    }
+
+    let rest = &text[end..];
+    if !has_alpha || rest.starts_with('#') || rest.starts_with('＃') || rest.starts_with("://") {
+        return None;
+    }
+
+    Some(end)
+}
+
+/// Matches the cashtag starting with `$` at `pos` and returns the end of it.
+fn match_cashtag(text: &str, pos: usize) -> Option<usize> {
+    if !text[..pos].chars().next_back().map_or(true, char::is_whitespace) {
+        return None;
+    }
+
+    let letters = |from: usize, max: usize| text[from..].bytes().take(max + 1).take_while(|x| x.is_ascii_alphabetic()).count();
+
+    let mut end = pos + 1;
+    let n = letters(end, 6);
+    if n == 0 || n > 6 { return None; }
+    end += n;
+
+    // Such as "$BRK.A"
+    if text[end..].starts_with('.') || text[end..].starts_with('_') {
+        let n = letters(end + 1, 2);
+        if n > 0 && n <= 2 { end += 1 + n; }
+    }
+
+    match next_char(text, end) {
+        Some(c) if !c.is_whitespace() && !c.is_ascii_punctuation() => None,
+        _ => Some(end),
+    }
+}
+
+/// Matches the mention starting with `@` at `pos` and returns the end of the screen name and the list.
+fn match_mention(text: &str, pos: usize) -> Option<(usize, usize)> {
+    if text[..pos].chars().next_back().map_or(false, is_invalid_mention_preceding_char) {
+        return None;
+    }
+
+    let start = pos + next_char(text, pos).unwrap().len_utf8();
+    let n = text[start..].chars().take(20).take_while(|&c| is_mention_char(c)).count();
+    if n == 0 { return None; }
+    let name_end = start + n;
+
+    let mut end = name_end;
+    if text[end..].starts_with('/') && next_char(text, end + 1).map_or(false, |c| c.is_ascii_alphabetic()) {
+        end += 2 + text[end + 2..].chars().take(24).take_while(|&c| is_mention_char(c) || c == '-').count();
+    }
+
+    let rest = &text[end..];
+    let invalid_end = rest.starts_with('@') || rest.starts_with('＠') || rest.starts_with("://")
+        || next_char(text, end).map_or(false, is_latin_accent);
+    if invalid_end { None } else { Some((name_end, end)) }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum EntityKind {
+    Url,
+    Hashtag,
+    Cashtag,
+    /// The end of the screen name
+    Mention(usize),
+}
+
+/// Finds all entities and removes the ones overlapping with the preceding entity.
+fn find_entities(text: &str) -> Vec<(EntityKind, Range<usize>)> {
+    let mut entities: Vec<_> = find_urls(text).into_iter().map(|x| (EntityKind::Url, x)).collect();
+
+    for (pos, c) in text.char_indices() {
+        let entity = match c {
+            '#' | '＃' => match_hashtag(text, pos).map(|end| (EntityKind::Hashtag, pos..end)),
+            '$' => match_cashtag(text, pos).map(|end| (EntityKind::Cashtag, pos..end)),
+            '@' | '＠' => match_mention(text, pos).map(|(name_end, end)| (EntityKind::Mention(name_end), pos..end)),
+            _ => None,
+        };
+        if let Some(x) = entity {
+            entities.push(x);
+        }
+    }
+
+    // URLs are preferred if the start is the same
+    entities.sort_by_key(|x| (x.1.start, x.0 != EntityKind::Url));
+
+    let mut result: Vec<(EntityKind, Range<usize>)> = Vec::with_capacity(entities.len());
+    for x in entities {
+        if result.last().map_or(true, |y| y.1.end <= x.1.start) {
+            result.push(x);
+        }
+    }
+    result
+}
+
+/// Converts a byte range to a range of UTF-16 code units, which `TextRange` uses.
+fn to_text_range(text: &str, range: &Range<usize>) -> TextRange {
+    let start = text[..range.start].encode_utf16().count();
+    let len = text[range.clone()].encode_utf16().count();
+    TextRange { start: start as i32, end: (start + len) as i32 }
+}
+
+fn symbol_entity(text: &str, range: &Range<usize>) -> SymbolEntity {
+    let sign_len = next_char(text, range.start).unwrap().len_utf8();
+    SymbolEntity {
+        indices: to_text_range(text, range),
+        text: text[range.start + sign_len..range.end].to_owned(),
+        #[cfg(feature = "extra-fields")]
+        extra: Default::default(),
+    }
+}
+
+/// A mention extracted from text. Unlike `UserMentionEntity`, the user ID and the name are unknown.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct MentionEntity {
+    pub indices: TextRange,
+    pub screen_name: String,
+    /// The slug of the list when the text is like `@user/list`.
+    pub list_slug: Option<String>,
+}
+
+/// The entities extracted from text.
+#[derive(Clone, Debug, Default)]
+pub struct ExtractedEntities {
+    pub hashtags: Vec<SymbolEntity>,
+    pub symbols: Vec<SymbolEntity>,
+    pub urls: Vec<UrlEntity>,
+    pub user_mentions: Vec<MentionEntity>,
+}
+
+/// Extracts all entities. The indices are in UTF-16 code units, as the server returns.
+pub fn extract_entities(text: &str) -> ExtractedEntities {
+    let mut result = ExtractedEntities::default();
+
+    for (kind, range) in find_entities(text) {
+        match kind {
+            EntityKind::Url => result.urls.push(UrlEntity {
+                display_url: None,
+                expanded_url: None,
+                indices: to_text_range(text, &range),
+                url: text[range].to_owned(),
+                #[cfg(feature = "extra-fields")]
+                extra: Default::default(),
+            }),
+            EntityKind::Hashtag => result.hashtags.push(symbol_entity(text, &range)),
+            EntityKind::Cashtag => result.symbols.push(symbol_entity(text, &range)),
+            EntityKind::Mention(name_end) => {
+                let name_start = range.start + next_char(text, range.start).unwrap().len_utf8();
+                result.user_mentions.push(MentionEntity {
+                    indices: to_text_range(text, &range),
+                    screen_name: text[name_start..name_end].to_owned(),
+                    list_slug: if name_end < range.end { Some(text[name_end + 1..range.end].to_owned()) } else { None },
+                });
+            }
+        }
+    }
+
+    result
+}
+
+pub fn extract_hashtags(text: &str) -> Vec<SymbolEntity> {
+    extract_entities(text).hashtags
+}
+
+pub fn extract_cashtags(text: &str) -> Vec<SymbolEntity> {
+    extract_entities(text).symbols
+}
+
+pub fn extract_urls(text: &str) -> Vec<UrlEntity> {
+    extract_entities(text).urls
+}
+
+pub fn extract_mentions(text: &str) -> Vec<MentionEntity> {
+    extract_entities(text).user_mentions
+}
+
+/// Returns the screen name if the text is a reply, which starts with a mention.
+pub fn extract_reply_screen_name(text: &str) -> Option<String> {
+    let trimmed = text.trim_start();
+    let offset = text.len() - trimmed.len();
+    match find_entities(text).into_iter().next() {
+        Some((EntityKind::Mention(name_end), ref range)) if range.start == offset && name_end == range.end => {
+            let name_start = range.start + next_char(text, range.start).unwrap().len_utf8();
+            Some(text[name_start..name_end].to_owned())
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn urls(text: &str) -> Vec<&str> {
+        find_urls(text).into_iter().map(|x| &text[x]).collect()
+    }
+
+    #[test]
+    fn find_urls_test() {
+        assert_eq!(urls("see https://example.com/a_(b)?q=1. and http://日本.jp"), vec!["https://example.com/a_(b)?q=1", "http://日本.jp"]);
+        assert_eq!(urls("www.example.com, bit.ly/abc! example.jp t.co"), vec!["www.example.com", "bit.ly/abc", "t.co"]);
+        assert_eq!(urls("user@example.com #example.com foo.qqq example.com:8080/x."), vec!["example.com:8080/x"]);
+        assert_eq!(urls("日本語example.com"), vec!["example.com"]);
+    }
+
+    #[test]
+    fn extract_entities_test() {
+        let text = "\u{1F600}#tag #123 a#b #日本語_1 ＃全角 http://example.com/#frag #\u{FE0F}\u{20E3}";
+        let hashtags: Vec<_> = extract_hashtags(text).into_iter().map(|x| (x.text, x.indices.start, x.indices.end)).collect();
+        assert_eq!(hashtags, vec![("tag".to_owned(), 2, 6), ("日本語_1".to_owned(), 16, 22), ("全角".to_owned(), 23, 26)]);
+
+        let cashtags: Vec<_> = extract_cashtags("$TWTR $BRK.A, a$B $toolong $12").into_iter().map(|x| x.text).collect();
+        assert_eq!(cashtags, vec!["TWTR", "BRK.A"]);
+
+        let mentions = extract_mentions("@a ＠b/list-1 a@c @d@ @e:// RT:@f");
+        let names: Vec<_> = mentions.iter().map(|x| (&x.screen_name[..], x.list_slug.as_ref().map(|x| &x[..]))).collect();
+        assert_eq!(names, vec![("a", None), ("b", Some("list-1")), ("f", None)]);
+        assert_eq!((mentions[1].indices.start, mentions[1].indices.end), (3, 12));
+
+        assert_eq!(extract_reply_screen_name("  @a hi"), Some("a".to_owned()));
+        assert_eq!(extract_reply_screen_name("hi @a"), None);
+        assert_eq!(extract_reply_screen_name("@a/list hi"), None);
+    }
+}
@@ -0,0 +1,273 @@
+//! Utilities for the text of tweets, compatible with [twitter-text](https://github.com/twitter/twitter-text).
+//!
+//! ```
+//! use tweetust::text;
+//!
+//! let result = text::validate("Hello, 世界! https://example.com/", &Default::default());
+//! assert_eq!(result.weighted_length, 36);
+//! assert_eq!(result.remaining, 244);
+//! assert!(result.is_valid());
+//! ```
+
+use std::ops::Range;
+use unicode_normalization::UnicodeNormalization;
+use models::Configuration;
+
+pub mod extractor;
+pub mod renderer;
+mod tld;
+
+/// The weight of the code points in `start..=end`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct WeightRange {
+    pub start: u32,
+    pub end: u32,
+    pub weight: usize,
+}
+
+/// The parameters of the weighted length. The default is the same as twitter-text v3.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TweetLengthConfig {
+    pub max_weighted_length: usize,
+    /// The weights are divided by this value.
+    pub scale: usize,
+    /// The weight of the code points not in `ranges`.
+    pub default_weight: usize,
+    /// The length of a URL after it is shortened by t.co.
+    pub transformed_url_length: usize,
+    pub ranges: Vec<WeightRange>,
+    /// Counts an emoji sequence as one character of `default_weight`.
+    pub emoji_parsing_enabled: bool,
+}
+
+impl Default for TweetLengthConfig {
+    fn default() -> TweetLengthConfig {
+        TweetLengthConfig {
+            max_weighted_length: 280,
+            scale: 100,
+            default_weight: 200,
+            transformed_url_length: 23,
+            ranges: vec![
+                WeightRange { start: 0, end: 4351, weight: 100 },
+                WeightRange { start: 8192, end: 8205, weight: 100 },
+                WeightRange { start: 8208, end: 8223, weight: 100 },
+                WeightRange { start: 8242, end: 8247, weight: 100 },
+            ],
+            emoji_parsing_enabled: true,
+        }
+    }
+}
+
+impl TweetLengthConfig {
+    /// Uses `short_url_length_https` of the response of `help().configuration()`.
+    pub fn from_configuration(config: &Configuration) -> TweetLengthConfig {
+        TweetLengthConfig {
+            transformed_url_length: config.short_url_length_https as usize,
+            .. Default::default()
+        }
+    }
+
+    fn weight(&self, c: char) -> usize {
+        let c = c as u32;
+        self.ranges.iter()
+            .find(|x| x.start <= c && c <= x.end)
+            .map_or(self.default_weight, |x| x.weight)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum TextErrorKind {
+    Empty,
+    /// The text after the range exceeds `max_weighted_length`.
+    TooLong,
+    /// U+FFFE, U+FEFF or U+FFFF, which the server refuses.
+    InvalidCharacter,
+}
+
+/// The part of the text which makes the tweet invalid.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct TextError {
+    pub kind: TextErrorKind,
+    /// The byte range in `TweetValidation::normalized_text`.
+    pub range: Range<usize>,
+}
+
+/// The result of `validate`.
+#[derive(Clone, Debug)]
+pub struct TweetValidation {
+    /// The NFC-normalized text, which the server counts. The ranges refer to this text.
+    pub normalized_text: String,
+    pub weighted_length: usize,
+    /// `max_weighted_length - weighted_length`, which is negative if the text is too long.
+    pub remaining: isize,
+    /// `weighted_length` per mille of `max_weighted_length`.
+    pub permillage: usize,
+    /// The byte range of the text within `max_weighted_length`.
+    pub valid_range: Range<usize>,
+    pub errors: Vec<TextError>,
+}
+
+impl TweetValidation {
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+fn is_invalid_char(c: char) -> bool {
+    match c {
+        '\u{FFFE}' | '\u{FEFF}' | '\u{FFFF}' => true,
+        _ => false,
+    }
+}
+
+fn is_regional_indicator(c: char) -> bool {
+    c >= '\u{1F1E6}' && c <= '\u{1F1FF}'
+}
+
+fn is_emoji_modifier(c: char) -> bool {
+    match c {
+        '\u{FE0F}' | '\u{1F3FB}'..='\u{1F3FF}' | '\u{E0020}'..='\u{E007F}' => true,
+        _ => false,
+    }
+}
+
+fn is_pictographic(c: char) -> bool {
+    match c {
+        '\u{203C}' | '\u{2049}' | '\u{2122}' | '\u{2139}' | '\u{2194}'..='\u{21AA}' |
+        '\u{231A}'..='\u{23FF}' | '\u{24C2}' | '\u{25AA}'..='\u{25FE}' | '\u{2600}'..='\u{27BF}' |
+        '\u{2934}' | '\u{2935}' | '\u{2B05}'..='\u{2B55}' | '\u{3030}' | '\u{303D}' | '\u{3297}' |
+        '\u{3299}' | '\u{1F000}'..='\u{1FAFF}' => true,
+        _ => false,
+    }
+}
+
+/// Returns the byte length of the emoji sequence at the beginning of `s`.
+fn emoji_len(s: &str) -> Option<usize> {
+    let mut chars = s.char_indices().peekable();
+    let first = match chars.next() {
+        Some((_, c)) => c,
+        None => return None,
+    };
+    let second = chars.peek().map(|x| x.1);
+
+    if is_regional_indicator(first) {
+        // A pair of regional indicators is a flag
+        return Some(match second {
+            Some(c) if is_regional_indicator(c) => first.len_utf8() + c.len_utf8(),
+            _ => first.len_utf8(),
+        });
+    }
+
+    match first {
+        '0'..='9' | '#' | '*' => {
+            // Keycap sequence
+            let rest = &s[1..];
+            let rest = if rest.starts_with('\u{FE0F}') { &rest['\u{FE0F}'.len_utf8()..] } else { rest };
+            return if rest.starts_with('\u{20E3}') { Some(s.len() - rest.len() + '\u{20E3}'.len_utf8()) } else { None };
+        }
+        // Text symbols are emoji only with the variation selector
+        '\u{A9}' | '\u{AE}' if second != Some('\u{FE0F}') => return None,
+        '\u{A9}' | '\u{AE}' => (),
+        c if is_pictographic(c) => (),
+        _ => return None,
+    }
+
+    let mut end = first.len_utf8();
+    loop {
+        match chars.next() {
+            Some((i, c)) if is_emoji_modifier(c) => end = i + c.len_utf8(),
+            Some((_, '\u{200D}')) => match chars.next() {
+                Some((i, c)) if is_pictographic(c) => end = i + c.len_utf8(),
+                _ => break,
+            },
+            _ => break,
+        }
+    }
+
+    Some(end)
+}
+
+/// Counts the weighted length of `text` and checks whether it can be posted as a tweet.
+pub fn validate(text: &str, config: &TweetLengthConfig) -> TweetValidation {
+    let text: String = text.nfc().collect();
+    let max_weight = config.max_weighted_length * config.scale;
+    let mut urls = extractor::find_urls(&text).into_iter().peekable();
+
+    let mut weight = 0;
+    let mut valid_end = 0;
+    let mut errors = Vec::new();
+    let mut pos = 0;
+
+    while let Some(c) = text[pos..].chars().next() {
+        let (next, w) = if urls.peek().map_or(false, |x| x.start == pos) {
+            (urls.next().unwrap().end, config.transformed_url_length * config.scale)
+        } else if let Some(len) = if config.emoji_parsing_enabled { emoji_len(&text[pos..]) } else { None } {
+            (pos + len, config.default_weight)
+        } else {
+            if is_invalid_char(c) {
+                errors.push(TextError { kind: TextErrorKind::InvalidCharacter, range: pos..pos + c.len_utf8() });
+            }
+            (pos + c.len_utf8(), config.weight(c))
+        };
+
+        weight += w;
+        if weight <= max_weight { valid_end = next; }
+        pos = next;
+    }
+
+    if text.is_empty() {
+        errors.push(TextError { kind: TextErrorKind::Empty, range: 0..0 });
+    }
+    if weight > max_weight {
+        errors.push(TextError { kind: TextErrorKind::TooLong, range: valid_end..text.len() });
+    }
+
+    let weighted_length = weight / config.scale;
+    TweetValidation {
+        weighted_length: weighted_length,
+        remaining: config.max_weighted_length as isize - weighted_length as isize,
+        permillage: weighted_length * 1000 / config.max_weighted_length,
+        valid_range: 0..valid_end,
+        errors: errors,
+        normalized_text: text,
+    }
+}
+
+/// Returns the weighted length of `text` with the default config.
+pub fn weighted_length(text: &str) -> usize {
+    validate(text, &Default::default()).weighted_length
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weighted_length_test() {
+        assert_eq!(weighted_length("abc"), 3);
+        assert_eq!(weighted_length("日本語"), 6);
+        assert_eq!(weighted_length("\u{2014}\u{2019}"), 2);
+        assert_eq!(weighted_length("\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}"), 2);
+        assert_eq!(weighted_length("\u{1F44D}\u{1F3FD} \u{1F1EF}\u{1F1F5} 1\u{FE0F}\u{20E3}"), 8);
+        assert_eq!(weighted_length("https://example.com/a/very/long/path/which/exceeds/23/characters"), 23);
+        assert_eq!(weighted_length("example.com example.jp"), 34);
+        // NFC turns "e" + U+0301 into "é"
+        assert_eq!(weighted_length("e\u{301}"), 1);
+    }
+
+    #[test]
+    fn validate_test() {
+        let config = TweetLengthConfig::default();
+        assert!(validate(&"a".repeat(280), &config).is_valid());
+
+        let text = format!("{}あい", "a".repeat(279));
+        let result = validate(&text, &config);
+        assert_eq!(result.remaining, -3);
+        assert_eq!(result.valid_range, 0..279);
+        assert_eq!(result.errors, vec![TextError { kind: TextErrorKind::TooLong, range: 279..text.len() }]);
+
+        let result = validate("a\u{FEFF}", &config);
+        assert_eq!(result.errors[0], TextError { kind: TextErrorKind::InvalidCharacter, range: 1..4 });
+        assert_eq!(validate("", &config).errors[0].kind, TextErrorKind::Empty);
+    }
+}
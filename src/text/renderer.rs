@@ -0,0 +1,424 @@
+//! Renders the text of tweets with the entities.
+//!
+//! ```
+//! use tweetust::models::Tweet;
+//! use tweetust::text::renderer::TweetRenderer;
+//!
+//! fn render(tweet: &Tweet) -> String {
+//!     TweetRenderer::from_tweet(tweet).to_html()
+//! }
+//! ```
+
+use std::fmt::Write;
+use url::percent_encoding::{utf8_percent_encode, DEFAULT_ENCODE_SET};
+use models::*;
+use super::extractor::{ExtractedEntities, MentionEntity};
+
+/// Converts each part of the text. Implement this trait to customize the output.
+///
+/// `text` is the part of the tweet text which the entity covers, e.g. `#Tag` or the t.co link.
+pub trait EntityFormatter {
+    /// Writes the text which is not an entity.
+    fn text(&self, out: &mut String, text: &str);
+
+    fn url(&self, out: &mut String, entity: &UrlEntity, text: &str) {
+        let url = entity.expanded_url.as_ref().or(entity.display_url.as_ref()).map_or(text, |x| &x[..]);
+        self.text(out, url)
+    }
+
+    fn hashtag(&self, out: &mut String, _entity: &SymbolEntity, text: &str) {
+        self.text(out, text)
+    }
+
+    fn cashtag(&self, out: &mut String, _entity: &SymbolEntity, text: &str) {
+        self.text(out, text)
+    }
+
+    fn mention(&self, out: &mut String, _entity: &UserMentionEntity, text: &str) {
+        self.text(out, text)
+    }
+
+    /// Writes the mention found by `extractor::extract_entities`, which may be a list like `@user/list`.
+    fn extracted_mention(&self, out: &mut String, _entity: &MentionEntity, text: &str) {
+        self.text(out, text)
+    }
+
+    /// Writes the link to the attached media. The default removes it.
+    fn media(&self, _out: &mut String, _entity: &MediaEntity, _text: &str) { }
+}
+
+fn escape_html(out: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+}
+
+fn escape_markdown(out: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '\\' | '`' | '*' | '_' | '[' | ']' | '(' | ')' | '#' | '<' | '>' | '~' | '|' => {
+                out.push('\\');
+                out.push(c);
+            }
+            _ => out.push(c),
+        }
+    }
+}
+
+fn percent_encode(text: &str) -> String {
+    utf8_percent_encode(text, DEFAULT_ENCODE_SET).to_string()
+}
+
+fn display_url(entity: &UrlEntity, text: &str) -> String {
+    entity.display_url.clone().unwrap_or_else(|| text.to_owned())
+}
+
+fn hashtag_url(entity: &SymbolEntity) -> String {
+    format!("https://twitter.com/hashtag/{}", percent_encode(&entity.text))
+}
+
+fn cashtag_url(entity: &SymbolEntity) -> String {
+    format!("https://twitter.com/search?q=%24{}", percent_encode(&entity.text))
+}
+
+fn mention_url(entity: &UserMentionEntity) -> String {
+    format!("https://twitter.com/{}", entity.screen_name)
+}
+
+fn extracted_mention_url(entity: &MentionEntity) -> String {
+    match entity.list_slug {
+        Some(ref slug) => format!("https://twitter.com/{}/lists/{}", entity.screen_name, percent_encode(slug)),
+        None => format!("https://twitter.com/{}", entity.screen_name),
+    }
+}
+
+/// Outputs HTML with links.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HtmlFormatter;
+
+impl HtmlFormatter {
+    fn link(&self, out: &mut String, href: &str, text: &str) {
+        out.push_str("<a href=\"");
+        escape_html(out, href);
+        out.push_str("\">");
+        escape_html(out, text);
+        out.push_str("</a>");
+    }
+}
+
+impl EntityFormatter for HtmlFormatter {
+    fn text(&self, out: &mut String, text: &str) {
+        escape_html(out, text)
+    }
+
+    fn url(&self, out: &mut String, entity: &UrlEntity, text: &str) {
+        self.link(out, entity.expanded_url.as_ref().map_or(&entity.url[..], |x| &x[..]), &display_url(entity, text))
+    }
+
+    fn hashtag(&self, out: &mut String, entity: &SymbolEntity, text: &str) {
+        self.link(out, &hashtag_url(entity), text)
+    }
+
+    fn cashtag(&self, out: &mut String, entity: &SymbolEntity, text: &str) {
+        self.link(out, &cashtag_url(entity), text)
+    }
+
+    fn mention(&self, out: &mut String, entity: &UserMentionEntity, text: &str) {
+        self.link(out, &mention_url(entity), text)
+    }
+
+    fn extracted_mention(&self, out: &mut String, entity: &MentionEntity, text: &str) {
+        self.link(out, &extracted_mention_url(entity), text)
+    }
+}
+
+/// Outputs Markdown with links.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MarkdownFormatter;
+
+impl MarkdownFormatter {
+    fn link(&self, out: &mut String, href: &str, text: &str) {
+        out.push('[');
+        escape_markdown(out, text);
+        write!(out, "](<{}>)", href.replace('<', "%3C").replace('>', "%3E")).unwrap();
+    }
+}
+
+impl EntityFormatter for MarkdownFormatter {
+    fn text(&self, out: &mut String, text: &str) {
+        escape_markdown(out, text)
+    }
+
+    fn url(&self, out: &mut String, entity: &UrlEntity, text: &str) {
+        self.link(out, entity.expanded_url.as_ref().map_or(&entity.url[..], |x| &x[..]), &display_url(entity, text))
+    }
+
+    fn hashtag(&self, out: &mut String, entity: &SymbolEntity, text: &str) {
+        self.link(out, &hashtag_url(entity), text)
+    }
+
+    fn cashtag(&self, out: &mut String, entity: &SymbolEntity, text: &str) {
+        self.link(out, &cashtag_url(entity), text)
+    }
+
+    fn mention(&self, out: &mut String, entity: &UserMentionEntity, text: &str) {
+        self.link(out, &mention_url(entity), text)
+    }
+
+    fn extracted_mention(&self, out: &mut String, entity: &MentionEntity, text: &str) {
+        self.link(out, &extracted_mention_url(entity), text)
+    }
+}
+
+/// Outputs the plain text replacing t.co links with the expanded URLs.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PlainTextFormatter;
+
+impl EntityFormatter for PlainTextFormatter {
+    fn text(&self, out: &mut String, text: &str) {
+        out.push_str(text)
+    }
+}
+
+enum Entity<'a> {
+    Url(&'a UrlEntity),
+    Hashtag(&'a SymbolEntity),
+    Cashtag(&'a SymbolEntity),
+    Mention(&'a UserMentionEntity),
+    ExtractedMention(&'a MentionEntity),
+    Media(&'a MediaEntity),
+}
+
+/// Applies `EntityFormatter` to the text of a tweet.
+///
+/// The text of a tweet is HTML-unescaped before rendering because the API escapes `&`, `<` and `>`
+/// while the indices of the entities are counted on the unescaped text.
+pub struct TweetRenderer<'a> {
+    text: &'a str,
+    html_escaped: bool,
+    display_text_range: Option<TextRange>,
+    entities: Option<&'a Entities>,
+    extended_entities: Option<&'a ExtendedEntities>,
+    extracted_entities: Option<&'a ExtractedEntities>,
+}
+
+impl<'a> TweetRenderer<'a> {
+    /// Renders `text` with `entities`.
+    pub fn new(text: &'a str, entities: Option<&'a Entities>) -> TweetRenderer<'a> {
+        TweetRenderer {
+            text: text,
+            html_escaped: true,
+            display_text_range: None,
+            entities: entities,
+            extended_entities: None,
+            extracted_entities: None,
+        }
+    }
+
+    /// Renders the text which is not escaped, e.g. a draft, with the result of `extractor::extract_entities`.
+    pub fn from_extracted(text: &'a str, entities: &'a ExtractedEntities) -> TweetRenderer<'a> {
+        TweetRenderer {
+            text: text,
+            html_escaped: false,
+            display_text_range: None,
+            entities: None,
+            extended_entities: None,
+            extracted_entities: Some(entities),
+        }
+    }
+
+    /// Uses `extended_tweet` if the tweet has it, otherwise `full_text` or `text`.
+    pub fn from_tweet(tweet: &'a Tweet) -> TweetRenderer<'a> {
+        if let Some(ref x) = tweet.extended_tweet {
+            return TweetRenderer::from_extended_tweet(x);
+        }
+
+        TweetRenderer {
+            text: tweet.full_text.as_ref().or(tweet.text.as_ref()).map_or("", |x| &x[..]),
+            html_escaped: true,
+            display_text_range: tweet.display_text_range,
+            entities: tweet.entities.as_ref().map(|x| &**x),
+            extended_entities: tweet.extended_entities.as_ref(),
+            extracted_entities: None,
+        }
+    }
+
+    pub fn from_extended_tweet(tweet: &'a CompatExtendedTweet) -> TweetRenderer<'a> {
+        TweetRenderer {
+            text: &tweet.full_text,
+            html_escaped: true,
+            display_text_range: Some(tweet.display_text_range),
+            entities: Some(&tweet.entities),
+            extended_entities: tweet.extended_entities.as_ref(),
+            extracted_entities: None,
+        }
+    }
+
+    /// Renders only this range. `None` renders the whole text including the leading mentions and the media links.
+    pub fn display_text_range(mut self, val: Option<TextRange>) -> Self {
+        self.display_text_range = val;
+        self
+    }
+
+    pub fn render<F: EntityFormatter + ?Sized>(&self, formatter: &F) -> String {
+        let text = if self.html_escaped {
+            self.text.replace("&lt;", "<").replace("&gt;", ">").replace("&amp;", "&")
+        } else {
+            self.text.to_owned()
+        };
+
+        // The byte offset of each UTF-16 index. The index in a surrogate pair points to the end of the character.
+        let mut offsets = Vec::with_capacity(text.len() + 1);
+        for (i, c) in text.char_indices() {
+            offsets.push(i);
+            if c.len_utf16() == 2 { offsets.push(i + c.len_utf8()); }
+        }
+        offsets.push(text.len());
+        let to_byte = |x: i32| if x < 0 { None } else { offsets.get(x as usize).cloned() };
+
+        let (start, end) = match self.display_text_range {
+            Some(x) => (to_byte(x.start).unwrap_or(0), to_byte(x.end).unwrap_or(text.len())),
+            None => (0, text.len()),
+        };
+
+        let mut entities = Vec::new();
+        if let Some(x) = self.entities {
+            entities.extend(x.urls.iter().map(|e| (e.indices, Entity::Url(e))));
+            entities.extend(x.hashtags.iter().map(|e| (e.indices, Entity::Hashtag(e))));
+            entities.extend(x.symbols.iter().map(|e| (e.indices, Entity::Cashtag(e))));
+            entities.extend(x.user_mentions.iter().map(|e| (e.indices, Entity::Mention(e))));
+            if let Some(ref media) = x.media {
+                entities.extend(media.iter().map(|e| (e.indices, Entity::Media(e))));
+            }
+        }
+        if let Some(x) = self.extended_entities {
+            entities.extend(x.media.iter().map(|e| (e.indices, Entity::Media(e))));
+        }
+        if let Some(x) = self.extracted_entities {
+            entities.extend(x.urls.iter().map(|e| (e.indices, Entity::Url(e))));
+            entities.extend(x.hashtags.iter().map(|e| (e.indices, Entity::Hashtag(e))));
+            entities.extend(x.symbols.iter().map(|e| (e.indices, Entity::Cashtag(e))));
+            entities.extend(x.user_mentions.iter().map(|e| (e.indices, Entity::ExtractedMention(e))));
+        }
+        entities.sort_by_key(|x| x.0.start);
+
+        let mut out = String::with_capacity(text.len());
+        let mut pos = start;
+        for (indices, entity) in entities {
+            let (s, e) = match (to_byte(indices.start), to_byte(indices.end)) {
+                (Some(s), Some(e)) => (s, e),
+                _ => continue,
+            };
+            // Skips the entities out of the range and the duplicated media
+            if s < pos || e > end || s >= e { continue; }
+
+            let text_start = out.len();
+            formatter.text(&mut out, &text[pos..s]);
+            let t = &text[s..e];
+            match entity {
+                Entity::Url(x) => formatter.url(&mut out, x, t),
+                Entity::Hashtag(x) => formatter.hashtag(&mut out, x, t),
+                Entity::Cashtag(x) => formatter.cashtag(&mut out, x, t),
+                Entity::Mention(x) => formatter.mention(&mut out, x, t),
+                Entity::ExtractedMention(x) => formatter.extracted_mention(&mut out, x, t),
+                Entity::Media(x) => {
+                    let len = out.len();
+                    formatter.media(&mut out, x, t);
+                    // Removes the space before the media link if the link is removed
+                    if out.len() == len {
+                        let trimmed_len = text_start + out[text_start..].trim_end().len();
+                        out.truncate(trimmed_len);
+                    }
+                }
+            }
+            pos = e;
+        }
+
+        if pos < end {
+            formatter.text(&mut out, &text[pos..end]);
+        }
+
+        out
+    }
+
+    pub fn to_html(&self) -> String {
+        self.render(&HtmlFormatter)
+    }
+
+    pub fn to_markdown(&self) -> String {
+        self.render(&MarkdownFormatter)
+    }
+
+    pub fn to_plain_text(&self) -> String {
+        self.render(&PlainTextFormatter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+    use models::CompatExtendedTweet;
+    use text::extractor::extract_entities;
+    use super::*;
+
+    #[test]
+    fn render_test() {
+        let tweet: CompatExtendedTweet = serde_json::from_str(r#"{
+            "full_text": "@bob 😀 &lt;b&gt; #Rust &amp; $TWTR t.co/a t.co/m",
+            "display_text_range": [5, 32],
+            "entities": {
+                "hashtags": [{"indices": [12, 17], "text": "Rust"}],
+                "symbols": [{"indices": [20, 25], "text": "TWTR"}],
+                "urls": [{"indices": [26, 32], "url": "t.co/a", "expanded_url": "https://example.com/?a=1&b=2", "display_url": "example.com/?a=1…"}],
+                "user_mentions": [{"indices": [0, 4], "id": 1, "name": "Bob", "screen_name": "bob"}]
+            }
+        }"#).unwrap();
+        let r = TweetRenderer::from_extended_tweet(&tweet);
+
+        assert_eq!(r.to_plain_text(), "\u{1F600} <b> #Rust & $TWTR https://example.com/?a=1&b=2");
+        assert_eq!(r.to_html(), "\u{1F600} &lt;b&gt; <a href=\"https://twitter.com/hashtag/Rust\">#Rust</a> &amp; \
+            <a href=\"https://twitter.com/search?q=%24TWTR\">$TWTR</a> \
+            <a href=\"https://example.com/?a=1&amp;b=2\">example.com/?a=1…</a>");
+        assert_eq!(r.to_markdown(), "\u{1F600} \\<b\\> [\\#Rust](<https://twitter.com/hashtag/Rust>) & \
+            [$TWTR](<https://twitter.com/search?q=%24TWTR>) [example.com/?a=1…](<https://example.com/?a=1&b=2>)");
+
+        let full = r.display_text_range(None).to_plain_text();
+        assert!(full.starts_with("@bob "));
+        assert!(full.ends_with(" t.co/m"));
+    }
+
+    #[test]
+    fn media_test() {
+        let tweet: CompatExtendedTweet = serde_json::from_str(r#"{
+            "full_text": "a t.co/m b  ",
+            "display_text_range": [0, 12],
+            "entities": {"hashtags": [], "symbols": [], "urls": [], "user_mentions": []},
+            "extended_entities": {"media": [{
+                "display_url": "pic.twitter.com/m", "expanded_url": "https://twitter.com/a/status/1/photo/1",
+                "id": 1, "indices": [2, 8], "media_url": "http://pbs.twimg.com/media/m.jpg", "media_url_https": "https://pbs.twimg.com/media/m.jpg",
+                "sizes": {}, "type": "photo", "url": "t.co/m"
+            }]}
+        }"#).unwrap();
+
+        // Only the space before the removed link is trimmed
+        assert_eq!(TweetRenderer::from_extended_tweet(&tweet).to_plain_text(), "a b  ");
+    }
+
+    #[test]
+    fn extracted_test() {
+        let text = "@bob/list-1 &lt; #Rust";
+        let entities = extract_entities(text);
+        let r = TweetRenderer::from_extracted(text, &entities);
+
+        assert_eq!(r.to_plain_text(), text);
+        assert_eq!(r.to_html(), "<a href=\"https://twitter.com/bob/lists/list-1\">@bob/list-1</a> &amp;lt; \
+            <a href=\"https://twitter.com/hashtag/Rust\">#Rust</a>");
+    }
+}
@@ -0,0 +1,56 @@
+//! The top-level domains which are recognized in URLs.
+
+/// The ASCII country code TLDs.
+pub const CCTLDS: &'static [&'static str] = &[
+    "ac", "ad", "ae", "af", "ag", "ai", "al", "am", "an", "ao", "aq", "ar", "as", "at", "au", "aw", "ax", "az",
+    "ba", "bb", "bd", "be", "bf", "bg", "bh", "bi", "bj", "bl", "bm", "bn", "bo", "bq", "br", "bs", "bt", "bv",
+    "bw", "by", "bz", "ca", "cc", "cd", "cf", "cg", "ch", "ci", "ck", "cl", "cm", "cn", "co", "cr", "cu", "cv",
+    "cw", "cx", "cy", "cz", "de", "dj", "dk", "dm", "do", "dz", "ec", "ee", "eg", "eh", "er", "es", "et", "eu",
+    "fi", "fj", "fk", "fm", "fo", "fr", "ga", "gb", "gd", "ge", "gf", "gg", "gh", "gi", "gl", "gm", "gn", "gp",
+    "gq", "gr", "gs", "gt", "gu", "gw", "gy", "hk", "hm", "hn", "hr", "ht", "hu", "id", "ie", "il", "im", "in",
+    "io", "iq", "ir", "is", "it", "je", "jm", "jo", "jp", "ke", "kg", "kh", "ki", "km", "kn", "kp", "kr", "kw",
+    "ky", "kz", "la", "lb", "lc", "li", "lk", "lr", "ls", "lt", "lu", "lv", "ly", "ma", "mc", "md", "me", "mf",
+    "mg", "mh", "mk", "ml", "mm", "mn", "mo", "mp", "mq", "mr", "ms", "mt", "mu", "mv", "mw", "mx", "my", "mz",
+    "na", "nc", "ne", "nf", "ng", "ni", "nl", "no", "np", "nr", "nu", "nz", "om", "pa", "pe", "pf", "pg", "ph",
+    "pk", "pl", "pm", "pn", "pr", "ps", "pt", "pw", "py", "qa", "re", "ro", "rs", "ru", "rw", "sa", "sb", "sc",
+    "sd", "se", "sg", "sh", "si", "sj", "sk", "sl", "sm", "sn", "so", "sr", "ss", "st", "su", "sv", "sx", "sy",
+    "sz", "tc", "td", "tf", "tg", "th", "tj", "tk", "tl", "tm", "tn", "to", "tp", "tr", "tt", "tv", "tw", "tz",
+    "ua", "ug", "uk", "um", "us", "uy", "uz", "va", "vc", "ve", "vg", "vi", "vn", "vu", "wf", "ws", "ye", "yt",
+    "za", "zm", "zw",
+];
+
+/// The generic TLDs. This is not the full list of the delegated TLDs but the ones which commonly appear in tweets.
+pub const GTLDS: &'static [&'static str] = &[
+    "aero", "agency", "app", "art", "asia", "audio", "auto", "band", "bank", "bar", "best", "bet", "bid", "bike",
+    "bio", "biz", "blog", "book", "boutique", "build", "business", "buzz", "cafe", "camera", "camp", "capital",
+    "care", "careers", "casa", "cash", "cat", "center", "chat", "cheap", "church", "city", "click", "cloud",
+    "club", "codes", "coffee", "college", "com", "community", "company", "computer", "consulting", "cool",
+    "coop", "design", "dev", "diet", "digital", "directory", "dog", "domains", "download", "earth", "eco",
+    "edu", "education", "email", "energy", "engineering", "enterprises", "equipment", "estate", "events",
+    "exchange", "expert", "express", "fail", "family", "fans", "farm", "fashion", "film", "finance", "fit",
+    "fitness", "flowers", "foo", "food", "football", "forsale", "foundation", "free", "fun", "fund", "game",
+    "games", "garden", "gay", "gift", "gifts", "global", "gmbh", "gold", "golf", "google", "gov", "graphics",
+    "green", "group", "guide", "guru", "health", "help", "hiphop", "holdings", "holiday", "homes", "horse",
+    "host", "hosting", "house", "how", "icu", "inc", "industries", "info", "ink", "institute", "int",
+    "international", "investments", "jobs", "kitchen", "land", "law", "lgbt", "life", "lighting", "limited",
+    "link", "live", "llc", "loan", "lol", "love", "ltd", "luxury", "management", "market", "marketing",
+    "media", "menu", "mil", "mobi", "moda", "moe", "money", "movie", "museum", "music", "name", "net",
+    "network", "news", "ninja", "nyc", "one", "online", "ooo", "org", "page", "paris", "partners", "party",
+    "photo", "photography", "photos", "pics", "pink", "pizza", "place", "plus", "poker", "porn", "post",
+    "press", "pro", "productions", "properties", "pub", "red", "rent", "rest", "restaurant", "review",
+    "reviews", "rocks", "run", "sale", "school", "science", "services", "sex", "sexy", "shop", "shopping",
+    "show", "site", "social", "software", "solutions", "space", "sport", "store", "stream", "studio", "style",
+    "sucks", "support", "systems", "tax", "team", "tech", "technology", "tel", "tips", "today", "tokyo",
+    "tools", "top", "tours", "town", "toys", "trade", "training", "travel", "tube", "university", "uno",
+    "vegas", "ventures", "video", "vip", "vision", "vote", "voyage", "wang", "watch", "web", "website",
+    "wedding", "wiki", "win", "wine", "work", "works", "world", "wtf", "xxx", "xyz", "yoga", "zone",
+];
+
+/// Returns true if `tld` is a known gTLD or ccTLD, or a punycode TLD. `tld` must be in lower case.
+pub fn is_valid_tld(tld: &str) -> bool {
+    tld.starts_with("xn--") || is_cctld(tld) || GTLDS.binary_search(&tld).is_ok()
+}
+
+pub fn is_cctld(tld: &str) -> bool {
+    CCTLDS.binary_search(&tld).is_ok()
+}